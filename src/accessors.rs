@@ -0,0 +1,316 @@
+use crate::core;
+use crate::map;
+
+use core::{Json, JsonError};
+use map::JsonMap;
+
+// 2^63 and 2^64, exclusive upper bounds for lossless integer conversions
+const I64_UPPER: f64 = 9_223_372_036_854_775_808.0;
+const U64_UPPER: f64 = 18_446_744_073_709_551_616.0;
+
+impl Json {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Json::JsonObject(_) => "object",
+            Json::JsonArray(_) => "array",
+            Json::JsonString(_) => "string",
+            Json::JsonNumber(_) => "number",
+            Json::JsonBoolean(_) => "boolean",
+            Json::JsonNull => "null",
+        }
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, Json::JsonObject(_))
+    }
+    pub fn is_array(&self) -> bool {
+        matches!(self, Json::JsonArray(_))
+    }
+    pub fn is_string(&self) -> bool {
+        matches!(self, Json::JsonString(_))
+    }
+    pub fn is_number(&self) -> bool {
+        matches!(self, Json::JsonNumber(_))
+    }
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Json::JsonBoolean(_))
+    }
+    pub fn is_null(&self) -> bool {
+        matches!(self, Json::JsonNull)
+    }
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::JsonString(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::JsonNumber(n) => Some(*n),
+            _ => None,
+        }
+    }
+    // Only integral numbers that fit without loss are returned
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::JsonNumber(n) if n.fract() == 0.0 && *n >= -I64_UPPER && *n < I64_UPPER => Some(*n as i64),
+            _ => None,
+        }
+    }
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::JsonNumber(n) if n.fract() == 0.0 && *n >= 0.0 && *n < U64_UPPER => Some(*n as u64),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::JsonBoolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::JsonArray(vec) => Some(vec),
+            _ => None,
+        }
+    }
+    pub fn as_object(&self) -> Option<&JsonMap> {
+        match self {
+            Json::JsonObject(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_str_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Json::JsonString(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_f64_mut(&mut self) -> Option<&mut f64> {
+        match self {
+            Json::JsonNumber(n) => Some(n),
+            _ => None,
+        }
+    }
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            Json::JsonBoolean(b) => Some(b),
+            _ => None,
+        }
+    }
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Json>> {
+        match self {
+            Json::JsonArray(vec) => Some(vec),
+            _ => None,
+        }
+    }
+    pub fn as_object_mut(&mut self) -> Option<&mut JsonMap> {
+        match self {
+            Json::JsonObject(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        self.as_object().and_then(|map| map.get(key))
+    }
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Json> {
+        self.as_object_mut().and_then(|map| map.get_mut(key))
+    }
+    pub fn get_index(&self, idx: usize) -> Option<&Json> {
+        self.as_array().and_then(|vec| vec.get(idx))
+    }
+    pub fn get_index_mut(&mut self, idx: usize) -> Option<&mut Json> {
+        self.as_array_mut().and_then(|vec| vec.get_mut(idx))
+    }
+}
+
+fn type_error(expected: &str, found: &Json) -> JsonError {
+    JsonError::TypeError(format!("Expected {} but found {}", expected, found.type_name()))
+}
+
+impl<'a> TryFrom<&'a Json> for &'a str {
+    type Error = JsonError;
+    fn try_from(json: &'a Json) -> Result<Self, Self::Error> {
+        json.as_str().ok_or_else(|| type_error("string", json))
+    }
+}
+
+impl TryFrom<&Json> for String {
+    type Error = JsonError;
+    fn try_from(json: &Json) -> Result<Self, Self::Error> {
+        json.as_str().map(str::to_string).ok_or_else(|| type_error("string", json))
+    }
+}
+
+impl TryFrom<&Json> for bool {
+    type Error = JsonError;
+    fn try_from(json: &Json) -> Result<Self, Self::Error> {
+        json.as_bool().ok_or_else(|| type_error("boolean", json))
+    }
+}
+
+impl TryFrom<&Json> for f64 {
+    type Error = JsonError;
+    fn try_from(json: &Json) -> Result<Self, Self::Error> {
+        json.as_f64().ok_or_else(|| type_error("number", json))
+    }
+}
+
+impl TryFrom<&Json> for f32 {
+    type Error = JsonError;
+    fn try_from(json: &Json) -> Result<Self, Self::Error> {
+        json.as_f64().map(|n| n as f32).ok_or_else(|| type_error("number", json))
+    }
+}
+
+macro_rules! impl_try_from_json_signed {
+    ($($t:ty),*) => {$(
+        impl TryFrom<&Json> for $t {
+            type Error = JsonError;
+            fn try_from(json: &Json) -> Result<Self, Self::Error> {
+                json.as_i64()
+                    .and_then(|n| <$t>::try_from(n).ok())
+                    .ok_or_else(|| type_error(stringify!($t), json))
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_try_from_json_unsigned {
+    ($($t:ty),*) => {$(
+        impl TryFrom<&Json> for $t {
+            type Error = JsonError;
+            fn try_from(json: &Json) -> Result<Self, Self::Error> {
+                json.as_u64()
+                    .and_then(|n| <$t>::try_from(n).ok())
+                    .ok_or_else(|| type_error(stringify!($t), json))
+            }
+        }
+    )*};
+}
+
+impl_try_from_json_signed!(i8, i16, i32, i64, isize);
+impl_try_from_json_unsigned!(u8, u16, u32, u64, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::parse;
+
+    fn sample() -> Json {
+        parse(r#"{"name": "Alice", "age": 30, "ratio": 0.5, "ok": true, "tags": ["a", "b"], "none": null}"#)
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn test_type_name() {
+        let json = sample();
+        assert_eq!(json.type_name(), "object");
+        assert_eq!(json.get("tags").unwrap().type_name(), "array");
+        assert_eq!(json.get("name").unwrap().type_name(), "string");
+        assert_eq!(json.get("age").unwrap().type_name(), "number");
+        assert_eq!(json.get("ok").unwrap().type_name(), "boolean");
+        assert_eq!(json.get("none").unwrap().type_name(), "null");
+    }
+
+    #[test]
+    fn test_predicates() {
+        let json = sample();
+        assert!(json.is_object());
+        assert!(json["tags"].is_array());
+        assert!(json["name"].is_string());
+        assert!(json["ratio"].is_number());
+        assert!(json["ok"].is_bool());
+        assert!(json["none"].is_null());
+        assert!(json["age"].is_i64());
+        assert!(!json["ratio"].is_i64());
+        assert!(!json["name"].is_number());
+    }
+
+    #[test]
+    fn test_as_scalars() {
+        let json = sample();
+        assert_eq!(json["name"].as_str(), Some("Alice"));
+        assert_eq!(json["age"].as_f64(), Some(30.0));
+        assert_eq!(json["age"].as_i64(), Some(30));
+        assert_eq!(json["age"].as_u64(), Some(30));
+        assert_eq!(json["ratio"].as_i64(), None);
+        assert_eq!(json["ok"].as_bool(), Some(true));
+        assert_eq!(json["name"].as_f64(), None);
+        assert_eq!(json["age"].as_str(), None);
+    }
+
+    #[test]
+    fn test_as_integer_bounds() {
+        assert_eq!(Json::JsonNumber(-1.0).as_u64(), None);
+        assert_eq!(Json::JsonNumber(-1.0).as_i64(), Some(-1));
+        assert_eq!(Json::JsonNumber(1e19).as_i64(), None);
+        assert_eq!(Json::JsonNumber(1e19).as_u64(), Some(10_000_000_000_000_000_000));
+        assert_eq!(Json::JsonNumber(1e20).as_u64(), None);
+        assert_eq!(Json::JsonNumber(f64::NAN).as_i64(), None);
+        assert_eq!(Json::JsonNumber(f64::INFINITY).as_u64(), None);
+    }
+
+    #[test]
+    fn test_as_containers() {
+        let json = sample();
+        assert_eq!(json["tags"].as_array().map(Vec::len), Some(2));
+        assert_eq!(json.as_object().map(JsonMap::len), Some(6));
+        assert!(json["tags"].as_object().is_none());
+        assert!(json.as_array().is_none());
+    }
+
+    #[test]
+    fn test_as_mut() {
+        let mut json = sample();
+        json.get_mut("name").unwrap().as_str_mut().unwrap().push_str(" Smith");
+        *json.get_mut("age").unwrap().as_f64_mut().unwrap() += 1.0;
+        *json.get_mut("ok").unwrap().as_bool_mut().unwrap() = false;
+        json.get_mut("tags").unwrap().as_array_mut().unwrap().push(Json::JsonNull);
+        json.as_object_mut().unwrap().remove("none");
+        assert_eq!(json["name"].as_str(), Some("Alice Smith"));
+        assert_eq!(json["age"].as_i64(), Some(31));
+        assert_eq!(json["ok"].as_bool(), Some(false));
+        assert_eq!(json["tags"].as_array().map(Vec::len), Some(3));
+        assert!(json.get("none").is_none());
+    }
+
+    #[test]
+    fn test_get() {
+        let mut json = sample();
+        assert_eq!(json.get("age"), Some(&Json::JsonNumber(30.0)));
+        assert_eq!(json.get("missing"), None);
+        assert_eq!(json["tags"].get_index(1), Some(&Json::JsonString("b".to_string())));
+        assert_eq!(json["tags"].get_index(2), None);
+        assert_eq!(json["tags"].get("a"), None);
+        assert_eq!(json.get_index(0), None);
+        *json.get_mut("tags").unwrap().get_index_mut(0).unwrap() = Json::JsonNull;
+        assert_eq!(json["tags"][0], Json::JsonNull);
+    }
+
+    #[test]
+    fn test_try_from() {
+        let json = sample();
+        assert_eq!(<&str>::try_from(json.get("name").unwrap()), Ok("Alice"));
+        assert_eq!(String::try_from(json.get("name").unwrap()), Ok("Alice".to_string()));
+        assert_eq!(bool::try_from(json.get("ok").unwrap()), Ok(true));
+        assert_eq!(f64::try_from(json.get("ratio").unwrap()), Ok(0.5));
+        assert_eq!(u8::try_from(json.get("age").unwrap()), Ok(30));
+        assert_eq!(i32::try_from(json.get("age").unwrap()), Ok(30));
+        assert!(u8::try_from(&Json::JsonNumber(256.0)).is_err());
+        assert!(i64::try_from(json.get("ratio").unwrap()).is_err());
+        let err = bool::try_from(json.get("name").unwrap()).unwrap_err();
+        assert_eq!(err, JsonError::TypeError("Expected boolean but found string".to_string()));
+    }
+}
@@ -1,43 +1,50 @@
 use std::ops::{Index, IndexMut};
 
 use crate::core;
-use crate::core::parse;
+use crate::core::parse_value;
 use crate::utils;
+use crate::options;
+use crate::lookup;
+use crate::intern;
 
 use core::{Json, JsonError};
-use utils::ignore_ws;
-
-pub fn parse_array(source: &str) -> Result<(Json, &str), JsonError> {
-    let mut s: &str = ignore_ws(source);
-    if !s.starts_with('[') {
+use utils::{ignore_ws_bytes, peek_char};
+use options::ParseOptions;
+use lookup::Lookup;
+use intern::Keys;
+
+pub fn parse_array<'a>(source: &'a [u8], options: &ParseOptions, depth: usize, keys: &mut Keys) -> Result<(Json, &'a [u8]), JsonError> {
+    let mut s: &[u8] = ignore_ws_bytes(source);
+    if !s.starts_with(b"[") {
         return Err(JsonError::SyntaxError("Expected '[' at start of array".to_string()));
     }
     s = &s[1..]; // consume '['
     let mut json: Vec<Json> = Vec::new();
 
-    s = ignore_ws(s);
-    if s.starts_with(']') {
+    s = ignore_ws_bytes(s);
+    if s.starts_with(b"]") {
         // Empty array
         s = &s[1..];
         return Ok((Json::JsonArray(json), s));
     }
 
     loop {
-        let (value, tail) = parse(s)?;
+        let (value, tail) = parse_value(s, options, depth, keys)?;
         json.push(value);
-        s = ignore_ws(tail);
+        s = ignore_ws_bytes(tail);
 
-        if s.starts_with(',') {
+        if s.starts_with(b",") {
             s = &s[1..];
-            s = ignore_ws(s);
-            if s.starts_with(']') {
+            s = ignore_ws_bytes(s);
+            if s.starts_with(b"]") {
                 // Trailing comma
                 return Err(JsonError::SyntaxError("JSON Array should not end in a comma".to_string()));
             }
-        } else if s.starts_with(']') {
+        } else if s.starts_with(b"]") {
             s = &s[1..];
             break;
         } else {
+            peek_char(s)?;
             return Err(JsonError::SyntaxError("JSON Array should separate values with commas".to_string()));
         }
     }
@@ -46,22 +53,16 @@ pub fn parse_array(source: &str) -> Result<(Json, &str), JsonError> {
 
 
 impl Index<usize> for Json {
-    type Output = Json;
+    type Output = Lookup;
     fn index(&self, idx: usize) -> &Self::Output {
-        match self {
-            Json::JsonArray(vec) => vec.get(idx).unwrap_or(&Json::JsonEmpty),
-            _ => &Json::JsonEmpty,
-        }
+        Lookup::new(self.get_index(idx))
     }
 }
 
 
 impl IndexMut<usize> for Json {
     fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
-        match self {
-            Json::JsonArray(vec) => vec.get_mut(idx).unwrap_or_else(|| panic!("Index out of bounds")),
-            _ => panic!("Not a JsonArray"),
-        }
+        Lookup::new_mut(self.get_index_mut(idx))
     }
 }
 
@@ -72,8 +73,8 @@ mod tests {
     #[test]
     fn test_parse_array_valid() {
         let valid = r#"[null, true, "hello", 1]"#;
-        let (arr, tail) = parse_array(valid).unwrap();
-        assert_eq!(tail, "");
+        let (arr, tail) = parse_array(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
+        assert_eq!(tail, b"");
         assert_eq!(arr[0], Json::JsonNull);
         assert_eq!(arr[1], Json::JsonBoolean(true));
         assert_eq!(arr[2], Json::JsonString("hello".to_string()));
@@ -83,8 +84,8 @@ mod tests {
     #[test]
     fn test_parse_array_empty() {
         let valid = r#"[]"#;
-        let (arr, tail) = parse_array(valid).unwrap();
-        assert_eq!(tail, "");
+        let (arr, tail) = parse_array(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
+        assert_eq!(tail, b"");
         // Should be an empty object
         if let Json::JsonArray(vec) = arr {
             assert!(vec.is_empty());
@@ -96,30 +97,30 @@ mod tests {
     #[test]
     fn test_parse_array_trailing_ws() {
         let valid = r#"[null]     "#;
-        let (obj, tail) = parse_array(valid).unwrap();
+        let (obj, tail) = parse_array(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
         assert_eq!(obj[0], Json::JsonNull);
-        assert_eq!(tail.trim(), "");
+        assert_eq!(tail.trim_ascii(), b"");
     }
 
     #[test]
     fn test_parse_array_missing_comma() {
         let invalid = r#"[null true]"#;
-        let result = parse_array(invalid);
+        let result = parse_array(invalid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None));
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_array_comma_at_end() {
         let invalid = r#"[null, true,]"#;
-        let result = parse_array(invalid);
+        let result = parse_array(invalid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None));
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_array_nested() {
         let valid = r#"[null, [true]]"#;
-        let (arr, tail) = parse_array(valid).unwrap();
-        assert_eq!(tail, "");
+        let (arr, tail) = parse_array(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
+        assert_eq!(tail, b"");
         assert_eq!(arr[0], Json::JsonNull);
         let inner = &arr[1];
         assert_eq!(inner[0], Json::JsonBoolean(true));
@@ -128,8 +129,8 @@ mod tests {
     #[test]
     fn test_parse_array_with_whitespace_and_newlines() {
         let valid = "[  null ,\n true ,\t\"hi\"  , 42 ]";
-        let (arr, tail) = parse_array(valid).unwrap();
-        assert_eq!(tail, "");
+        let (arr, tail) = parse_array(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
+        assert_eq!(tail, b"");
         assert_eq!(arr[0], Json::JsonNull);
         assert_eq!(arr[1], Json::JsonBoolean(true));
         assert_eq!(arr[2], Json::JsonString("hi".to_string()));
@@ -139,8 +140,8 @@ mod tests {
     #[test]
     fn test_parse_array_deeply_nested() {
         let valid = "[[[]], [null], [[true]]]";
-        let (arr, tail) = parse_array(valid).unwrap();
-        assert_eq!(tail, "");
+        let (arr, tail) = parse_array(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
+        assert_eq!(tail, b"");
         assert_eq!(arr[0][0], Json::JsonArray(vec![]));
         assert_eq!(arr[1][0], Json::JsonNull);
         assert_eq!(arr[2][0][0], Json::JsonBoolean(true));
@@ -149,7 +150,7 @@ mod tests {
     #[test]
     fn test_parse_array_only_commas() {
         let invalid = "[,]";
-        let result = parse_array(invalid);
+        let result = parse_array(invalid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None));
         assert!(result.is_err());
     }
 }
\ No newline at end of file
@@ -0,0 +1,147 @@
+use crate::core;
+
+use core::Json;
+
+// How binary data from CBOR or MessagePack, which JSON has no type for,
+// becomes a `Json` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BytesPolicy {
+    // URL-safe base64 without padding, as RFC 8949 section 6.1 suggests
+    #[default]
+    Base64Url,
+    Base64,
+    Hex,
+    // An array of numbers from 0 to 255
+    Array,
+    Reject,
+}
+
+// What to do with map keys that are not strings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyPolicy {
+    // Binary keys follow `BytesPolicy`; anything else becomes its JSON text,
+    // so the key 1 turns into "1"
+    #[default]
+    Stringify,
+    Reject,
+}
+
+// What to do with NaN and the infinities, which CBOR and MessagePack floats
+// can hold but JSON text cannot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFinitePolicy {
+    // Keep the number as it is; `dump` writes it as null
+    #[default]
+    Keep,
+    Null,
+    Reject,
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// None when the policy rejects binary data
+pub(crate) fn bytes_to_json(bytes: &[u8], policy: BytesPolicy) -> Option<Json> {
+    match policy {
+        BytesPolicy::Base64Url => Some(Json::JsonString(base64(bytes, BASE64_URL, false))),
+        BytesPolicy::Base64 => Some(Json::JsonString(base64(bytes, BASE64, true))),
+        BytesPolicy::Hex => Some(Json::JsonString(hex(bytes))),
+        BytesPolicy::Array => Some(Json::JsonArray(bytes.iter().map(|&b| Json::JsonNumber(b as f64)).collect())),
+        BytesPolicy::Reject => None,
+    }
+}
+
+// None when the policy rejects a non-finite number
+pub(crate) fn number_to_json(n: f64, policy: NonFinitePolicy) -> Option<Json> {
+    match policy {
+        _ if n.is_finite() => Some(Json::JsonNumber(n)),
+        NonFinitePolicy::Keep => Some(Json::JsonNumber(n)),
+        NonFinitePolicy::Null => Some(Json::JsonNull),
+        NonFinitePolicy::Reject => None,
+    }
+}
+
+// Whether `n` can be written as an integer. -0 is integral but only a float
+// keeps its sign.
+pub(crate) fn is_integer(n: f64) -> bool {
+    n.fract() == 0.0 && !(n == 0.0 && n.is_sign_negative())
+}
+
+fn base64(bytes: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(alphabet[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+        }
+        if pad {
+            out.extend(std::iter::repeat_n('=', 3 - chunk.len()));
+        }
+    }
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(DIGITS[(b >> 4) as usize] as char);
+        out.push(DIGITS[(b & 0xF) as usize] as char);
+    }
+    out
+}
+
+// Test vectors are written as hex strings
+#[cfg(test)]
+pub(crate) fn from_hex(s: &str) -> Vec<u8> {
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4648 section 10
+    #[test]
+    fn test_base64_vectors() {
+        let vectors = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+        for (input, expected) in vectors {
+            assert_eq!(base64(input.as_bytes(), BASE64, true), expected);
+            assert_eq!(base64(input.as_bytes(), BASE64_URL, false), expected.trim_end_matches('='));
+        }
+        assert_eq!(base64(&[0xFB, 0xFF], BASE64_URL, false), "-_8");
+        assert_eq!(hex(&[0x00, 0xAB, 0x7F]), "00ab7f");
+    }
+
+    #[test]
+    fn test_bytes_to_json() {
+        let bytes = [1, 2, 255];
+        assert_eq!(bytes_to_json(&bytes, BytesPolicy::Base64Url), Some(Json::JsonString("AQL_".to_string())));
+        assert_eq!(bytes_to_json(&bytes, BytesPolicy::Base64), Some(Json::JsonString("AQL/".to_string())));
+        assert_eq!(bytes_to_json(&bytes, BytesPolicy::Hex), Some(Json::JsonString("0102ff".to_string())));
+        assert_eq!(bytes_to_json(&bytes, BytesPolicy::Array), "[1, 2, 255]".parse().ok());
+        assert_eq!(bytes_to_json(&bytes, BytesPolicy::Reject), None);
+    }
+
+    #[test]
+    fn test_number_to_json() {
+        for policy in [NonFinitePolicy::Keep, NonFinitePolicy::Null, NonFinitePolicy::Reject] {
+            assert_eq!(number_to_json(-1.5, policy), Some(Json::JsonNumber(-1.5)));
+        }
+        assert_eq!(number_to_json(f64::INFINITY, NonFinitePolicy::Keep), Some(Json::JsonNumber(f64::INFINITY)));
+        assert_eq!(number_to_json(f64::NAN, NonFinitePolicy::Null), Some(Json::JsonNull));
+        assert_eq!(number_to_json(f64::NEG_INFINITY, NonFinitePolicy::Reject), None);
+    }
+
+    #[test]
+    fn test_is_integer() {
+        assert!(is_integer(0.0));
+        assert!(is_integer(-3.0));
+        assert!(is_integer(1e300));
+        assert!(!is_integer(-0.0));
+        assert!(!is_integer(0.5));
+        assert!(!is_integer(f64::INFINITY));
+        assert!(!is_integer(f64::NAN));
+        assert_eq!(from_hex("00ab7f"), [0x00, 0xAB, 0x7F]);
+    }
+}
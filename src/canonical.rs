@@ -0,0 +1,199 @@
+use crate::core;
+use crate::string;
+
+use core::{Json, JsonError};
+use string::dump_string;
+
+impl Json {
+    // RFC 8785 JSON Canonicalization Scheme: no whitespace, members sorted
+    // by the UTF-16 code units of their keys, ECMAScript number formatting
+    // and the minimal string escaping `dump` already uses. Equal values give
+    // identical bytes, so the result is fit for hashing and signing. Fails
+    // on infinite or NaN numbers, which JSON cannot represent.
+    pub fn to_canonical_string(&self) -> Result<String, JsonError> {
+        let mut out = String::new();
+        write_canonical(self, &mut out)?;
+        Ok(out)
+    }
+}
+
+fn write_canonical(json: &Json, out: &mut String) -> Result<(), JsonError> {
+    match json {
+        Json::JsonObject(map) => {
+            let mut members: Vec<(&str, &Json)> = map.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                dump_string(key, out);
+                out.push(':');
+                write_canonical(value, out)?;
+            }
+            out.push('}');
+        }
+        Json::JsonArray(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(value, out)?;
+            }
+            out.push(']');
+        }
+        Json::JsonString(s) => dump_string(s, out),
+        Json::JsonNumber(n) => out.push_str(&es_number(*n)?),
+        Json::JsonBoolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        Json::JsonNull => out.push_str("null"),
+    }
+    Ok(())
+}
+
+// ECMAScript Number::toString, which JCS mandates. `{:e}` finds how many
+// digits the shortest round-trip form needs, but rounds ties up where
+// ECMAScript picks the even digit; formatting again with that precision
+// rounds exactly, ties to even.
+fn es_number(n: f64) -> Result<String, JsonError> {
+    if !n.is_finite() {
+        return Err(JsonError::TypeError(format!("Cannot canonicalize non-finite number {}", n)));
+    }
+    if n == 0.0 {
+        // Negative zero included
+        return Ok("0".to_string());
+    }
+    let shortest = format!("{:e}", n.abs());
+    let precision = shortest.split_once('e').map_or(0, |(m, _)| m.len().saturating_sub(2));
+    let sci = format!("{:.*e}", precision, n.abs());
+    let (mantissa, exponent) = sci.split_once('e').expect("`{:e}` always has an exponent");
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let k = digits.len() as i32;
+    // Position of the decimal point relative to the digits
+    let point = exponent.parse::<i32>().expect("`{:e}` exponent is an integer") + 1;
+
+    let mut out = String::new();
+    if n < 0.0 {
+        out.push('-');
+    }
+    if k <= point && point <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (point - k) as usize));
+    } else if 0 < point && point <= 21 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', (-point) as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let e = point - 1;
+        out.push_str(&format!("e{}{}", if e < 0 { '-' } else { '+' }, e.abs()));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8785 appendix B
+    #[test]
+    fn test_es_number_rfc_vectors() {
+        let vectors = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in vectors {
+            assert_eq!(es_number(f64::from_bits(bits)).unwrap(), expected, "{:016x}", bits);
+        }
+        assert!(es_number(f64::from_bits(0x7fffffffffffffff)).is_err());
+        assert!(es_number(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_es_number_round_trips() {
+        let mut state: u64 = 0x9E3779B97F4A7C15;
+        for _ in 0..20000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let n = f64::from_bits(state);
+            if n.is_finite() {
+                assert_eq!(es_number(n).unwrap().parse::<f64>().unwrap(), n, "{:016x}", state);
+            }
+        }
+    }
+
+    // RFC 8785 section 3.2.2
+    #[test]
+    fn test_canonical_rfc_example() {
+        let source = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let json: Json = source.parse().unwrap();
+        assert_eq!(
+            json.to_canonical_string().unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    // RFC 8785 section 3.2.3
+    #[test]
+    fn test_canonical_sorts_by_utf16() {
+        let source = r#"{
+            "€": "Euro Sign",
+            "\r": "Carriage Return",
+            "דּ": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "😀": "Emoji: Grinning Face",
+            "\u0080": "Control",
+            "ö": "Latin Small Letter O With Diaeresis"
+        }"#;
+        let json: Json = source.parse().unwrap();
+        let keys: Vec<String> = json.to_canonical_string().unwrap()
+            .parse::<Json>().unwrap()
+            .as_object().unwrap()
+            .keys().map(str::to_string).collect();
+        assert_eq!(keys, ["\r", "1", "\u{80}", "ö", "€", "😀", "\u{FB33}"]);
+    }
+
+    #[test]
+    fn test_canonical_ignores_member_order() {
+        let a: Json = r#"{"b": [1.0, {"y": 1, "x": 2}], "a": "é"}"#.parse().unwrap();
+        let b: Json = r#"{"a":"é","b":[1,{"x":2,"y":1}]}"#.parse().unwrap();
+        assert_eq!(a.to_canonical_string().unwrap(), b.to_canonical_string().unwrap());
+        assert_eq!(b.to_canonical_string().unwrap(), r#"{"a":"é","b":[1,{"x":2,"y":1}]}"#);
+        assert!(Json::JsonArray(vec![Json::JsonNumber(f64::NAN)]).to_canonical_string().is_err());
+    }
+}
@@ -0,0 +1,586 @@
+use crate::core;
+use crate::map;
+use crate::options;
+use crate::binary;
+
+use core::{Json, JsonError};
+use map::JsonMap;
+use options::DEFAULT_MAX_DEPTH;
+use binary::{BytesPolicy, KeyPolicy, NonFinitePolicy, bytes_to_json, is_integer, number_to_json};
+
+// How CBOR (RFC 8949) data without a JSON counterpart is decoded. Numbers,
+// strings, arrays, maps, booleans and null map across directly; undefined
+// becomes null.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CborOptions {
+    // Deepest allowed nesting of arrays, maps and tags. Decoding is
+    // recursive, as with `ParseOptions::max_depth`.
+    pub max_depth: usize,
+    pub bytes: BytesPolicy,
+    pub tags: TagPolicy,
+    pub keys: KeyPolicy,
+    pub non_finite: NonFinitePolicy,
+}
+
+// What to do with a tag other than the ones with a natural mapping: bignums
+// (2, 3) become numbers, the base64url, base64 and base16 hints (21, 22, 23)
+// pick how the byte string they wrap is written, and the self-described CBOR
+// marker (55799) is dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagPolicy {
+    // Keep the tagged value and drop the tag
+    #[default]
+    Unwrap,
+    // Replace the value with `{"tag": <number>, "value": <value>}`
+    Wrap,
+    Reject,
+}
+
+impl CborOptions {
+    pub const fn new() -> Self {
+        CborOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            bytes: BytesPolicy::Base64Url,
+            tags: TagPolicy::Unwrap,
+            keys: KeyPolicy::Stringify,
+            non_finite: NonFinitePolicy::Keep,
+        }
+    }
+}
+
+impl Default for CborOptions {
+    fn default() -> Self {
+        CborOptions::new()
+    }
+}
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xFF;
+
+// 2^64, the first magnitude a CBOR integer cannot hold
+const TWO_POW_64: f64 = 18446744073709551616.0;
+
+// Encodes with the core deterministic encoding of RFC 8949 section 4.2.1:
+// definite lengths, the shortest argument for every head, map keys sorted by
+// their encoded bytes. Integral numbers are written as integers and all
+// others in the narrowest float that holds them exactly.
+pub fn to_cbor(json: &Json) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_value(json, &mut out);
+    out
+}
+
+fn write_head(major: u8, arg: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match arg {
+        0..24 => out.push(major | arg as u8),
+        24..0x100 => out.extend([major | 24, arg as u8]),
+        0x100..0x1_0000 => {
+            out.push(major | 25);
+            out.extend((arg as u16).to_be_bytes());
+        }
+        0x1_0000..0x1_0000_0000 => {
+            out.push(major | 26);
+            out.extend((arg as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend(arg.to_be_bytes());
+        }
+    }
+}
+
+fn write_value(json: &Json, out: &mut Vec<u8>) {
+    match json {
+        Json::JsonObject(map) => {
+            let mut members: Vec<(Vec<u8>, &Json)> = map
+                .iter()
+                .map(|(key, value)| {
+                    let mut encoded = Vec::with_capacity(key.len() + 9);
+                    write_text(key, &mut encoded);
+                    (encoded, value)
+                })
+                .collect();
+            members.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            write_head(MAP, members.len() as u64, out);
+            for (key, value) in members {
+                out.extend(key);
+                write_value(value, out);
+            }
+        }
+        Json::JsonArray(values) => {
+            write_head(ARRAY, values.len() as u64, out);
+            for value in values {
+                write_value(value, out);
+            }
+        }
+        Json::JsonString(s) => write_text(s, out),
+        Json::JsonNumber(n) => write_number(*n, out),
+        Json::JsonBoolean(b) => out.push(if *b { 0xF5 } else { 0xF4 }),
+        Json::JsonNull => out.push(0xF6),
+    }
+}
+
+fn write_text(s: &str, out: &mut Vec<u8>) {
+    write_head(TEXT, s.len() as u64, out);
+    out.extend(s.as_bytes());
+}
+
+fn write_number(n: f64, out: &mut Vec<u8>) {
+    let integral = is_integer(n);
+    if integral && (0.0..TWO_POW_64).contains(&n) {
+        return write_head(UNSIGNED, n as u64, out);
+    }
+    if integral && (-TWO_POW_64..0.0).contains(&n) {
+        // -1 - arg, computed without rounding; `as` saturates -2^64 to u64::MAX
+        let arg = if -n >= TWO_POW_64 { u64::MAX } else { (-n) as u64 - 1 };
+        return write_head(NEGATIVE, arg, out);
+    }
+    if n.is_nan() {
+        // The deterministic encoding's canonical NaN
+        return out.extend([0xF9, 0x7E, 0x00]);
+    }
+    let single = n as f32;
+    if single as f64 != n {
+        out.push(0xFB);
+        out.extend(n.to_bits().to_be_bytes());
+    } else if let Some(half) = f32_to_f16(single) {
+        out.push(0xF9);
+        out.extend(half.to_be_bytes());
+    } else {
+        out.push(0xFA);
+        out.extend(single.to_bits().to_be_bytes());
+    }
+}
+
+// The IEEE 754 half-precision bits of `x` when it has an exact one. NaN is
+// handled by the caller.
+fn f32_to_f16(x: f32) -> Option<u16> {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+    match exp {
+        0xFF => Some(sign | 0x7C00),
+        0 if mantissa == 0 => Some(sign),
+        // f32 subnormals are far below the smallest half
+        0 => None,
+        _ => {
+            let e = exp - 127;
+            if (-14..=15).contains(&e) && mantissa & 0x1FFF == 0 {
+                Some(sign | ((e + 15) as u16) << 10 | (mantissa >> 13) as u16)
+            } else if (-24..-14).contains(&e) {
+                // Half subnormal: the significand shifted down to units of 2^-24
+                let significand = 0x80_0000 | mantissa;
+                let shift = -e - 1;
+                (significand & ((1 << shift) - 1) == 0).then_some(sign | (significand >> shift) as u16)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn f16_to_f64(half: u16) -> f64 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exp = ((half >> 10) & 0x1F) as i32;
+    let mantissa = (half & 0x3FF) as f64;
+    match exp {
+        0 => sign * mantissa * 2f64.powi(-24),
+        0x1F if mantissa == 0.0 => sign * f64::INFINITY,
+        0x1F => f64::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f64.powi(exp - 15),
+    }
+}
+
+pub fn from_cbor(bytes: &[u8]) -> Result<Json, JsonError> {
+    from_cbor_with_options(bytes, &CborOptions::default())
+}
+
+// Decodes exactly one data item; bytes left after it are an error
+pub fn from_cbor_with_options(bytes: &[u8], options: &CborOptions) -> Result<Json, JsonError> {
+    let mut decoder = Decoder { bytes, pos: 0, options };
+    let value = decoder.value(0)?;
+    if decoder.pos < bytes.len() {
+        return Err(decoder.error(decoder.pos, "Unexpected trailing bytes after CBOR data item"));
+    }
+    Ok(value)
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    options: &'a CborOptions,
+}
+
+// The initial byte of a data item and its argument; None for indefinite length
+struct Head {
+    offset: usize,
+    major: u8,
+    info: u8,
+    arg: Option<u64>,
+}
+
+impl<'a> Decoder<'a> {
+    fn error(&self, offset: usize, message: &str) -> JsonError {
+        JsonError::EncodingError { offset, message: message.to_string() }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], JsonError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(self.error(self.bytes.len(), "Unexpected end of CBOR data"));
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn head(&mut self) -> Result<Head, JsonError> {
+        let offset = self.pos;
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1F);
+        let arg = match info {
+            0..24 => Some(info as u64),
+            24 => Some(self.take(1)?[0] as u64),
+            25 => Some(u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64),
+            26 => Some(u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64),
+            27 => Some(u64::from_be_bytes(self.take(8)?.try_into().unwrap())),
+            INDEFINITE if matches!(major, BYTES | TEXT | ARRAY | MAP | SIMPLE) => None,
+            _ => return Err(self.error(offset, "Reserved additional information in CBOR head")),
+        };
+        Ok(Head { offset, major, info, arg })
+    }
+
+    fn at_break(&self) -> bool {
+        self.bytes.get(self.pos) == Some(&BREAK)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, JsonError> {
+        let head = self.head()?;
+        match (head.major, head.arg) {
+            (UNSIGNED, Some(n)) => Ok(Json::JsonNumber(n as f64)),
+            (NEGATIVE, Some(n)) => Ok(Json::JsonNumber(-1.0 - n as f64)),
+            (BYTES, _) => {
+                let bytes = self.string(&head)?;
+                self.bytes_value(&bytes, self.options.bytes, head.offset)
+            }
+            (TEXT, _) => {
+                let bytes = self.string(&head)?;
+                // Chunks of an indefinite-length string are reported as a whole
+                let start = if head.arg.is_some() { self.pos - bytes.len() } else { head.offset };
+                String::from_utf8(bytes)
+                    .map(Json::JsonString)
+                    .map_err(|err| self.error(start + err.utf8_error().valid_up_to(), "Invalid UTF-8 in CBOR text string"))
+            }
+            (ARRAY, len) => {
+                let depth = self.enter(depth, head.offset)?;
+                let mut values = Vec::with_capacity(self.capacity(len));
+                while self.more(len, values.len())? {
+                    values.push(self.value(depth)?);
+                }
+                Ok(Json::JsonArray(values))
+            }
+            (MAP, len) => {
+                let depth = self.enter(depth, head.offset)?;
+                let mut map = JsonMap::with_capacity(self.capacity(len));
+                let mut count = 0;
+                while self.more(len, count)? {
+                    let key = self.key(depth)?;
+                    map.insert(key, self.value(depth)?);
+                    count += 1;
+                }
+                Ok(Json::JsonObject(map))
+            }
+            (TAG, Some(tag)) => {
+                let depth = self.enter(depth, head.offset)?;
+                self.tagged(tag, depth, head.offset)
+            }
+            (SIMPLE, _) => self.simple(&head),
+            _ => unreachable!("indefinite length is only accepted for the other major types"),
+        }
+    }
+
+    // Containers count against the nesting limit
+    fn enter(&self, depth: usize, offset: usize) -> Result<usize, JsonError> {
+        if depth >= self.options.max_depth {
+            return Err(self.error(offset, "Maximum nesting depth exceeded"));
+        }
+        Ok(depth + 1)
+    }
+
+    // A claimed length only reserves as much as the remaining bytes could hold
+    fn capacity(&self, len: Option<u64>) -> usize {
+        len.map_or(0, |n| n.min((self.bytes.len() - self.pos) as u64) as usize)
+    }
+
+    // Whether another item follows: up to `len` of them, or until a break
+    fn more(&mut self, len: Option<u64>, count: usize) -> Result<bool, JsonError> {
+        match len {
+            Some(n) => Ok((count as u64) < n),
+            None if self.at_break() => {
+                self.pos += 1;
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+
+    // The contents of a byte or text string, joining indefinite-length chunks
+    fn string(&mut self, head: &Head) -> Result<Vec<u8>, JsonError> {
+        match head.arg {
+            Some(len) => {
+                let len = usize::try_from(len).map_err(|_| self.error(head.offset, "CBOR string is too long"))?;
+                Ok(self.take(len)?.to_vec())
+            }
+            None => {
+                let mut bytes = Vec::new();
+                while !self.at_break() {
+                    let chunk = self.head()?;
+                    if chunk.major != head.major || chunk.arg.is_none() {
+                        return Err(self.error(chunk.offset, "Indefinite-length CBOR string chunks must be definite strings of the same type"));
+                    }
+                    bytes.extend(self.string(&chunk)?);
+                }
+                self.pos += 1;
+                Ok(bytes)
+            }
+        }
+    }
+
+    fn bytes_value(&self, bytes: &[u8], policy: BytesPolicy, offset: usize) -> Result<Json, JsonError> {
+        bytes_to_json(bytes, policy).ok_or_else(|| JsonError::TypeError(format!("CBOR byte string at offset {} has no JSON equivalent", offset)))
+    }
+
+    fn number(&self, n: f64, offset: usize) -> Result<Json, JsonError> {
+        number_to_json(n, self.options.non_finite).ok_or_else(|| JsonError::TypeError(format!("CBOR number at offset {} has no JSON equivalent", offset)))
+    }
+
+    fn key(&mut self, depth: usize) -> Result<String, JsonError> {
+        let offset = self.pos;
+        let key = self.value(depth)?;
+        match (key, self.options.keys) {
+            (Json::JsonString(s), _) if self.bytes[offset] >> 5 == TEXT => Ok(s),
+            (_, KeyPolicy::Reject) => Err(JsonError::TypeError(format!("CBOR map key at offset {} is not a text string", offset))),
+            (Json::JsonString(s), KeyPolicy::Stringify) => Ok(s),
+            (key, KeyPolicy::Stringify) => Ok(key.dump()),
+        }
+    }
+
+    fn tagged(&mut self, tag: u64, depth: usize, offset: usize) -> Result<Json, JsonError> {
+        match tag {
+            // Unsigned and negative bignums
+            2 | 3 => {
+                let head = self.head()?;
+                if head.major != BYTES {
+                    return Err(self.error(head.offset, "CBOR bignum must be a byte string"));
+                }
+                let magnitude = self.string(&head)?.iter().fold(0.0, |n, &b| n * 256.0 + b as f64);
+                // Past 2^1024 the magnitude is infinite
+                self.number(if tag == 2 { magnitude } else { -1.0 - magnitude }, offset)
+            }
+            // Expected conversion of the byte string inside
+            21..=23 if self.bytes.get(self.pos).is_some_and(|b| b >> 5 == BYTES) && self.options.bytes != BytesPolicy::Reject => {
+                let head = self.head()?;
+                let bytes = self.string(&head)?;
+                let policy = match tag {
+                    21 => BytesPolicy::Base64Url,
+                    22 => BytesPolicy::Base64,
+                    _ => BytesPolicy::Hex,
+                };
+                self.bytes_value(&bytes, policy, head.offset)
+            }
+            55799 => self.value(depth),
+            _ => match self.options.tags {
+                TagPolicy::Unwrap => self.value(depth),
+                TagPolicy::Wrap => {
+                    let value = self.value(depth)?;
+                    Ok(Json::from_iter([("tag", Json::JsonNumber(tag as f64)), ("value", value)]))
+                }
+                TagPolicy::Reject => Err(JsonError::TypeError(format!("CBOR tag {} at offset {} has no JSON equivalent", tag, offset))),
+            },
+        }
+    }
+
+    fn simple(&mut self, head: &Head) -> Result<Json, JsonError> {
+        match (head.info, head.arg) {
+            (20, _) => Ok(Json::JsonBoolean(false)),
+            (21, _) => Ok(Json::JsonBoolean(true)),
+            // null and undefined
+            (22 | 23, _) => Ok(Json::JsonNull),
+            (24, Some(n)) if n < 32 => Err(self.error(head.offset, "Simple value encoded in two bytes must be at least 32")),
+            (25, Some(n)) => self.number(f16_to_f64(n as u16), head.offset),
+            (26, Some(n)) => self.number(f32::from_bits(n as u32) as f64, head.offset),
+            (27, Some(n)) => self.number(f64::from_bits(n), head.offset),
+            (INDEFINITE, _) => Err(self.error(head.offset, "Unexpected CBOR break")),
+            _ => Err(JsonError::TypeError(format!("CBOR simple value at offset {} has no JSON equivalent", head.offset))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::from_hex;
+
+    fn parse(source: &str) -> Json {
+        source.parse().unwrap()
+    }
+
+    // RFC 8949 appendix A, for the items with a JSON equivalent
+    #[test]
+    fn test_rfc_examples() {
+        let examples = [
+            ("0", "00"), ("1", "01"), ("10", "0a"), ("23", "17"), ("24", "1818"), ("25", "1819"),
+            ("100", "1864"), ("1000", "1903e8"), ("1000000", "1a000f4240"), ("1000000000000", "1b000000e8d4a51000"),
+            ("-18446744073709551616", "3bffffffffffffffff"),
+            ("-1", "20"), ("-10", "29"), ("-100", "3863"), ("-1000", "3903e7"),
+            ("1.5", "f93e00"), ("100000", "1a000186a0"), ("3.4028234663852886e+38", "fa7f7fffff"),
+            ("1.0e+300", "fb7e37e43c8800759c"), ("5.960464477539063e-8", "f90001"), ("0.00006103515625", "f90400"),
+            ("-4.1", "fbc010666666666666"), ("false", "f4"), ("true", "f5"), ("null", "f6"),
+            (r#""""#, "60"), (r#""a""#, "6161"), (r#""IETF""#, "6449455446"), (r#""\"\\""#, "62225c"),
+            (r#""ü""#, "62c3bc"), (r#""水""#, "63e6b0b4"), (r#""𐅑""#, "64f0908591"),
+            ("[]", "80"), ("[1, 2, 3]", "83010203"), ("[1, [2, 3], [4, 5]]", "8301820203820405"),
+            ("{}", "a0"), (r#"{"a": 1, "b": [2, 3]}"#, "a26161016162820203"), (r#"["a", {"b": "c"}]"#, "826161a161626163"),
+        ];
+        for (json, cbor) in examples {
+            let json = parse(json);
+            assert_eq!(to_cbor(&json), from_hex(cbor), "{}", cbor);
+            assert_eq!(from_cbor(&from_hex(cbor)).unwrap(), json, "{}", cbor);
+        }
+        // 2^64 - 1 rounds to 2^64, which only a float holds
+        assert_eq!(from_cbor(&from_hex("1bffffffffffffffff")).unwrap(), Json::JsonNumber(18446744073709551615.0));
+        assert_eq!(to_cbor(&Json::JsonNumber(18446744073709551615.0)), from_hex("fa5f800000"));
+        assert_eq!(to_cbor(&Json::JsonNumber(-0.0)), from_hex("f98000"));
+        assert_eq!(to_cbor(&Json::JsonNumber(f64::INFINITY)), from_hex("f97c00"));
+        assert_eq!(to_cbor(&Json::JsonNumber(f64::NEG_INFINITY)), from_hex("f9fc00"));
+        assert_eq!(to_cbor(&Json::JsonNumber(f64::NAN)), from_hex("f97e00"));
+        assert!(from_cbor(&from_hex("fa7fc00000")).unwrap().as_f64().unwrap().is_nan());
+        assert_eq!(from_cbor(&from_hex("fb3ff199999999999a")).unwrap(), Json::JsonNumber(1.1));
+        assert_eq!(from_cbor(&from_hex("f7")).unwrap(), Json::JsonNull);
+    }
+
+    #[test]
+    fn test_half_floats_are_exact() {
+        for bits in 0..=u16::MAX {
+            let value = f16_to_f64(bits);
+            if !value.is_nan() {
+                assert_eq!(f32_to_f16(value as f32), Some(bits), "{:04x}", bits);
+            }
+        }
+        assert_eq!(f32_to_f16(1.0 + f32::EPSILON), None);
+        assert_eq!(f32_to_f16(65520.0), None);
+        assert_eq!(f32_to_f16(2f32.powi(-25)), None);
+    }
+
+    #[test]
+    fn test_deterministic_map_order() {
+        let json = parse(r#"{"bb": 1, "a": 2, "aa": 3, "b": 4}"#);
+        assert_eq!(to_cbor(&json), from_hex("a46161026162046261610362626201"));
+        let reordered = parse(r#"{"b": 4, "aa": 3, "a": 2, "bb": 1}"#);
+        assert_eq!(to_cbor(&json), to_cbor(&reordered));
+    }
+
+    #[test]
+    fn test_indefinite_lengths() {
+        let examples = [
+            ("5f42010243030405ff", r#""AQIDBAU""#),
+            ("7f657374726561646d696e67ff", r#""streaming""#),
+            ("9fff", "[]"),
+            ("9f018202039f0405ffff", "[1, [2, 3], [4, 5]]"),
+            ("9f01820203820405ff", "[1, [2, 3], [4, 5]]"),
+            ("83018202039f0405ff", "[1, [2, 3], [4, 5]]"),
+            ("bf61610161629f0203ffff", r#"{"a": 1, "b": [2, 3]}"#),
+            ("826161bf61626163ff", r#"["a", {"b": "c"}]"#),
+            ("bf6346756ef563416d7421ff", r#"{"Fun": true, "Amt": -2}"#),
+        ];
+        for (cbor, json) in examples {
+            assert_eq!(from_cbor(&from_hex(cbor)).unwrap(), parse(json), "{}", cbor);
+        }
+    }
+
+    #[test]
+    fn test_policies() {
+        let bytes = from_hex("4401020304");
+        assert_eq!(from_cbor(&bytes).unwrap(), parse(r#""AQIDBA""#));
+        let options = CborOptions { bytes: BytesPolicy::Hex, ..CborOptions::default() };
+        assert_eq!(from_cbor_with_options(&bytes, &options).unwrap(), parse(r#""01020304""#));
+        let options = CborOptions { bytes: BytesPolicy::Reject, ..CborOptions::default() };
+        assert!(matches!(from_cbor_with_options(&bytes, &options), Err(JsonError::TypeError(_))));
+        // base16 hint
+        assert_eq!(from_cbor(&from_hex("d74401020304")).unwrap(), parse(r#""01020304""#));
+
+        // Epoch date, bignum and negative bignum
+        let tagged = from_hex("c11a514b67b0");
+        assert_eq!(from_cbor(&tagged).unwrap(), Json::JsonNumber(1363896240.0));
+        let options = CborOptions { tags: TagPolicy::Wrap, ..CborOptions::default() };
+        assert_eq!(from_cbor_with_options(&tagged, &options).unwrap(), parse(r#"{"tag": 1, "value": 1363896240}"#));
+        let options = CborOptions { tags: TagPolicy::Reject, ..CborOptions::default() };
+        assert!(from_cbor_with_options(&tagged, &options).is_err());
+        assert_eq!(from_cbor(&from_hex("c249010000000000000000")).unwrap(), Json::JsonNumber(18446744073709551616.0));
+        assert_eq!(from_cbor(&from_hex("c349010000000000000000")).unwrap(), Json::JsonNumber(-18446744073709551617.0));
+        assert_eq!(from_cbor_with_options(&from_hex("d9d9f780"), &options).unwrap(), parse("[]"));
+
+        // {1: 2, 3: 4} and {h'01': true, [1]: null}
+        let map = from_hex("a201020304");
+        assert_eq!(from_cbor(&map).unwrap(), parse(r#"{"1": 2, "3": 4}"#));
+        assert_eq!(from_cbor(&from_hex("a24101f58101f6")).unwrap(), parse(r#"{"AQ": true, "[1]": null}"#));
+        let options = CborOptions { keys: KeyPolicy::Reject, ..CborOptions::default() };
+        assert!(matches!(from_cbor_with_options(&map, &options), Err(JsonError::TypeError(_))));
+
+        // [Infinity, NaN] and a negative bignum past the largest double
+        let non_finite = from_hex("82f97c00fb7ff8000000000000");
+        let huge = from_hex(&format!("c35881{}", "ff".repeat(129)));
+        let values = from_cbor(&non_finite).unwrap();
+        assert_eq!(values[0], Json::JsonNumber(f64::INFINITY));
+        assert!(values[1].as_f64().unwrap().is_nan());
+        assert_eq!(from_cbor(&huge).unwrap(), Json::JsonNumber(f64::NEG_INFINITY));
+        let options = CborOptions { non_finite: NonFinitePolicy::Null, ..CborOptions::default() };
+        assert_eq!(from_cbor_with_options(&non_finite, &options).unwrap(), parse("[null, null]"));
+        assert_eq!(from_cbor_with_options(&huge, &options).unwrap(), Json::JsonNull);
+        let options = CborOptions { non_finite: NonFinitePolicy::Reject, ..CborOptions::default() };
+        assert_eq!(from_cbor_with_options(&non_finite, &options), Err(JsonError::TypeError("CBOR number at offset 1 has no JSON equivalent".to_string())));
+        assert!(matches!(from_cbor_with_options(&huge, &options), Err(JsonError::TypeError(_))));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let malformed = [
+            ("", 0), ("18", 1), ("62c3", 2), ("62c328", 1), ("7f62c328ff", 0), ("1c", 0), ("ff", 0), ("9f01", 2), ("5f6161ff", 1),
+            ("f818", 0), ("0001", 1), ("bf0001", 3), ("7f5f", 1),
+        ];
+        for (cbor, offset) in malformed {
+            match from_cbor(&from_hex(cbor)) {
+                Err(JsonError::EncodingError { offset: at, .. }) => assert_eq!(at, offset, "{}", cbor),
+                other => panic!("{}: expected an encoding error, got {:?}", cbor, other),
+            }
+        }
+        assert!(matches!(from_cbor(&from_hex("e0")), Err(JsonError::TypeError(_))));
+        // Claims 2^32 elements in four bytes
+        assert!(from_cbor(&from_hex("9b0000000100000000")).is_err());
+        let nested = [vec![0x81; 200], vec![0x00]].concat();
+        assert!(from_cbor(&nested).is_err());
+        let options = CborOptions { max_depth: 300, ..CborOptions::default() };
+        assert!(from_cbor_with_options(&nested, &options).is_ok());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = parse(r#"{
+            "id": 9007199254740993, "neg": -42, "ratio": 0.1, "small": 1.5e-10, "big": 1e300,
+            "name": "sensor é😀", "tags": ["a", "", null, true, false],
+            "nested": {"deep": [[], {}, [-0.0, 65504, 65505, 3.0e38]]}
+        }"#);
+        let cbor = to_cbor(&json);
+        assert_eq!(from_cbor(&cbor).unwrap(), json);
+        assert_eq!(to_cbor(&from_cbor(&cbor).unwrap()), cbor);
+    }
+}
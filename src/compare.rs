@@ -0,0 +1,244 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::core;
+use crate::map;
+
+use core::Json;
+use map::JsonMap;
+
+// Total order over values: null < booleans < numbers < strings < arrays <
+// objects. Numbers compare by value with -0 equal to 0 and NaN equal to
+// itself and above every other number, strings by code point, arrays
+// element by element, and objects as their members sorted by key. Equality
+// and hashing agree with it, so values can be map and set keys.
+fn rank(json: &Json) -> u8 {
+    match json {
+        Json::JsonNull => 0,
+        Json::JsonBoolean(_) => 1,
+        Json::JsonNumber(_) => 2,
+        Json::JsonString(_) => 3,
+        Json::JsonArray(_) => 4,
+        Json::JsonObject(_) => 5,
+    }
+}
+
+fn cmp_numbers(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.partial_cmp(&b).expect("neither number is NaN"),
+    }
+}
+
+// One bit pattern per equivalence class of `cmp_numbers`
+fn number_bits(n: f64) -> u64 {
+    if n.is_nan() {
+        f64::NAN.to_bits()
+    } else if n == 0.0 {
+        0
+    } else {
+        n.to_bits()
+    }
+}
+
+fn sorted_members(map: &JsonMap) -> Vec<(&str, &Json)> {
+    let mut members: Vec<(&str, &Json)> = map.iter().collect();
+    members.sort_unstable_by_key(|(key, _)| *key);
+    members
+}
+
+impl PartialEq for Json {
+    fn eq(&self, other: &Json) -> bool {
+        match (self, other) {
+            (Json::JsonObject(a), Json::JsonObject(b)) => a == b,
+            (Json::JsonArray(a), Json::JsonArray(b)) => a == b,
+            (Json::JsonString(a), Json::JsonString(b)) => a == b,
+            (Json::JsonNumber(a), Json::JsonNumber(b)) => cmp_numbers(*a, *b) == Ordering::Equal,
+            (Json::JsonBoolean(a), Json::JsonBoolean(b)) => a == b,
+            (Json::JsonNull, Json::JsonNull) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Json {}
+
+impl Ord for Json {
+    fn cmp(&self, other: &Json) -> Ordering {
+        match (self, other) {
+            (Json::JsonObject(a), Json::JsonObject(b)) => a.cmp(b),
+            (Json::JsonArray(a), Json::JsonArray(b)) => a.cmp(b),
+            (Json::JsonString(a), Json::JsonString(b)) => a.cmp(b),
+            (Json::JsonNumber(a), Json::JsonNumber(b)) => cmp_numbers(*a, *b),
+            (Json::JsonBoolean(a), Json::JsonBoolean(b)) => a.cmp(b),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+impl PartialOrd for Json {
+    fn partial_cmp(&self, other: &Json) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Json {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u8(rank(self));
+        match self {
+            Json::JsonObject(map) => map.hash(state),
+            Json::JsonArray(values) => values.hash(state),
+            Json::JsonString(s) => s.hash(state),
+            Json::JsonNumber(n) => state.write_u64(number_bits(*n)),
+            Json::JsonBoolean(b) => b.hash(state),
+            Json::JsonNull => {}
+        }
+    }
+}
+
+impl Eq for JsonMap {}
+
+impl Ord for JsonMap {
+    fn cmp(&self, other: &JsonMap) -> Ordering {
+        sorted_members(self).cmp(&sorted_members(other))
+    }
+}
+
+impl PartialOrd for JsonMap {
+    fn partial_cmp(&self, other: &JsonMap) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for JsonMap {
+    // Members are hashed on their own and summed, which ignores their
+    // order without sorting
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut sum: u64 = 0;
+        for member in self.iter() {
+            let mut hasher = DefaultHasher::new();
+            member.hash(&mut hasher);
+            sum = sum.wrapping_add(hasher.finish());
+        }
+        state.write_usize(self.len());
+        state.write_u64(sum);
+    }
+}
+
+impl Json {
+    // Structural equality where two numbers match when they differ by at
+    // most `epsilon`
+    pub fn approx_eq(&self, other: &Json, epsilon: f64) -> bool {
+        self.eq_by(other, &|a, b| a == b || (a - b).abs() <= epsilon)
+    }
+
+    // Structural equality where two numbers match when they differ by at
+    // most `max_relative` times the larger magnitude
+    pub fn approx_eq_relative(&self, other: &Json, max_relative: f64) -> bool {
+        self.eq_by(other, &|a, b| a == b || (a - b).abs() <= max_relative * a.abs().max(b.abs()))
+    }
+
+    fn eq_by(&self, other: &Json, numbers_match: &dyn Fn(f64, f64) -> bool) -> bool {
+        match (self, other) {
+            (Json::JsonNumber(a), Json::JsonNumber(b)) => (a.is_nan() && b.is_nan()) || numbers_match(*a, *b),
+            (Json::JsonArray(a), Json::JsonArray(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.eq_by(y, numbers_match))
+            }
+            (Json::JsonObject(a), Json::JsonObject(b)) => {
+                a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|w| v.eq_by(w, numbers_match)))
+            }
+            _ => self == other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, HashSet};
+
+    fn hash_of(json: &Json) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        json.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn parse(source: &str) -> Json {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn test_total_order_across_types() {
+        let ordered = [
+            Json::JsonNull,
+            Json::JsonBoolean(false),
+            Json::JsonBoolean(true),
+            Json::JsonNumber(f64::NEG_INFINITY),
+            Json::JsonNumber(-1.0),
+            Json::JsonNumber(2.5),
+            Json::JsonNumber(f64::INFINITY),
+            Json::JsonNumber(f64::NAN),
+            parse(r#""""#),
+            parse(r#""a""#),
+            parse(r#""b""#),
+            parse("[]"),
+            parse("[1]"),
+            parse("[1, 2]"),
+            parse("[2]"),
+            parse("{}"),
+            parse(r#"{"a": 1}"#),
+            parse(r#"{"a": 1, "b": 0}"#),
+            parse(r#"{"a": 2}"#),
+            parse(r#"{"b": 0}"#),
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(a.cmp(b), i.cmp(&j), "{:?} vs {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_eq_and_hash_agree() {
+        let pairs = [
+            (Json::JsonNumber(f64::NAN), Json::JsonNumber(-f64::NAN)),
+            (Json::JsonNumber(0.0), Json::JsonNumber(-0.0)),
+            (parse(r#"{"a": 1, "b": [true, null]}"#), parse(r#"{"b": [true, null], "a": 1.0}"#)),
+        ];
+        for (a, b) in &pairs {
+            assert_eq!(a, b);
+            assert_eq!(a.cmp(b), Ordering::Equal);
+            assert_eq!(hash_of(a), hash_of(b));
+        }
+        assert_ne!(parse(r#"{"a": 1}"#), parse(r#"{"a": 1, "b": 1}"#));
+        assert_ne!(hash_of(&parse("[1, 2]")), hash_of(&parse("[2, 1]")));
+    }
+
+    #[test]
+    fn test_collections() {
+        let records = [r#"{"id": 1, "t": "x"}"#, r#"{"t": "x", "id": 1}"#, r#"{"id": 2}"#, "[0]", "null"];
+        let set: HashSet<Json> = records.iter().map(|s| parse(s)).collect();
+        assert_eq!(set.len(), 4);
+        let sorted: Vec<Json> = records.iter().map(|s| parse(s)).collect::<BTreeSet<_>>().into_iter().collect();
+        assert_eq!(sorted, [parse("null"), parse("[0]"), parse(r#"{"id": 1, "t": "x"}"#), parse(r#"{"id": 2}"#)]);
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let a = parse(r#"{"x": [0.1, 1000000], "y": "s"}"#);
+        let b = Json::from_iter([
+            ("y", Json::JsonString("s".to_string())),
+            ("x", Json::JsonArray(vec![Json::JsonNumber(0.1 + 0.2 - 0.2), Json::JsonNumber(1000001.0)])),
+        ]);
+        assert_ne!(a, b);
+        assert!(!a.approx_eq(&b, 1e-9));
+        assert!(a.approx_eq(&b, 1.0));
+        assert!(a.approx_eq_relative(&b, 1e-6));
+        assert!(!a.approx_eq_relative(&b, 1e-7));
+        assert!(Json::JsonNumber(f64::NAN).approx_eq(&Json::JsonNumber(f64::NAN), 0.0));
+        assert!(!a.approx_eq(&parse(r#"{"x": [0.1, 1000000], "y": "t"}"#), 1.0));
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use crate::core;
+
+use core::Json;
+
+// Builds a value from a borrowed Rust value. String types convert to
+// `JsonString`; use `str::parse` or `Json::try_from` to parse JSON text.
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+impl Json {
+    pub fn string<S: Into<String>>(value: S) -> Self {
+        Json::JsonString(value.into())
+    }
+}
+
+impl ToJson for Json {
+    fn to_json(&self) -> Json {
+        self.clone()
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> Json {
+        Json::JsonString(self.to_string())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> Json {
+        Json::JsonString(self.clone())
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> Json {
+        Json::JsonString(self.to_string())
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> Json {
+        Json::JsonBoolean(*self)
+    }
+}
+
+impl ToJson for () {
+    fn to_json(&self) -> Json {
+        Json::JsonNull
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> Json {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> Json {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> Json {
+        match self {
+            Some(value) => value.to_json(),
+            None => Json::JsonNull,
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> Json {
+        Json::JsonArray(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson, const N: usize> ToJson for [T; N] {
+    fn to_json(&self) -> Json {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Json {
+        self.as_slice().to_json()
+    }
+}
+
+impl<K: AsRef<str>, V: ToJson, S: BuildHasher> ToJson for HashMap<K, V, S> {
+    fn to_json(&self) -> Json {
+        Json::JsonObject(self.iter().map(|(k, v)| (k.as_ref().to_string(), v.to_json())).collect())
+    }
+}
+
+impl<K: AsRef<str>, V: ToJson> ToJson for BTreeMap<K, V> {
+    fn to_json(&self) -> Json {
+        Json::JsonObject(self.iter().map(|(k, v)| (k.as_ref().to_string(), v.to_json())).collect())
+    }
+}
+
+macro_rules! impl_number_conversions {
+    ($($t:ty),*) => {$(
+        impl ToJson for $t {
+            fn to_json(&self) -> Json {
+                Json::JsonNumber(*self as f64)
+            }
+        }
+
+        impl From<$t> for Json {
+            fn from(val: $t) -> Json {
+                Json::JsonNumber(val as f64)
+            }
+        }
+    )*};
+}
+
+impl_number_conversions!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl From<bool> for Json {
+    fn from(val: bool) -> Json {
+        Json::JsonBoolean(val)
+    }
+}
+
+impl From<char> for Json {
+    fn from(val: char) -> Json {
+        Json::JsonString(val.to_string())
+    }
+}
+
+impl From<()> for Json {
+    fn from(_: ()) -> Json {
+        Json::JsonNull
+    }
+}
+
+impl<T: ToJson> From<Option<T>> for Json {
+    fn from(val: Option<T>) -> Json {
+        val.to_json()
+    }
+}
+
+impl<T: ToJson> From<Vec<T>> for Json {
+    fn from(val: Vec<T>) -> Json {
+        val.to_json()
+    }
+}
+
+impl<T: ToJson> From<&[T]> for Json {
+    fn from(val: &[T]) -> Json {
+        val.to_json()
+    }
+}
+
+impl<K: AsRef<str>, V: ToJson, S: BuildHasher> From<HashMap<K, V, S>> for Json {
+    fn from(val: HashMap<K, V, S>) -> Json {
+        val.to_json()
+    }
+}
+
+impl<K: AsRef<str>, V: ToJson> From<BTreeMap<K, V>> for Json {
+    fn from(val: BTreeMap<K, V>) -> Json {
+        val.to_json()
+    }
+}
+
+impl FromIterator<Json> for Json {
+    fn from_iter<I: IntoIterator<Item = Json>>(iter: I) -> Json {
+        Json::JsonArray(iter.into_iter().collect())
+    }
+}
+
+impl<K: Into<Arc<str>>> FromIterator<(K, Json)> for Json {
+    fn from_iter<I: IntoIterator<Item = (K, Json)>>(iter: I) -> Json {
+        Json::JsonObject(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_constructor() {
+        assert_eq!(Json::string("{}"), Json::JsonString("{}".to_string()));
+        assert_eq!(Json::string(String::from("x")), Json::JsonString("x".to_string()));
+        assert_eq!("[1]".to_json(), Json::JsonString("[1]".to_string()));
+    }
+
+    #[test]
+    fn test_from_scalars() {
+        assert_eq!(Json::from(true), Json::JsonBoolean(true));
+        assert_eq!(Json::from(3u8), Json::JsonNumber(3.0));
+        assert_eq!(Json::from(-7i64), Json::JsonNumber(-7.0));
+        assert_eq!(Json::from(1.5f32), Json::JsonNumber(1.5));
+        assert_eq!(Json::from('c'), Json::JsonString("c".to_string()));
+        assert_eq!(Json::from(()), Json::JsonNull);
+    }
+
+    #[test]
+    fn test_from_option() {
+        assert_eq!(Json::from(Some(2)), Json::JsonNumber(2.0));
+        assert_eq!(Json::from(None::<i32>), Json::JsonNull);
+        assert_eq!(Json::from(Some("text")), Json::JsonString("text".to_string()));
+    }
+
+    #[test]
+    fn test_from_vec_and_slice() {
+        let expected = Json::JsonArray(vec![Json::JsonString("a".to_string()), Json::JsonString("b".to_string())]);
+        assert_eq!(Json::from(vec!["a", "b"]), expected);
+        assert_eq!(Json::from(&["a".to_string(), "b".to_string()][..]), expected);
+        assert_eq!(
+            Json::from(vec![vec![1], vec![]]),
+            Json::JsonArray(vec![Json::JsonArray(vec![Json::JsonNumber(1.0)]), Json::JsonArray(vec![])])
+        );
+    }
+
+    #[test]
+    fn test_from_maps() {
+        let mut map = HashMap::new();
+        map.insert("a", 1);
+        let json = Json::from(map);
+        assert_eq!(json["a"], Json::JsonNumber(1.0));
+
+        let mut tree = BTreeMap::new();
+        tree.insert("k".to_string(), vec![true]);
+        let json = Json::from(tree);
+        assert_eq!(json["k"][0], Json::JsonBoolean(true));
+    }
+
+    #[test]
+    fn test_from_iterator() {
+        let arr: Json = (1..=3).map(Json::from).collect();
+        assert_eq!(arr, Json::from(vec![1, 2, 3]));
+
+        let obj: Json = vec![("x", Json::from(1)), ("y", Json::JsonNull)].into_iter().collect();
+        assert_eq!(obj["x"], Json::JsonNumber(1.0));
+        assert_eq!(obj["y"], Json::JsonNull);
+        assert_eq!(obj.as_object().map(|map| map.len()), Some(2));
+    }
+}
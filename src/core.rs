@@ -1,34 +1,39 @@
-use std::collections::HashMap;
-
 use crate::utils;
 use crate::object;
 use crate::array;
 use crate::string;
 use crate::literals;
 use crate::number;
+use crate::map;
+use crate::options;
+use crate::intern;
 
-use utils::ignore_ws;
+use utils::{from_start, ignore_ws_bytes, peek_char, rest};
 use object::parse_object;
 use array::parse_array;
-use string::parse_string;
+use string::{dump_string, parse_string};
 use literals::{parse_true, parse_false, parse_null};
-use number::parse_number;
+use number::{dump_number, parse_number};
+use map::JsonMap;
+use options::ParseOptions;
+use intern::{Interner, Keys};
 
 
-#[derive(Debug, Clone, PartialEq)]
+// Equality, ordering and hashing live in compare.rs
+#[derive(Debug, Clone, Default)]
 pub enum Json {
-    JsonObject(HashMap<String, Json>),
+    JsonObject(JsonMap),
     JsonArray(Vec<Json>),
     JsonString(String),
     JsonNumber(f64),
     JsonBoolean(bool),
-    JsonNull,
-    JsonEmpty
+    #[default]
+    JsonNull
 }
 
 impl Json {
     pub fn new_object() -> Self {
-        Json::JsonObject(HashMap::new())
+        Json::JsonObject(JsonMap::new())
     }
     pub fn new_array() -> Self {
         Json::JsonArray(Vec::new())
@@ -38,9 +43,12 @@ impl Json {
             Json::JsonObject(fields) => {
                 let mut result = String::from("{");
                 for (key, value) in fields {
-                    result.push_str(&format!("\"{}\":{},", key, value.dump()));
+                    dump_string(key, &mut result);
+                    result.push_str(&format!(":{},", value.dump()));
+                }
+                if !fields.is_empty() {
+                    result.pop(); // Remove last comma
                 }
-                result.pop(); // Remove last comma
                 result.push('}');
                 result
             }
@@ -49,37 +57,155 @@ impl Json {
                 for element in elements {
                     result.push_str(&format!("{},", element.dump()));
                 }
-                result.pop(); // Remove last comma
+                if !elements.is_empty() {
+                    result.pop(); // Remove last comma
+                }
                 result.push(']');
                 result
             }
-            Json::JsonString(s) => format!("\"{}\"", s),
-            Json::JsonNumber(n) => n.to_string(),
+            Json::JsonString(s) => {
+                let mut result = String::new();
+                dump_string(s, &mut result);
+                result
+            }
+            Json::JsonNumber(n) => {
+                let mut result = String::new();
+                dump_number(*n, &mut result);
+                result
+            }
             Json::JsonBoolean(b) => b.to_string(),
-            Json::JsonNull => "null".to_string(),
-            Json::JsonEmpty => "".to_string()
+            Json::JsonNull => "null".to_string()
+        }
+    }
+
+    // Like `dump` but with each member and element on its own line, nested
+    // `indent` spaces deeper than its container
+    pub fn dump_pretty(&self, indent: usize) -> String {
+        let mut result = String::new();
+        self.dump_pretty_into(indent, 0, &mut result);
+        result
+    }
+
+    fn dump_pretty_into(&self, indent: usize, level: usize, out: &mut String) {
+        let newline = |out: &mut String, level: usize| {
+            out.push('\n');
+            out.extend(std::iter::repeat_n(' ', indent * level));
+        };
+        match self {
+            Json::JsonObject(fields) if !fields.is_empty() => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, level + 1);
+                    dump_string(key, out);
+                    out.push_str(": ");
+                    value.dump_pretty_into(indent, level + 1, out);
+                }
+                newline(out, level);
+                out.push('}');
+            }
+            Json::JsonArray(elements) if !elements.is_empty() => {
+                out.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, level + 1);
+                    element.dump_pretty_into(indent, level + 1, out);
+                }
+                newline(out, level);
+                out.push(']');
+            }
+            Json::JsonNumber(n) => dump_number(*n, out),
+            value => out.push_str(&value.dump()),
         }
     }
 }
 
-pub fn parse(source: &str) -> Result<(Json, &str), JsonError>{
-    let s = ignore_ws(source);  
-    match s.chars().next() {
-        Some('{') => parse_object(&s),
-        Some('[') => parse_array(&s),
-        Some('"') => parse_string(&s),
-        Some('t') => parse_true(&s),
-        Some('f') => parse_false(&s),
-        Some('n') => parse_null(&s),
-        Some(_) => parse_number(&s),
-        None => Ok((Json::JsonEmpty, ""))
-    }  
+pub fn parse(source: &str) -> Result<(Json, &str), JsonError> {
+    let (json, tail) = parse_value(source.as_bytes(), &ParseOptions::default(), 0, &mut Keys::new(None))?;
+    Ok((json, rest(source, tail)))
+}
+
+// `depth` counts the arrays and objects enclosing the value. The lexer works
+// on bytes, so that input which is not yet known to be UTF-8 is checked as
+// it goes; callers holding a `&str` map the tail back with `utils::rest`.
+pub(crate) fn parse_value<'a>(source: &'a [u8], options: &ParseOptions, depth: usize, keys: &mut Keys) -> Result<(Json, &'a [u8]), JsonError> {
+    let s = ignore_ws_bytes(source);
+    match s.first() {
+        Some(b'{' | b'[') if depth >= options.max_depth => {
+            Err(JsonError::DepthError { max_depth: options.max_depth })
+        }
+        Some(b'{') => parse_object(s, options, depth + 1, keys),
+        Some(b'[') => parse_array(s, options, depth + 1, keys),
+        Some(b'"') => parse_string(s, options),
+        Some(b't') => parse_true(s),
+        Some(b'f') => parse_false(s),
+        Some(b'n') => parse_null(s),
+        Some(_) => parse_number(s, options),
+        None => Err(JsonError::SyntaxError("Unexpected end of input".to_string()))
+    }
+}
+
+fn strip_bom_bytes<'a>(source: &'a [u8], options: &ParseOptions) -> Result<&'a [u8], JsonError> {
+    match source.strip_prefix("\u{FEFF}".as_bytes()) {
+        Some(rest) if options.allow_bom => Ok(rest),
+        Some(_) => Err(JsonError::SyntaxError("Unexpected byte order mark at start of input".to_string())),
+        None => Ok(source),
+    }
+}
+
+pub(crate) fn strip_bom<'a>(source: &'a str, options: &ParseOptions) -> Result<&'a str, JsonError> {
+    Ok(rest(source, strip_bom_bytes(source.as_bytes(), options)?))
+}
+
+// Parses a whole document: anything but whitespace after the value is an error
+pub fn parse_with_options(source: &str, options: &ParseOptions) -> Result<Json, JsonError> {
+    parse_utf8(source.as_bytes(), options)
+}
+
+// `parse_with_options` for bytes that the lexer checks to be UTF-8 as it
+// goes, reporting where they are not
+pub(crate) fn parse_utf8(source: &[u8], options: &ParseOptions) -> Result<Json, JsonError> {
+    let mut interner = Interner::new();
+    let keys = Keys::new(options.intern_keys.then_some(&mut interner));
+    parse_document(source, options, keys)
+}
+
+// Like `parse_with_options` with keys always interned, sharing `interner`
+// with earlier and later parses
+pub fn parse_with_interner(source: &str, options: &ParseOptions, interner: &mut Interner) -> Result<Json, JsonError> {
+    parse_document(source.as_bytes(), options, Keys::new(Some(interner)))
+}
+
+fn parse_document(source: &[u8], options: &ParseOptions, mut keys: Keys) -> Result<Json, JsonError> {
+    parse_rest(source, options, &mut keys).map_err(|error| from_start(error, source.len()))
+}
+
+fn parse_rest(source: &[u8], options: &ParseOptions, keys: &mut Keys) -> Result<Json, JsonError> {
+    let source = strip_bom_bytes(source, options)?;
+    let (json, tail) = parse_value(source, options, 0, keys)?;
+    let tail = ignore_ws_bytes(tail);
+    if tail.is_empty() {
+        Ok(json)
+    } else {
+        peek_char(tail)?;
+        Err(JsonError::SyntaxError("Unexpected trailing characters after JSON value".to_string()))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum JsonError {
     LexicalError(String),
-    SyntaxError(String)
+    SyntaxError(String),
+    TypeError(String),
+    IndexError(String),
+    // Input bytes that are not valid text; `offset` is into the original bytes
+    EncodingError { offset: usize, message: String },
+    // Arrays and objects nested deeper than `ParseOptions::max_depth`
+    DepthError { max_depth: usize },
 }
 
 #[cfg(test)]
@@ -260,5 +386,54 @@ mod tests {
             panic!("Expected JsonArray");
         }
     }
-}
 
+    #[test]
+    fn test_parse_empty_input() {
+        assert!(parse("").is_err());
+        assert!(parse("   \n").is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let json: Json = " {\"a\": [1]} ".parse().unwrap();
+        assert_eq!(json["a"][0], Json::JsonNumber(1.0));
+        assert!("".parse::<Json>().is_err());
+        assert!("[1] x".parse::<Json>().is_err());
+        assert!(Json::try_from("nul").is_err());
+        assert_eq!(Json::try_from("true".to_string()), Ok(Json::JsonBoolean(true)));
+    }
+
+    #[test]
+    fn test_dump_empty_containers() {
+        assert_eq!(Json::new_object().dump(), "{}");
+        assert_eq!(Json::new_array().dump(), "[]");
+        let (json, _) = parse(r#"{"a":{},"b":[]}"#).unwrap();
+        assert_eq!(json.dump(), r#"{"a":{},"b":[]}"#);
+    }
+
+    #[test]
+    fn test_dump_pretty() {
+        let (json, _) = parse(r#"{"a":[1,{"b\"":null}],"c":{},"d":[]}"#).unwrap();
+        let expected = "{\n  \"a\": [\n    1,\n    {\n      \"b\\\"\": null\n    }\n  ],\n  \"c\": {},\n  \"d\": []\n}";
+        assert_eq!(json.dump_pretty(2), expected);
+        assert_eq!(parse("[1,[]]").unwrap().0.dump_pretty(0), "[\n1,\n[]\n]");
+        assert_eq!(Json::JsonString("x".to_string()).dump_pretty(4), "\"x\"");
+        assert_eq!(parse(&json.dump_pretty(3)).unwrap().0, json);
+    }
+
+    #[test]
+    fn test_dump_non_finite_numbers() {
+        let json = Json::JsonArray(vec![Json::JsonNumber(f64::INFINITY), Json::JsonNumber(f64::NAN), Json::JsonNumber(-0.5)]);
+        assert_eq!(json.dump(), "[null,null,-0.5]");
+        assert_eq!(json.dump_pretty(0), "[\nnull,\nnull,\n-0.5\n]");
+        assert_eq!(Json::JsonNumber(f64::NEG_INFINITY).dump_pretty(2), "null");
+        assert!(parse(&json.dump()).is_ok());
+    }
+
+    #[test]
+    fn test_dump_escapes_strings() {
+        let source = r#"{"k\"ey":["line\nbreak","tab\there","\u0000"]}"#;
+        let (json, _) = parse(source).unwrap();
+        assert_eq!(json.dump(), source);
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+
+use crate::core;
+use crate::pointer;
+
+use core::{Json, JsonError};
+use pointer::JsonPointer;
+
+// Conversion between an array of objects and RFC 4180 CSV, one object per
+// row. Nested objects spread over one column per leaf, named by the path to
+// it; the header is the union of every record's columns in order of first
+// appearance, and cells a record lacks stay empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    // ',' for CSV, '\t' for TSV
+    pub delimiter: char,
+    pub columns: ColumnStyle,
+    pub arrays: ArrayPolicy,
+    // When reading, turn cells that are JSON numbers, booleans, null, arrays
+    // or objects into those values instead of strings. Either way empty cells
+    // leave the member out.
+    pub infer_types: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnStyle {
+    // `address.city`; keys that contain '.' cannot be told apart
+    #[default]
+    Dotted,
+    // `/address/city`, escaped as RFC 6901 says
+    Pointer,
+}
+
+// How an array inside a record is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayPolicy {
+    // Its compact JSON text in one cell
+    Encode,
+    // Its elements in one cell, separated by the given character
+    Join(char),
+    // One row per element, the rest of the record repeated on each. Several
+    // arrays in a record give every combination of their elements.
+    Explode,
+}
+
+impl CsvOptions {
+    pub const fn new() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            columns: ColumnStyle::Dotted,
+            arrays: ArrayPolicy::Encode,
+            infer_types: false,
+        }
+    }
+
+    pub const fn tsv() -> Self {
+        CsvOptions { delimiter: '\t', ..CsvOptions::new() }
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions::new()
+    }
+}
+
+fn check_delimiter(delimiter: char) -> Result<(), JsonError> {
+    match delimiter {
+        '"' | '\r' | '\n' => Err(JsonError::TypeError(format!("{:?} cannot be used as a delimiter", delimiter))),
+        _ => Ok(()),
+    }
+}
+
+// A row as (column, cell) pairs in the order the record produced them
+type Row = Vec<(String, String)>;
+
+pub fn to_csv(json: &Json, options: &CsvOptions) -> Result<String, JsonError> {
+    check_delimiter(options.delimiter)?;
+    let records = json
+        .as_array()
+        .ok_or_else(|| JsonError::TypeError(format!("Expected an array of objects but found {}", json.type_name())))?;
+
+    let mut columns: Vec<String> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut rows: Vec<Vec<(usize, String)>> = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        let Json::JsonObject(map) = record else {
+            return Err(JsonError::TypeError(format!("Expected an object at index {} but found {}", i, record.type_name())));
+        };
+        let record_rows = map
+            .iter()
+            .fold(vec![Row::new()], |rows, (key, value)| flatten(value, column_name("", key, options.columns), rows, options));
+        for row in record_rows {
+            let row = row
+                .into_iter()
+                .map(|(column, cell)| {
+                    let position = *positions.entry(column).or_insert_with_key(|column| {
+                        columns.push(column.clone());
+                        columns.len() - 1
+                    });
+                    (position, cell)
+                })
+                .collect();
+            rows.push(row);
+        }
+    }
+
+    let mut out = String::new();
+    if columns.is_empty() && rows.is_empty() {
+        return Ok(out);
+    }
+    write_row(columns.iter().map(String::as_str), options.delimiter, &mut out);
+    let mut cells = vec![String::new(); columns.len()];
+    for row in rows {
+        cells.iter_mut().for_each(String::clear);
+        for (position, cell) in row {
+            cells[position] = cell;
+        }
+        write_row(cells.iter().map(String::as_str), options.delimiter, &mut out);
+    }
+    Ok(out)
+}
+
+fn column_name(prefix: &str, key: &str, style: ColumnStyle) -> String {
+    match style {
+        ColumnStyle::Dotted if prefix.is_empty() => key.to_string(),
+        ColumnStyle::Dotted => format!("{}.{}", prefix, key),
+        ColumnStyle::Pointer => format!("{}/{}", prefix, key.replace('~', "~0").replace('/', "~1")),
+    }
+}
+
+// Adds the cells of `value`, found under `column`, to every row in `rows`
+fn flatten(value: &Json, column: String, rows: Vec<Row>, options: &CsvOptions) -> Vec<Row> {
+    match value {
+        Json::JsonObject(map) if !map.is_empty() => map
+            .iter()
+            .fold(rows, |rows, (key, value)| flatten(value, column_name(&column, key, options.columns), rows, options)),
+        // An empty array would leave a column next to its elements' columns
+        Json::JsonArray(values) if options.arrays == ArrayPolicy::Explode && values.is_empty() => rows,
+        Json::JsonArray(values) if options.arrays == ArrayPolicy::Explode => rows
+            .into_iter()
+            .flat_map(|row| {
+                values
+                    .iter()
+                    .flat_map(|value| flatten(value, column.clone(), vec![row.clone()], options))
+                    .collect::<Vec<Row>>()
+            })
+            .collect(),
+        _ => {
+            let cell = cell_text(value, options);
+            rows.into_iter()
+                .map(|mut row| {
+                    row.push((column.clone(), cell.clone()));
+                    row
+                })
+                .collect()
+        }
+    }
+}
+
+fn cell_text(value: &Json, options: &CsvOptions) -> String {
+    match value {
+        Json::JsonString(s) => s.clone(),
+        Json::JsonNull => String::new(),
+        Json::JsonArray(values) => match options.arrays {
+            ArrayPolicy::Encode => value.dump(),
+            // Arrays among the elements are encoded
+            ArrayPolicy::Join(separator) => {
+                let encode = CsvOptions { arrays: ArrayPolicy::Encode, ..*options };
+                let parts: Vec<String> = values.iter().map(|value| cell_text(value, &encode)).collect();
+                parts.join(separator.encode_utf8(&mut [0; 4]))
+            }
+            // `flatten` spreads these over rows before they get here
+            ArrayPolicy::Explode => value.dump(),
+        },
+        _ => value.dump(),
+    }
+}
+
+fn write_row<'a>(cells: impl Iterator<Item = &'a str>, delimiter: char, out: &mut String) {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            out.push(delimiter);
+        }
+        if cell.contains([delimiter, '"', '\r', '\n']) {
+            out.push('"');
+            out.push_str(&cell.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(cell);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+// Reads CSV with a header row into an array of objects, nesting them again
+// along the column names. Accepts LF as well as CRLF line endings and skips
+// blank lines and a leading byte order mark.
+pub fn from_csv(text: &str, options: &CsvOptions) -> Result<Json, JsonError> {
+    check_delimiter(options.delimiter)?;
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    let mut rows = read_rows(text, options.delimiter)?.into_iter();
+    let Some((_, header)) = rows.next() else {
+        return Ok(Json::new_array());
+    };
+    let paths = header
+        .iter()
+        .map(|name| match options.columns {
+            ColumnStyle::Dotted => Ok(name.split('.').map(str::to_string).collect()),
+            ColumnStyle::Pointer if name.is_empty() => Err(JsonError::SyntaxError("Empty column name".to_string())),
+            ColumnStyle::Pointer => Ok(JsonPointer::parse(name)?.tokens().map(str::to_string).collect()),
+        })
+        .collect::<Result<Vec<Vec<String>>, JsonError>>()?;
+
+    let mut records = Vec::new();
+    for (line, row) in rows {
+        if row.len() > header.len() {
+            return Err(JsonError::SyntaxError(format!("Row on line {} has {} fields but the header has {}", line, row.len(), header.len())));
+        }
+        let mut record = Json::new_object();
+        for ((path, name), cell) in paths.iter().zip(&header).zip(row) {
+            if cell.is_empty() {
+                continue;
+            }
+            let value = if options.infer_types { infer(cell) } else { Json::JsonString(cell) };
+            insert(&mut record, path, value)
+                .ok_or_else(|| JsonError::TypeError(format!("Column {:?} conflicts with another column on line {}", name, line)))?;
+        }
+        records.push(record);
+    }
+    Ok(Json::JsonArray(records))
+}
+
+// None when the path runs into a value that is already there
+fn insert(record: &mut Json, path: &[String], value: Json) -> Option<()> {
+    let (last, parents) = path.split_last()?;
+    let mut current = record;
+    for token in parents {
+        current = current.as_object_mut()?.entry(token.as_str()).or_insert_with(Json::new_object);
+    }
+    let map = current.as_object_mut()?;
+    if map.contains_key(last) {
+        return None;
+    }
+    map.insert(last.as_str(), value);
+    Some(())
+}
+
+fn infer(cell: String) -> Json {
+    let candidate = match cell.as_str() {
+        "true" => return Json::JsonBoolean(true),
+        "false" => return Json::JsonBoolean(false),
+        "null" => return Json::JsonNull,
+        // Leading zeros and surrounding space are not JSON, so "007" and
+        // " 1" stay strings
+        s => s.starts_with(|c: char| c == '-' || c == '[' || c == '{' || c.is_ascii_digit()) && s.trim() == s,
+    };
+    match candidate.then(|| cell.parse::<Json>()) {
+        Some(Ok(value)) => value,
+        _ => Json::JsonString(cell),
+    }
+}
+
+// Rows as their fields, each with the line it starts on
+fn read_rows(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, JsonError> {
+    let mut rows = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    // Whether the current field was quoted, which an empty one may have been
+    let mut quoted = false;
+    let (mut line, mut start) = (1, 1);
+    let mut chars = text.chars().peekable();
+    loop {
+        match chars.next() {
+            Some('"') if field.is_empty() && !quoted => {
+                quoted = true;
+                let opened = line;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.push(c);
+                        }
+                        None => return Err(JsonError::SyntaxError(format!("Unterminated quoted field starting on line {}", opened))),
+                    }
+                }
+                match chars.peek() {
+                    None | Some('\r' | '\n') => {}
+                    Some(&c) if c == delimiter => {}
+                    Some(_) => return Err(JsonError::SyntaxError(format!("Unexpected character after a closing quote on line {}", line))),
+                }
+            }
+            Some(c) if c == delimiter => {
+                fields.push(std::mem::take(&mut field));
+                quoted = false;
+            }
+            Some('\r') if chars.peek() == Some(&'\n') => {}
+            Some('\n') => {
+                end_row(&mut rows, &mut fields, &mut field, quoted, start);
+                quoted = false;
+                line += 1;
+                start = line;
+            }
+            Some(c) => field.push(c),
+            None => {
+                end_row(&mut rows, &mut fields, &mut field, quoted, start);
+                return Ok(rows);
+            }
+        }
+    }
+}
+
+fn end_row(rows: &mut Vec<(usize, Vec<String>)>, fields: &mut Vec<String>, field: &mut String, quoted: bool, line: usize) {
+    // A blank line holds no fields at all
+    if fields.is_empty() && field.is_empty() && !quoted {
+        return;
+    }
+    fields.push(std::mem::take(field));
+    rows.push((line, std::mem::take(fields)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Json {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn test_to_csv_flattens_and_unions_headers() {
+        let json = parse(r#"[
+            {"id": 1, "name": "Ada", "address": {"city": "London", "geo": {"lat": 51.5}}},
+            {"id": 2, "address": {"city": "Paris"}, "active": true, "note": null},
+            {"id": 3, "name": "Eve", "meta": {}}
+        ]"#);
+        let csv = to_csv(&json, &CsvOptions::default()).unwrap();
+        assert_eq!(csv, concat!(
+            "id,name,address.city,address.geo.lat,active,note,meta\r\n",
+            "1,Ada,London,51.5,,,\r\n",
+            "2,,Paris,,true,,\r\n",
+            "3,Eve,,,,,{}\r\n",
+        ));
+        let options = CsvOptions { columns: ColumnStyle::Pointer, ..CsvOptions::tsv() };
+        let csv = to_csv(&parse(r#"[{"a.b": {"c/d": 1, "e~": "x\ty"}}]"#), &options).unwrap();
+        assert_eq!(csv, "/a.b/c~1d\t/a.b/e~0\r\n1\t\"x\ty\"\r\n");
+        assert_eq!(to_csv(&parse("[]"), &CsvOptions::default()).unwrap(), "");
+        assert!(matches!(to_csv(&parse("{}"), &CsvOptions::default()), Err(JsonError::TypeError(_))));
+        assert!(matches!(to_csv(&parse("[{}, 1]"), &CsvOptions::default()), Err(JsonError::TypeError(_))));
+    }
+
+    #[test]
+    fn test_rfc4180_quoting() {
+        let json = parse(r#"[{"a": "plain", "b": "x,y", "c": "say \"hi\"", "d": "two\nlines", "e": " padded "}]"#);
+        let csv = to_csv(&json, &CsvOptions::default()).unwrap();
+        assert_eq!(csv, "a,b,c,d,e\r\nplain,\"x,y\",\"say \"\"hi\"\"\",\"two\nlines\", padded \r\n");
+        assert_eq!(from_csv(&csv, &CsvOptions::default()).unwrap(), json);
+    }
+
+    #[test]
+    fn test_array_policies() {
+        let json = parse(r#"[{"id": 1, "tags": ["a", "b"], "dims": [[1, 2], {"w": 3}]}, {"id": 2, "tags": []}]"#);
+        let encode = to_csv(&json, &CsvOptions::default()).unwrap();
+        assert_eq!(encode, "id,tags,dims\r\n1,\"[\"\"a\"\",\"\"b\"\"]\",\"[[1,2],{\"\"w\"\":3}]\"\r\n2,[],\r\n");
+
+        let options = CsvOptions { arrays: ArrayPolicy::Join('|'), ..CsvOptions::default() };
+        let join = to_csv(&json, &options).unwrap();
+        assert_eq!(join, "id,tags,dims\r\n1,a|b,\"[1,2]|{\"\"w\"\":3}\"\r\n2,,\r\n");
+
+        let json = parse(r#"[{"order": 7, "items": [{"sku": "x", "qty": 1}, {"sku": "y"}], "codes": [10, 20]}, {"order": 8, "items": []}]"#);
+        let options = CsvOptions { arrays: ArrayPolicy::Explode, ..CsvOptions::default() };
+        let explode = to_csv(&json, &options).unwrap();
+        assert_eq!(explode, concat!(
+            "order,items.sku,items.qty,codes\r\n",
+            "7,x,1,10\r\n", "7,x,1,20\r\n", "7,y,,10\r\n", "7,y,,20\r\n",
+            "8,,,\r\n",
+        ));
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "\u{FEFF}id,user.name,user.zip,score,ok,raw\r\n1,Ada,007,-2.5e1,true,null\n\n2,\"Lovelace, A\",,,false,\"[1, {\"\"a\"\": 2}]\"";
+        let strings = from_csv(csv, &CsvOptions::default()).unwrap();
+        assert_eq!(strings, parse(r#"[
+            {"id": "1", "user": {"name": "Ada", "zip": "007"}, "score": "-2.5e1", "ok": "true", "raw": "null"},
+            {"id": "2", "user": {"name": "Lovelace, A"}, "ok": "false", "raw": "[1, {\"a\": 2}]"}
+        ]"#));
+        let options = CsvOptions { infer_types: true, ..CsvOptions::default() };
+        let typed = from_csv(csv, &options).unwrap();
+        assert_eq!(typed, parse(r#"[
+            {"id": 1, "user": {"name": "Ada", "zip": "007"}, "score": -25, "ok": true, "raw": null},
+            {"id": 2, "user": {"name": "Lovelace, A"}, "ok": false, "raw": [1, {"a": 2}]}
+        ]"#));
+
+        let options = CsvOptions { columns: ColumnStyle::Pointer, ..CsvOptions::tsv() };
+        assert_eq!(from_csv("/a~1b/c\t/d\nx\t\"\"\n", &options).unwrap(), parse(r#"[{"a/b": {"c": "x"}}]"#));
+        assert_eq!(from_csv("", &CsvOptions::default()).unwrap(), parse("[]"));
+        assert_eq!(from_csv("a,b\n", &CsvOptions::default()).unwrap(), parse("[]"));
+        assert_eq!(from_csv("a,b\n1", &CsvOptions::default()).unwrap(), parse(r#"[{"a": "1"}]"#));
+    }
+
+    #[test]
+    fn test_from_csv_errors() {
+        let options = CsvOptions::default();
+        let cases = [
+            ("a\n\"open", "Unterminated quoted field starting on line 2"),
+            ("a,b\n\"x\"y,1", "Unexpected character after a closing quote on line 2"),
+            ("a\n\"multi\nline\"\n1,2", "Row on line 4 has 2 fields but the header has 1"),
+        ];
+        for (csv, message) in cases {
+            match from_csv(csv, &options) {
+                Err(JsonError::SyntaxError(m)) => assert_eq!(m, message),
+                other => panic!("{:?}: expected a syntax error, got {:?}", csv, other),
+            }
+        }
+        assert!(matches!(from_csv("a,a.b\n1,2", &options), Err(JsonError::TypeError(_))));
+        assert!(matches!(from_csv("a.b,a\n1,2", &options), Err(JsonError::TypeError(_))));
+        assert!(matches!(from_csv("a", &CsvOptions { delimiter: '"', ..options }), Err(JsonError::TypeError(_))));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = parse(r#"[
+            {"id": 1, "name": "Ada, \"the first\"", "tags": ["x", "y"], "geo": {"lat": 51.5, "lng": -0.12}, "ok": true},
+            {"id": 2, "name": "Bob\r\nJr", "tags": [], "geo": {"lat": 0}, "ok": false}
+        ]"#);
+        let options = CsvOptions { infer_types: true, ..CsvOptions::default() };
+        assert_eq!(from_csv(&to_csv(&json, &options).unwrap(), &options).unwrap(), json);
+        let options = CsvOptions { infer_types: true, ..CsvOptions::tsv() };
+        assert_eq!(from_csv(&to_csv(&json, &options).unwrap(), &options).unwrap(), json);
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+// Byte range into the parsed source. Zero-width spans mark a position, e.g.
+// where a missing comma should have been.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    pub fn at(pos: usize) -> Self {
+        Span { start: pos, end: pos }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+// Codes are part of the public contract: never renumber or reuse one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    UnexpectedEof,
+    UnexpectedCharacter,
+    MissingComma,
+    MissingColon,
+    MissingValue,
+    TrailingComma,
+    UnclosedContainer,
+    UnterminatedString,
+    InvalidEscape,
+    ControlCharacter,
+    InvalidNumber,
+    InvalidLiteral,
+    UnquotedKey,
+    TrailingCharacters,
+    DuplicateKey,
+    NestingTooDeep,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedEof => "E001",
+            ErrorCode::UnexpectedCharacter => "E002",
+            ErrorCode::MissingComma => "E003",
+            ErrorCode::MissingColon => "E004",
+            ErrorCode::MissingValue => "E005",
+            ErrorCode::TrailingComma => "E006",
+            ErrorCode::UnclosedContainer => "E007",
+            ErrorCode::UnterminatedString => "E008",
+            ErrorCode::InvalidEscape => "E009",
+            ErrorCode::ControlCharacter => "E010",
+            ErrorCode::InvalidNumber => "E011",
+            ErrorCode::InvalidLiteral => "E012",
+            ErrorCode::UnquotedKey => "E013",
+            ErrorCode::TrailingCharacters => "E014",
+            ErrorCode::DuplicateKey => "W001",
+            ErrorCode::NestingTooDeep => "E015",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            ErrorCode::DuplicateKey => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(code: ErrorCode, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            code,
+            severity: code.severity(),
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {} at {}..{}", self.severity, self.code, self.message, self.span.start, self.span.end)
+    }
+}
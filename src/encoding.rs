@@ -0,0 +1,196 @@
+use crate::core;
+use crate::options;
+
+use core::{Json, JsonError, parse_utf8, parse_with_options};
+use options::ParseOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+// A byte order mark wins; otherwise RFC 4627 section 3: the first two
+// characters of a JSON text are ASCII, so the pattern of zero bytes among the
+// first four gives the encoding away.
+fn detect(bytes: &[u8]) -> Encoding {
+    match bytes {
+        [0x00, 0x00, 0xFE, 0xFF, ..] => Encoding::Utf32Be,
+        [0xFF, 0xFE, 0x00, 0x00, ..] => Encoding::Utf32Le,
+        [0xFE, 0xFF, ..] => Encoding::Utf16Be,
+        [0xFF, 0xFE, ..] => Encoding::Utf16Le,
+        [0xEF, 0xBB, 0xBF, ..] => Encoding::Utf8,
+        [0x00, 0x00, 0x00, a, ..] if *a != 0 => Encoding::Utf32Be,
+        [a, 0x00, 0x00, 0x00, ..] if *a != 0 => Encoding::Utf32Le,
+        [0x00, a, 0x00, b, ..] if *a != 0 && *b != 0 => Encoding::Utf16Be,
+        [a, 0x00, b, 0x00, ..] if *a != 0 && *b != 0 => Encoding::Utf16Le,
+        // A one character document
+        [0x00, a] if *a != 0 => Encoding::Utf16Be,
+        [a, 0x00] if *a != 0 => Encoding::Utf16Le,
+        _ => Encoding::Utf8,
+    }
+}
+
+fn encoding_error(offset: usize, message: String) -> JsonError {
+    JsonError::EncodingError { offset, message }
+}
+
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> Result<String, JsonError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(encoding_error(bytes.len() - 1, "Truncated code unit in UTF-16 input".to_string()));
+    }
+    let units = bytes.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        if little_endian { u16::from_le_bytes(pair) } else { u16::from_be_bytes(pair) }
+    });
+    let mut result = String::with_capacity(bytes.len() / 2);
+    let mut offset = 0;
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => {
+                result.push(c);
+                offset += c.len_utf16() * 2;
+            }
+            Err(err) => return Err(encoding_error(offset, format!("Unpaired surrogate {:04X} in UTF-16 input", err.unpaired_surrogate()))),
+        }
+    }
+    Ok(result)
+}
+
+fn decode_utf32(bytes: &[u8], little_endian: bool) -> Result<String, JsonError> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(encoding_error(bytes.len() - bytes.len() % 4, "Truncated code unit in UTF-32 input".to_string()));
+    }
+    let mut result = String::with_capacity(bytes.len() / 4);
+    for (i, unit) in bytes.chunks_exact(4).enumerate() {
+        let unit = [unit[0], unit[1], unit[2], unit[3]];
+        let code = if little_endian { u32::from_le_bytes(unit) } else { u32::from_be_bytes(unit) };
+        let c = char::from_u32(code)
+            .ok_or_else(|| encoding_error(i * 4, format!("Invalid code point {:X} in UTF-32 input", code)))?;
+        result.push(c);
+    }
+    Ok(result)
+}
+
+// Parses a whole document from raw bytes in any encoding RFC 4627 allows.
+// UTF-8 is checked by the lexer as it parses, without a separate pass; the
+// other encodings are transcoded first. A byte order mark is decoded along
+// with the text and then treated like a leading U+FEFF, so it is only
+// skipped when `allow_bom` is set.
+pub fn parse_bytes_with_options(bytes: &[u8], options: &ParseOptions) -> Result<Json, JsonError> {
+    match detect(bytes) {
+        Encoding::Utf8 => parse_utf8(bytes, options),
+        Encoding::Utf16Le => parse_with_options(&decode_utf16(bytes, true)?, options),
+        Encoding::Utf16Be => parse_with_options(&decode_utf16(bytes, false)?, options),
+        Encoding::Utf32Le => parse_with_options(&decode_utf32(bytes, true)?, options),
+        Encoding::Utf32Be => parse_with_options(&decode_utf32(bytes, false)?, options),
+    }
+}
+
+pub fn parse_bytes(bytes: &[u8]) -> Result<Json, JsonError> {
+    parse_bytes_with_options(bytes, &ParseOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|u| if little_endian { u.to_le_bytes() } else { u.to_be_bytes() })
+            .collect()
+    }
+
+    fn utf32(text: &str, little_endian: bool) -> Vec<u8> {
+        text.chars()
+            .flat_map(|c| if little_endian { (c as u32).to_le_bytes() } else { (c as u32).to_be_bytes() })
+            .collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"{}"), Encoding::Utf8);
+        assert_eq!(detect(b""), Encoding::Utf8);
+        assert_eq!(detect(&utf16("1", true)), Encoding::Utf16Le);
+        assert_eq!(detect(&utf16("[1]", false)), Encoding::Utf16Be);
+        assert_eq!(detect(&utf32("[1]", true)), Encoding::Utf32Le);
+        assert_eq!(detect(&utf32("1", false)), Encoding::Utf32Be);
+        assert_eq!(detect(&utf16("\u{FEFF}\u{e9}", true)), Encoding::Utf16Le);
+        assert_eq!(detect(b"[\x00]"), Encoding::Utf8);
+    }
+
+    #[test]
+    fn test_parse_bytes_utf8() {
+        assert_eq!(parse_bytes(b"{\"a\": [1, \"\xC3\xA9\"]}").unwrap(), json!({"a": [1, "é"]}));
+        assert!(parse_bytes(b"[1] x").is_err());
+    }
+
+    #[test]
+    fn test_parse_bytes_invalid_utf8_offsets() {
+        let err = parse_bytes(b"[\"ab\xFFc\"]").unwrap_err();
+        assert!(matches!(err, JsonError::EncodingError { offset: 4, .. }), "{:?}", err);
+        let err = parse_bytes(b"[\"\xC3\xA9\xE2\x82").unwrap_err();
+        assert!(matches!(err, JsonError::EncodingError { offset: 4, .. }), "{:?}", err);
+        // Overlong encoding of '/'
+        let err = parse_bytes(b"\"\xC0\xAF\"").unwrap_err();
+        assert!(matches!(err, JsonError::EncodingError { offset: 1, .. }), "{:?}", err);
+        // Cut short by the closing quote rather than the end of input
+        let err = parse_bytes(b"{\"k\xC3\": 1}").unwrap_err();
+        assert_eq!(err, JsonError::EncodingError { offset: 3, message: "Invalid UTF-8 sequence [C3]".to_string() });
+        let err = parse_bytes(b"[\"a\\n\xE2\x82\"]").unwrap_err();
+        assert_eq!(err, JsonError::EncodingError { offset: 5, message: "Invalid UTF-8 sequence [E2, 82]".to_string() });
+        // Outside strings, wherever the lexer stops on them
+        for (bytes, offset) in [(&b"[\xFF]"[..], 1), (b"[1 \xFF]", 3), (b"{\"a\" \xFF}", 5), (b"{\"a\": 1 \xC3}", 8), (b"[1] \xFF", 4), (b"tr\xFFe", 2)] {
+            assert!(matches!(parse_bytes(bytes), Err(JsonError::EncodingError { offset: o, .. }) if o == offset), "{:?}", bytes);
+        }
+        // Errors before any invalid UTF-8 come first, like in a `&str`
+        assert!(matches!(parse_bytes(b"[1,] \xFF"), Err(JsonError::SyntaxError(_))));
+        // Non-ASCII that is UTF-8 outside a string is still a syntax error
+        assert!(matches!(parse_bytes("[\u{e9}]".as_bytes()), Err(JsonError::SyntaxError(_))));
+    }
+
+    #[test]
+    fn test_parse_bytes_bom() {
+        let with_bom = b"\xEF\xBB\xBF{\"a\": 1}";
+        let allow = ParseOptions { allow_bom: true, ..ParseOptions::default() };
+        assert_eq!(parse_bytes_with_options(with_bom, &allow).unwrap(), json!({"a": 1}));
+        let err = parse_bytes(with_bom).unwrap_err();
+        assert_eq!(err, JsonError::SyntaxError("Unexpected byte order mark at start of input".to_string()));
+    }
+
+    #[test]
+    fn test_parse_bytes_utf16_and_utf32() {
+        let text = "{\"k\": [\"\u{e9}\u{1D11E}\", 2]}";
+        let expected = json!({"k": ["\u{e9}\u{1D11E}", 2]});
+        for little_endian in [true, false] {
+            assert_eq!(parse_bytes(&utf16(text, little_endian)).unwrap(), expected);
+            assert_eq!(parse_bytes(&utf32(text, little_endian)).unwrap(), expected);
+        }
+        let allow = ParseOptions { allow_bom: true, ..ParseOptions::default() };
+        let with_bom = utf16(&format!("\u{FEFF}{}", text), true);
+        assert_eq!(parse_bytes_with_options(&with_bom, &allow).unwrap(), expected);
+        assert!(parse_bytes(&with_bom).is_err());
+        let with_bom = utf32(&format!("\u{FEFF}{}", text), false);
+        assert_eq!(parse_bytes_with_options(&with_bom, &allow).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_bytes_invalid_utf16_and_utf32() {
+        let mut bytes = utf16("[\"a", true);
+        bytes.extend_from_slice(&[0x00, 0xD8]);
+        bytes.extend(utf16("\"]", true));
+        let err = parse_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, JsonError::EncodingError { offset: 6, .. }), "{:?}", err);
+
+        let mut bytes = utf16("[1]", false);
+        bytes.push(0);
+        assert!(matches!(parse_bytes(&bytes), Err(JsonError::EncodingError { offset: 6, .. })));
+
+        let mut bytes = utf32("[1", true);
+        bytes.extend_from_slice(&0x110000u32.to_le_bytes());
+        assert!(matches!(parse_bytes(&bytes), Err(JsonError::EncodingError { offset: 8, .. })));
+    }
+}
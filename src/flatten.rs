@@ -0,0 +1,358 @@
+use std::collections::HashSet;
+
+use crate::core;
+use crate::map;
+use crate::pointer;
+
+use core::{Json, JsonError};
+use map::JsonMap;
+use pointer::{JsonPointer, parse_array_index};
+
+// Conversion between a document and a single-level object mapping the path
+// of every leaf to its value, e.g. `{"a":{"b":[1,2]}}` and
+// `{"a.b.0":1,"a.b.1":2}`. With the defaults the round trip is lossless for
+// any object or array except an empty root array, which comes back as `{}`.
+// A scalar root is kept under the empty path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlattenOptions {
+    pub keys: FlatKeys,
+    pub numeric_segments: NumericSegments,
+    pub empty_containers: EmptyContainers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlatKeys {
+    // Segments joined by `separator`. Inside a key, `escape` goes before a
+    // separator or escape character, and before a key that is all digits so
+    // it is not taken for an array index.
+    Separated { separator: char, escape: char },
+    // RFC 6901 pointers such as `/a/b/0`. Index-like keys cannot be told
+    // apart from indices here.
+    Pointer,
+}
+
+// What a segment that is an array index rebuilds on `unflatten`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumericSegments {
+    #[default]
+    Arrays,
+    Objects,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyContainers {
+    // As leaves holding `{}` or `[]`
+    #[default]
+    Keep,
+    // Left out, so they do not come back
+    Drop,
+}
+
+impl FlattenOptions {
+    pub const fn new() -> Self {
+        FlattenOptions {
+            keys: FlatKeys::Separated { separator: '.', escape: '\\' },
+            numeric_segments: NumericSegments::Arrays,
+            empty_containers: EmptyContainers::Keep,
+        }
+    }
+
+    fn check(&self) -> Result<(), JsonError> {
+        match self.keys {
+            FlatKeys::Separated { separator, escape } if separator == escape => {
+                Err(JsonError::TypeError("The separator and escape characters must differ".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        FlattenOptions::new()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+// Array slots past the end that `unflatten` and `from_gron` fill with null
+// beyond one per entry, so paths like `a.4294967295` cannot allocate huge
+// arrays
+const MAX_PADDING: usize = 1 << 16;
+
+// Puts a document back together from values at paths, for `unflatten` and
+// `from_gron`. Null marks a position nothing has been put at yet, unless its
+// path is in `nulls`, the positions that hold a null value.
+pub(crate) struct Rebuilder {
+    pub(crate) root: Json,
+    nulls: HashSet<Vec<Segment>>,
+    padding: usize,
+}
+
+// Why a value could not go where its path says
+pub(crate) enum Clash {
+    // The position holds this type, which cannot take the segment
+    Path(&'static str, Segment),
+    // The position already holds a value of this type
+    Value(&'static str),
+    // The array index is past what padding allows
+    TooLarge(usize),
+}
+
+impl Rebuilder {
+    pub(crate) fn new(entries: usize) -> Self {
+        Rebuilder { root: Json::JsonNull, nulls: HashSet::new(), padding: entries + MAX_PADDING }
+    }
+
+    // With `merge`, putting `{}` or `[]` where a container of that kind is
+    // already keeps it, and putting a scalar equal to the one there is not a
+    // clash
+    pub(crate) fn insert(&mut self, segments: &[Segment], value: Json, merge: bool) -> Result<(), Clash> {
+        let mut current = &mut self.root;
+        for (depth, segment) in segments.iter().enumerate() {
+            if current.is_null() && !self.nulls.contains(&segments[..depth]) {
+                *current = match segment {
+                    Segment::Index(_) => Json::new_array(),
+                    Segment::Key(_) => Json::new_object(),
+                };
+            }
+            current = match (current, segment) {
+                (Json::JsonArray(values), Segment::Index(i)) => {
+                    if *i >= values.len() {
+                        self.padding = self.padding.checked_sub(i - values.len()).ok_or(Clash::TooLarge(*i))?;
+                        values.resize(i + 1, Json::JsonNull);
+                    }
+                    &mut values[*i]
+                }
+                (Json::JsonObject(map), Segment::Key(k)) => map.entry(k.as_str()).or_insert(Json::JsonNull),
+                (current, segment) => return Err(Clash::Path(current.type_name(), segment.clone())),
+            };
+        }
+        match (&*current, &value) {
+            (Json::JsonObject(_), Json::JsonObject(map)) if merge && map.is_empty() => {}
+            (Json::JsonArray(_), Json::JsonArray(values)) if merge && values.is_empty() => {}
+            (Json::JsonNull, _) if !self.nulls.contains(segments) => {
+                if value.is_null() {
+                    self.nulls.insert(segments.to_vec());
+                }
+                *current = value;
+            }
+            (current, value) if merge && current == value && !matches!(value, Json::JsonObject(_) | Json::JsonArray(_)) => {}
+            (current, _) => return Err(Clash::Value(current.type_name())),
+        }
+        Ok(())
+    }
+}
+
+impl Json {
+    pub fn flatten(&self, options: &FlattenOptions) -> Result<Json, JsonError> {
+        options.check()?;
+        let mut out = JsonMap::new();
+        // Values still to visit with their paths, in reverse document order;
+        // None is the root, whose path is empty like that of the key ""
+        let mut stack: Vec<(&Json, Option<String>)> = vec![(self, None)];
+        while let Some((value, path)) = stack.pop() {
+            match value {
+                Json::JsonObject(map) if !map.is_empty() => {
+                    for (key, value) in map.iter().rev() {
+                        stack.push((value, Some(join(path.as_deref(), &encode_key(key, options), options))));
+                    }
+                }
+                Json::JsonArray(values) if !values.is_empty() => {
+                    for (i, value) in values.iter().enumerate().rev() {
+                        stack.push((value, Some(join(path.as_deref(), &i.to_string(), options))));
+                    }
+                }
+                // An empty root flattens to nothing
+                Json::JsonObject(_) | Json::JsonArray(_) if path.is_none() || options.empty_containers == EmptyContainers::Drop => {}
+                _ => {
+                    out.insert(path.unwrap_or_default(), value.clone());
+                }
+            }
+        }
+        Ok(Json::JsonObject(out))
+    }
+
+    // The inverse of `flatten`; `self` has to be an object. Fails when two
+    // paths disagree about what is at some position, e.g. `a` and `a.b`.
+    pub fn unflatten(&self, options: &FlattenOptions) -> Result<Json, JsonError> {
+        options.check()?;
+        let map = self
+            .as_object()
+            .ok_or_else(|| JsonError::TypeError(format!("Expected an object but found {}", self.type_name())))?;
+        let mut rebuilder = Rebuilder::new(map.len());
+        for (key, value) in map.iter() {
+            let segments = split(key, options)?;
+            rebuilder.insert(&segments, value.clone(), false).map_err(|clash| match clash {
+                Clash::TooLarge(i) => JsonError::IndexError(format!("Array index {} in flattened key {:?} is too large", i, key)),
+                Clash::Path(..) | Clash::Value(_) => JsonError::TypeError(format!("Flattened key {:?} conflicts with another key", key)),
+            })?;
+        }
+        Ok(match rebuilder.root {
+            Json::JsonNull if map.is_empty() => Json::new_object(),
+            root => root,
+        })
+    }
+}
+
+fn encode_key(key: &str, options: &FlattenOptions) -> String {
+    match options.keys {
+        FlatKeys::Separated { separator, escape } => {
+            let mut encoded = String::with_capacity(key.len());
+            if options.numeric_segments == NumericSegments::Arrays && parse_array_index(key).is_some() {
+                encoded.push(escape);
+            }
+            for c in key.chars() {
+                if c == separator || c == escape {
+                    encoded.push(escape);
+                }
+                encoded.push(c);
+            }
+            encoded
+        }
+        FlatKeys::Pointer => key.replace('~', "~0").replace('/', "~1"),
+    }
+}
+
+fn join(path: Option<&str>, segment: &str, options: &FlattenOptions) -> String {
+    match (options.keys, path) {
+        (FlatKeys::Separated { .. }, None) => segment.to_string(),
+        (FlatKeys::Separated { separator, .. }, Some(path)) => format!("{}{}{}", path, separator, segment),
+        (FlatKeys::Pointer, path) => format!("{}/{}", path.unwrap_or_default(), segment),
+    }
+}
+
+fn split(key: &str, options: &FlattenOptions) -> Result<Vec<Segment>, JsonError> {
+    let arrays = options.numeric_segments == NumericSegments::Arrays;
+    let segment = |token: String, literal: bool| match parse_array_index(&token) {
+        Some(i) if arrays && !literal => Segment::Index(i),
+        _ => Segment::Key(token),
+    };
+    match options.keys {
+        FlatKeys::Pointer => Ok(JsonPointer::parse(key)?.tokens().map(|token| segment(token.to_string(), false)).collect()),
+        FlatKeys::Separated { separator, escape } => {
+            let mut segments = Vec::new();
+            let mut token = String::new();
+            // An escaped leading digit marks a key rather than an index
+            let mut literal = false;
+            let mut chars = key.chars();
+            while let Some(c) = chars.next() {
+                if c == escape {
+                    match chars.next() {
+                        Some(next) if next == separator || next == escape => token.push(next),
+                        Some(next) if next.is_ascii_digit() && token.is_empty() => {
+                            literal = true;
+                            token.push(next);
+                        }
+                        _ => return Err(JsonError::SyntaxError(format!("Invalid escape in flattened key {:?}", key))),
+                    }
+                } else if c == separator {
+                    segments.push(segment(std::mem::take(&mut token), literal));
+                    literal = false;
+                } else {
+                    token.push(c);
+                }
+            }
+            segments.push(segment(token, literal));
+            Ok(segments)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Json {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn test_flatten() {
+        let options = FlattenOptions::default();
+        assert_eq!(parse(r#"{"a": {"b": [1, 2]}}"#).flatten(&options).unwrap(), parse(r#"{"a.b.0": 1, "a.b.1": 2}"#));
+        let json = parse(r#"{"x.y": {"a\\b": true, "7": null}, "e": [], "o": {}, "s": [{"k": "v"}]}"#);
+        let flat = json.flatten(&options).unwrap();
+        assert_eq!(flat, parse(r#"{"x\\.y.a\\\\b": true, "x\\.y.\\7": null, "e": [], "o": {}, "s.0.k": "v"}"#));
+        let keys: Vec<&str> = flat.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["x\\.y.a\\\\b", "x\\.y.\\7", "e", "o", "s.0.k"]);
+
+        let pointer = FlattenOptions { keys: FlatKeys::Pointer, ..options };
+        assert_eq!(json.flatten(&pointer).unwrap(), parse(r#"{"/x.y/a\\b": true, "/x.y/7": null, "/e": [], "/o": {}, "/s/0/k": "v"}"#));
+        let slash = FlattenOptions { keys: FlatKeys::Separated { separator: '/', escape: '~' }, ..options };
+        assert_eq!(parse(r#"{"a/b": {"c~": 1}}"#).flatten(&slash).unwrap(), parse(r#"{"a~/b/c~~": 1}"#));
+
+        let drop = FlattenOptions { empty_containers: EmptyContainers::Drop, ..options };
+        assert_eq!(json.flatten(&drop).unwrap(), parse(r#"{"x\\.y.a\\\\b": true, "x\\.y.\\7": null, "s.0.k": "v"}"#));
+        assert_eq!(parse("3").flatten(&options).unwrap(), parse(r#"{"": 3}"#));
+        assert_eq!(parse("{}").flatten(&options).unwrap(), parse("{}"));
+        assert_eq!(parse("[]").flatten(&options).unwrap(), parse("{}"));
+        assert_eq!(parse(r#"{"": {"": 1}}"#).flatten(&options).unwrap(), parse(r#"{".": 1}"#));
+    }
+
+    #[test]
+    fn test_unflatten() {
+        let options = FlattenOptions::default();
+        let flat = parse(r#"{"a.b.1": 2, "a.b.0": 1, "a.\\0": "key", "a.c\\.d": null, "": 0}"#);
+        assert_eq!(flat.unflatten(&options).unwrap(), parse(r#"{"a": {"b": [1, 2], "0": "key", "c.d": null}, "": 0}"#));
+        let objects = FlattenOptions { numeric_segments: NumericSegments::Objects, ..options };
+        assert_eq!(parse(r#"{"a.0": 1, "a.1": 2}"#).unflatten(&objects).unwrap(), parse(r#"{"a": {"0": 1, "1": 2}}"#));
+        assert_eq!(parse(r#"{"0": "x", "2": "z"}"#).unflatten(&options).unwrap(), parse(r#"["x", null, "z"]"#));
+        assert_eq!(parse(r#"{"01": 1}"#).unflatten(&options).unwrap(), parse(r#"{"01": 1}"#));
+        let pointer = FlattenOptions { keys: FlatKeys::Pointer, ..options };
+        assert_eq!(parse(r#"{"/a~1b/0": 1, "/a~1b/1": {}}"#).unflatten(&pointer).unwrap(), parse(r#"{"a/b": [1, {}]}"#));
+        assert_eq!(parse("{}").unflatten(&options).unwrap(), parse("{}"));
+    }
+
+    #[test]
+    fn test_unflatten_errors() {
+        let options = FlattenOptions::default();
+        for flat in [
+            r#"{"a": 1, "a.b": 2}"#,
+            r#"{"a.b": 2, "a": 1}"#,
+            r#"{"a.0": 1, "a.x": 2}"#,
+            r#"{"a.x": 1, "a.0": 2}"#,
+            r#"{"a": null, "a.b": 1}"#,
+            r#"{"a.b": 1, "a": null}"#,
+            r#"{"a.0": null, "a.0.0": 1}"#,
+        ] {
+            assert!(matches!(parse(flat).unflatten(&options), Err(JsonError::TypeError(_))), "{}", flat);
+        }
+        assert!(matches!(parse(r#"{"a\\x": 1}"#).unflatten(&options), Err(JsonError::SyntaxError(_))));
+        assert!(matches!(parse(r#"{"a\\": 1}"#).unflatten(&options), Err(JsonError::SyntaxError(_))));
+        assert!(matches!(parse(r#"{"a.4294967295": 1}"#).unflatten(&options), Err(JsonError::IndexError(_))));
+        assert!(parse("[]").unflatten(&options).is_err());
+        let pointer = FlattenOptions { keys: FlatKeys::Pointer, ..options };
+        assert!(parse(r#"{"a": 1}"#).unflatten(&pointer).is_err());
+        let same = FlattenOptions { keys: FlatKeys::Separated { separator: '.', escape: '.' }, ..options };
+        assert!(parse("{}").flatten(&same).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let documents = [
+            r#"{"a": {"b": [1, 2]}}"#,
+            r#"{"a.b": {"c\\d": [[], {}, [[]], [{}]], "0": {"1": [true]}}, "": {"": ""}, "x": null}"#,
+            r#"[{"id": 1, "tags": ["a"]}, {"id": 2, "tags": []}, [], {}]"#,
+            r#"{"deep": [[[[{"k.\\": [0, -1.5, "s"]}]]]]}"#,
+        ];
+        let styles = [
+            FlattenOptions::default(),
+            FlattenOptions { keys: FlatKeys::Separated { separator: '/', escape: '%' }, ..FlattenOptions::default() },
+            FlattenOptions { keys: FlatKeys::Separated { separator: '.', escape: '\\' }, numeric_segments: NumericSegments::Objects, ..FlattenOptions::default() },
+        ];
+        for source in documents {
+            let json = parse(source);
+            for options in &styles {
+                let round_trip = json.flatten(options).unwrap().unflatten(options).unwrap();
+                if options.numeric_segments == NumericSegments::Arrays || !source.contains('[') {
+                    assert_eq!(round_trip, json, "{} {:?}", source, options);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::core;
+use crate::flatten;
+use crate::json5;
+use crate::options;
+use crate::pointer;
+use crate::string;
+use crate::utils;
+
+use core::{Json, JsonError};
+use flatten::{Clash, Rebuilder, Segment};
+use json5::{is_id_continue, is_identifier};
+use options::ParseOptions;
+use pointer::parse_array_index;
+use string::{dump_string, parse_quoted};
+
+// "gron" text: one JavaScript-style assignment per value, such as
+// `json.servers[3].port = 8080;`, so documents can be searched and compared
+// with line-based tools. Arrays and objects are assigned `[]` or `{}` ahead
+// of their members, and keys are sorted so equal documents give equal text.
+const ROOT: &str = "json";
+
+impl Json {
+    pub fn to_gron(&self) -> String {
+        let mut out = String::new();
+        let mut stack = vec![(self, ROOT.to_string())];
+        while let Some((value, path)) = stack.pop() {
+            out.push_str(&path);
+            out.push_str(" = ");
+            match value {
+                Json::JsonObject(map) => {
+                    out.push_str("{};\n");
+                    let mut members: Vec<(&str, &Json)> = map.iter().collect();
+                    members.sort_by_key(|(key, _)| *key);
+                    for (key, value) in members.into_iter().rev() {
+                        let mut child = path.clone();
+                        if is_identifier(key) {
+                            child.push('.');
+                            child.push_str(key);
+                        } else {
+                            child.push('[');
+                            dump_string(key, &mut child);
+                            child.push(']');
+                        }
+                        stack.push((value, child));
+                    }
+                }
+                Json::JsonArray(values) => {
+                    out.push_str("[];\n");
+                    for (i, value) in values.iter().enumerate().rev() {
+                        stack.push((value, format!("{}[{}]", path, i)));
+                    }
+                }
+                // JavaScript's spellings, where JSON text has only null
+                Json::JsonNumber(n) if n.is_nan() => out.push_str("NaN;\n"),
+                Json::JsonNumber(n) if n.is_infinite() => out.push_str(if *n > 0.0 { "Infinity;\n" } else { "-Infinity;\n" }),
+                scalar => {
+                    out.push_str(&scalar.dump());
+                    out.push_str(";\n");
+                }
+            }
+        }
+        out
+    }
+}
+
+// Rebuilds a document from gron lines in any order. Positions nobody assigns,
+// such as array slots skipped by a `grep`, come back as null, and an
+// assignment of `{}` or `[]` keeps a container of that kind that is already
+// there. Any other assignment to a position that already has a value, or to
+// a position inside a scalar, is an error unless it repeats the same value,
+// so the order of the lines never changes the result. Blank input gives null.
+pub fn from_gron(text: &str) -> Result<Json, JsonError> {
+    let mut rebuilder = Rebuilder::new(text.lines().count());
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (segments, value) = parse_line(line).map_err(|e| at_line(e, i + 1))?;
+        let assigned = value.type_name();
+        rebuilder.insert(&segments, value, true).map_err(|clash| at_line(match clash {
+            Clash::Path(found, Segment::Index(index)) => JsonError::TypeError(format!("Cannot index {} with [{}]", found, index)),
+            Clash::Path(found, Segment::Key(k)) => JsonError::TypeError(format!("Cannot look up key {:?} in {}", k, found)),
+            Clash::Value(found) => JsonError::TypeError(format!("Cannot assign {} where {} is already assigned", assigned, found)),
+            Clash::TooLarge(index) => JsonError::IndexError(format!("Array index {} is too large", index)),
+        }, i + 1))?;
+    }
+    Ok(rebuilder.root)
+}
+
+fn parse_line(line: &str) -> Result<(Vec<Segment>, Json), JsonError> {
+    let expected = |what: &str, rest: &str| match rest.chars().next() {
+        Some(c) => JsonError::SyntaxError(format!("Expected {} but found '{}'", what, c)),
+        None => JsonError::SyntaxError(format!("Expected {} but the line ended", what)),
+    };
+    let mut rest = line.strip_prefix(ROOT).ok_or_else(|| JsonError::SyntaxError(format!("Expected an assignment to `{}`", ROOT)))?;
+    let mut segments = Vec::new();
+    loop {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(|c| !is_id_continue(c)).unwrap_or(tail.len());
+            if end == 0 {
+                return Err(expected("a key", tail));
+            }
+            segments.push(Segment::Key(tail[..end].to_string()));
+            rest = &tail[end..];
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let tail = if tail.starts_with('"') {
+                let (key, after) = parse_quoted(tail.as_bytes(), &ParseOptions::default())?;
+                segments.push(Segment::Key(key));
+                utils::rest(tail, after)
+            } else {
+                let end = tail.find(']').unwrap_or(tail.len());
+                let index = parse_array_index(&tail[..end]).ok_or_else(|| expected("an array index or a quoted key", tail))?;
+                segments.push(Segment::Index(index));
+                &tail[end..]
+            };
+            rest = tail.strip_prefix(']').ok_or_else(|| expected("']'", tail))?;
+        } else {
+            break;
+        }
+    }
+    let rest = rest.trim_start().strip_prefix('=').ok_or_else(|| expected("'='", rest.trim_start()))?;
+    // The value parser wants a JSON delimiter after literals, which ';' is not
+    let rest = rest.strip_suffix(';').unwrap_or(rest);
+    let (value, rest) = match rest.trim() {
+        "NaN" => (Json::JsonNumber(f64::NAN), ""),
+        "Infinity" => (Json::JsonNumber(f64::INFINITY), ""),
+        "-Infinity" => (Json::JsonNumber(f64::NEG_INFINITY), ""),
+        _ => core::parse(rest)?,
+    };
+    let rest = rest.trim_start();
+    if !rest.is_empty() {
+        return Err(expected("the end of the line", rest));
+    }
+    Ok((segments, value))
+}
+
+fn at_line(error: JsonError, line: usize) -> JsonError {
+    match error {
+        JsonError::LexicalError(message) => JsonError::LexicalError(format!("{} on line {}", message, line)),
+        JsonError::SyntaxError(message) => JsonError::SyntaxError(format!("{} on line {}", message, line)),
+        JsonError::TypeError(message) => JsonError::TypeError(format!("{} on line {}", message, line)),
+        JsonError::IndexError(message) => JsonError::IndexError(format!("{} on line {}", message, line)),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Json {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn test_to_gron() {
+        let json = parse(r#"{"servers": [{"port": 8080, "host": "a"}], "name": "x\"y", "a b": null, "true": false, "é": 1.5, "e": [], "o": {}}"#);
+        let expected = [
+            "json = {};",
+            r#"json["a b"] = null;"#,
+            "json.e = [];",
+            "json.name = \"x\\\"y\";",
+            "json.o = {};",
+            "json.servers = [];",
+            "json.servers[0] = {};",
+            "json.servers[0].host = \"a\";",
+            "json.servers[0].port = 8080;",
+            r#"json["true"] = false;"#,
+            "json.é = 1.5;",
+        ];
+        assert_eq!(json.to_gron(), expected.iter().map(|line| format!("{}\n", line)).collect::<String>());
+        assert_eq!(parse("3").to_gron(), "json = 3;\n");
+        assert_eq!(parse(r#"{"b": 1, "a": 2}"#).to_gron(), parse(r#"{"a": 2, "b": 1}"#).to_gron());
+    }
+
+    #[test]
+    fn test_from_gron() {
+        let text = "json = {};\njson.servers = [];\njson.servers[0] = {};\njson.servers[0].port = 8080;\njson[\"a\\nb\"] = [1];\n";
+        assert_eq!(from_gron(text).unwrap(), parse(r#"{"servers": [{"port": 8080}], "a\nb": [1]}"#));
+        // What is left after a grep
+        assert_eq!(from_gron("json.servers[2].port = 8080;").unwrap(), parse(r#"{"servers": [null, null, {"port": 8080}]}"#));
+        assert_eq!(from_gron("json.b = 1;\r\n\r\n  json.a  =  {} \njson.a.x = true").unwrap(), parse(r#"{"b": 1, "a": {"x": true}}"#));
+        assert_eq!(from_gron("json.a.x = 1;\njson.a = {};").unwrap(), parse(r#"{"a": {"x": 1}}"#));
+        assert_eq!(from_gron("json.a = null;\njson.b = null;\njson.a = null;").unwrap(), parse(r#"{"a": null, "b": null}"#));
+        assert_eq!(from_gron("json[1] = null;\njson[0].a = 1;").unwrap(), parse(r#"[{"a": 1}, null]"#));
+        assert_eq!(from_gron("json.$_x1 = 2;").unwrap(), parse(r#"{"$_x1": 2}"#));
+        assert_eq!(from_gron("").unwrap(), Json::JsonNull);
+    }
+
+    #[test]
+    fn test_from_gron_errors() {
+        for text in ["jsn = 1;", "json. = 1;", "json[01] = 1;", "json[x] = 1;", "json[\"a\" = 1;", "json.a 1;", "json.a = ;", "json.a = 1; 2", "json.a = {"] {
+            assert!(matches!(from_gron(text), Err(JsonError::SyntaxError(_) | JsonError::LexicalError(_))), "{}", text);
+        }
+        let error = from_gron("json = [];\njson.a = 1;").unwrap_err();
+        assert!(matches!(&error, JsonError::TypeError(message) if message.ends_with("on line 2")), "{:?}", error);
+        assert!(matches!(from_gron("json.a = 1;\njson.a[0] = 1;"), Err(JsonError::TypeError(_))));
+        assert!(matches!(from_gron("json[4294967295] = 1;"), Err(JsonError::IndexError(_))));
+        // An explicit null is a value like any other, in either order
+        for text in [
+            "json.a = null;\njson.a.b = 1;",
+            "json.a.b = 1;\njson.a = null;",
+            "json = null;\njson[0] = 1;",
+            "json[0] = 1;\njson = null;",
+            "json.a = 1;\njson.a = [];",
+            "json.a = [];\njson.a = 1;",
+            "json.a = 1;\njson.a = 2;",
+            "json.a = [1];\njson.a = [1];",
+        ] {
+            let error = from_gron(text).unwrap_err();
+            assert!(matches!(&error, JsonError::TypeError(message) if message.ends_with("on line 2")), "{}: {:?}", text, error);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let documents = [
+            r#"{"a": {"b": [1, 2]}}"#,
+            r#"{"": {"x.y": [[], {}, [[]], [{}]]}, "0": {"1": [true, null]}, "\u0001\t": "😀"}"#,
+            r#"[{"id": 1, "tags": ["a"]}, {"id": 2, "tags": []}, [], {}]"#,
+            r#"[[[[{"k\"\\": [0, -1.5, "s", 1e300]}]]]]"#,
+            "null",
+            "{}",
+        ];
+        for source in documents {
+            let json = parse(source);
+            assert_eq!(from_gron(&json.to_gron()).unwrap(), json, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_non_finite_round_trip() {
+        let json = Json::from_iter([("a", Json::JsonArray(vec![Json::JsonNumber(f64::INFINITY), Json::JsonNumber(f64::NEG_INFINITY), Json::JsonNumber(f64::NAN)]))]);
+        let text = json.to_gron();
+        assert_eq!(text, "json = {};\njson.a = [];\njson.a[0] = Infinity;\njson.a[1] = -Infinity;\njson.a[2] = NaN;\n");
+        let values = from_gron(&text).unwrap();
+        assert_eq!(values["a"][0], Json::JsonNumber(f64::INFINITY));
+        assert_eq!(values["a"][1], Json::JsonNumber(f64::NEG_INFINITY));
+        assert!(values["a"][2].as_f64().unwrap().is_nan());
+        assert_eq!(from_gron("json = -Infinity").unwrap(), Json::JsonNumber(f64::NEG_INFINITY));
+        assert!(from_gron("json = Infinity 1;").is_err());
+    }
+
+    #[test]
+    fn test_deep_round_trip() {
+        let depth = 1_000;
+        let mut json = Json::JsonNumber(1.0);
+        for _ in 0..depth {
+            json = Json::JsonArray(vec![json]);
+        }
+        let text = json.to_gron();
+        assert_eq!(text.lines().count(), depth + 1);
+        assert_eq!(from_gron(&text).unwrap(), json);
+    }
+}
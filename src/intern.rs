@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::core;
+use crate::options;
+use crate::string;
+use crate::utils;
+
+use core::JsonError;
+use options::ParseOptions;
+use string::parse_quoted_into;
+use utils::string_run;
+
+// Set of shared object keys. Every object parsed through the same interner
+// points at one allocation per distinct key, which is most of the memory
+// saved on arrays of records. Keep one across parses of similar documents,
+// e.g. the lines of an NDJSON file, to share keys between them too.
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    keys: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    pub fn intern(&mut self, key: &str) -> Arc<str> {
+        if let Some(shared) = self.keys.get(key) {
+            return shared.clone();
+        }
+        let shared: Arc<str> = Arc::from(key);
+        self.keys.insert(shared.clone());
+        shared
+    }
+
+    // Number of distinct keys seen
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
+
+// Per-parse state for object keys. Escaped keys are unescaped into one
+// reused buffer, so each key costs at most the allocation of its `Arc<str>`
+// and none once the interner has seen it.
+pub(crate) struct Keys<'i> {
+    scratch: String,
+    interner: Option<&'i mut Interner>,
+}
+
+impl<'i> Keys<'i> {
+    pub(crate) fn new(interner: Option<&'i mut Interner>) -> Self {
+        Keys { scratch: String::new(), interner }
+    }
+
+    // Parses the quoted key at the start of `source`
+    pub(crate) fn parse<'a>(&mut self, bytes: &'a [u8], options: &ParseOptions) -> Result<(Arc<str>, &'a [u8]), JsonError> {
+        // Keys without escapes, nearly all of them, are used straight from
+        // the source
+        let (run, end) = string_run(bytes, 1)?;
+        let (raw, tail) = if bytes.first() == Some(&b'"') && bytes.get(end) == Some(&b'"') {
+            (run, &bytes[end + 1..])
+        } else {
+            self.scratch.clear();
+            let tail = parse_quoted_into(bytes, options, &mut self.scratch)?;
+            (self.scratch.as_str(), tail)
+        };
+        let key = match &mut self.interner {
+            Some(interner) => interner.intern(raw),
+            None => Arc::from(raw),
+        };
+        Ok((key, tail))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Json, parse_with_interner, parse_with_options};
+
+    #[test]
+    fn test_interner_shares_keys() {
+        let mut interner = Interner::new();
+        let a = interner.intern("id");
+        let b = interner.intern("id");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(interner.len(), 1);
+        interner.intern("name");
+        assert_eq!(interner.len(), 2);
+    }
+
+    fn key_of(json: &Json, i: usize) -> Arc<str> {
+        let map = json[i].as_object().unwrap().clone();
+        map.into_iter().next().unwrap().0
+    }
+
+    #[test]
+    fn test_parse_interns_keys() {
+        let source = r#"[{"key":1},{"key":2}]"#;
+        let options = ParseOptions { intern_keys: true, ..ParseOptions::default() };
+        let json = parse_with_options(source, &options).unwrap();
+        assert!(Arc::ptr_eq(&key_of(&json, 0), &key_of(&json, 1)));
+        assert_eq!(json[1]["key"], Json::JsonNumber(2.0));
+
+        let plain = parse_with_options(source, &ParseOptions::default()).unwrap();
+        assert!(!Arc::ptr_eq(&key_of(&plain, 0), &key_of(&plain, 1)));
+        assert_eq!(plain, json);
+    }
+
+    #[test]
+    fn test_shared_interner_across_parses() {
+        let mut interner = Interner::new();
+        let first = parse_with_interner(r#"[{"a":1}]"#, &ParseOptions::default(), &mut interner).unwrap();
+        let second = parse_with_interner(r#"[{"a":2,"b":3}]"#, &ParseOptions::default(), &mut interner).unwrap();
+        assert!(Arc::ptr_eq(&key_of(&first, 0), &key_of(&second, 0)));
+        assert_eq!(interner.len(), 2);
+    }
+}
@@ -0,0 +1,595 @@
+use crate::core;
+use crate::map;
+use crate::options;
+use crate::unicode;
+
+use core::{Json, JsonError};
+use map::JsonMap;
+use options::ParseOptions;
+
+// JSON5 (https://spec.json5.org) is a superset of JSON: comments, trailing
+// commas, identifier keys, single-quoted strings, hexadecimal numbers,
+// Infinity/NaN and an extended set of whitespace.
+
+fn is_json5_ws(c: char) -> bool {
+    matches!(c,
+        '\u{0009}' | '\u{000A}' | '\u{000B}' | '\u{000C}' | '\u{000D}' | '\u{0020}'
+        | '\u{00A0}' | '\u{2028}' | '\u{2029}' | '\u{FEFF}'
+        // Remaining Unicode Space_Separator (Zs) characters
+        | '\u{1680}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}'
+    )
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+// IdentifierStart and IdentifierPart of ECMAScript: the Unicode ID_Start and
+// ID_Continue properties plus '$', '_', ZWNJ and ZWJ
+fn is_id_start(c: char) -> bool {
+    c == '$' || c == '_' || unicode::is_id_start(c)
+}
+
+pub(crate) fn is_id_continue(c: char) -> bool {
+    c == '$' || c == '\u{200C}' || c == '\u{200D}' || unicode::is_id_continue(c)
+}
+
+// Reserved words of ECMAScript 5.1 may not be emitted as bare keys for the
+// benefit of older consumers, even though JSON5 itself accepts them.
+const RESERVED_WORDS: &[&str] = &[
+    "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do",
+    "else", "enum", "export", "extends", "false", "finally", "for", "function", "if", "import", "in",
+    "instanceof", "new", "null", "return", "super", "switch", "this", "throw", "true", "try",
+    "typeof", "var", "void", "while", "with",
+];
+
+struct Json5Parser<'a> {
+    source: &'a str,
+    pos: usize,
+    options: &'a ParseOptions,
+    // Arrays and objects enclosing the current position, capped at
+    // `options.max_depth`
+    depth: usize,
+}
+
+impl<'a> Json5Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self, context: &str) -> JsonError {
+        match self.peek() {
+            Some(c) => JsonError::SyntaxError(format!("Unexpected character in {}: '{}'", context, c)),
+            None => JsonError::SyntaxError(format!("Unexpected end of input in {}", context)),
+        }
+    }
+
+    fn skip_ws(&mut self) -> Result<(), JsonError> {
+        loop {
+            let rest = self.rest();
+            if let Some(c) = self.peek()
+                && is_json5_ws(c) {
+                self.pos += c.len_utf8();
+            } else if rest.starts_with("//") {
+                let end = rest.find(is_line_terminator).unwrap_or(rest.len());
+                self.pos += end;
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let end = comment.find("*/")
+                    .ok_or_else(|| JsonError::SyntaxError("Unterminated block comment".to_string()))?;
+                self.pos += end + 4;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Json, JsonError> {
+        self.skip_ws()?;
+        match self.peek() {
+            Some('{' | '[') if self.depth >= self.options.max_depth => {
+                Err(JsonError::DepthError { max_depth: self.options.max_depth })
+            }
+            Some(open @ ('{' | '[')) => {
+                self.depth += 1;
+                let json = if open == '{' { self.parse_object() } else { self.parse_array() };
+                self.depth -= 1;
+                json
+            }
+            Some(quote @ ('"' | '\'')) => self.parse_string(quote).map(Json::JsonString),
+            Some('n') => self.parse_literal("null", Json::JsonNull),
+            Some('t') => self.parse_literal("true", Json::JsonBoolean(true)),
+            Some('f') => self.parse_literal("false", Json::JsonBoolean(false)),
+            Some(_) => self.parse_number(),
+            None => Err(JsonError::SyntaxError("Unexpected end of input".to_string())),
+        }
+    }
+
+    fn parse_literal(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        let rest = self.rest();
+        if rest.starts_with(word) && !rest[word.len()..].starts_with(is_id_continue) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(JsonError::SyntaxError(format!("Expected '{}' but found '{}'", word, &rest[..rest.floor_char_boundary(word.len() + 4)])))
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, JsonError> {
+        self.next(); // consume '{'
+        let mut map = JsonMap::new();
+        loop {
+            self.skip_ws()?;
+            if self.eat('}') {
+                break;
+            }
+            let key = match self.peek() {
+                Some(quote @ ('"' | '\'')) => self.parse_string(quote)?,
+                _ => self.parse_identifier()?,
+            };
+            self.skip_ws()?;
+            if !self.eat(':') {
+                return Err(JsonError::SyntaxError("Expected ':' after field name".to_string()));
+            }
+            let value = self.parse_value()?;
+            map.insert(key, value);
+            self.skip_ws()?;
+            if self.eat(',') {
+                continue;
+            } else if self.eat('}') {
+                break;
+            } else {
+                return Err(self.unexpected("object"));
+            }
+        }
+        Ok(Json::JsonObject(map))
+    }
+
+    fn parse_array(&mut self) -> Result<Json, JsonError> {
+        self.next(); // consume '['
+        let mut vec = Vec::new();
+        loop {
+            self.skip_ws()?;
+            if self.eat(']') {
+                break;
+            }
+            vec.push(self.parse_value()?);
+            self.skip_ws()?;
+            if self.eat(',') {
+                continue;
+            } else if self.eat(']') {
+                break;
+            } else {
+                return Err(self.unexpected("array"));
+            }
+        }
+        Ok(Json::JsonArray(vec))
+    }
+
+    fn parse_identifier(&mut self) -> Result<String, JsonError> {
+        let mut name = String::new();
+        loop {
+            let c = match self.peek() {
+                Some('\\') => {
+                    self.next();
+                    if !self.eat('u') {
+                        return Err(JsonError::LexicalError("Expected 'u' after '\\' in identifier".to_string()));
+                    }
+                    self.parse_unicode_escape()?
+                }
+                Some(c) if is_id_continue(c) => {
+                    self.next();
+                    c
+                }
+                _ => break,
+            };
+            let valid = if name.is_empty() { is_id_start(c) } else { is_id_continue(c) };
+            if !valid {
+                return Err(JsonError::LexicalError(format!("Invalid identifier character: '{}'", c)));
+            }
+            name.push(c);
+        }
+        if name.is_empty() {
+            return Err(self.unexpected("object key"));
+        }
+        Ok(name)
+    }
+
+    fn parse_hex(&mut self, digits: usize) -> Result<u32, JsonError> {
+        let rest = self.rest();
+        let hex = rest.get(..digits)
+            .filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| JsonError::LexicalError(format!("Expected {} hex digits in escape", digits)))?;
+        self.pos += digits;
+        Ok(u32::from_str_radix(hex, 16).unwrap())
+    }
+
+    // Reads the XXXX of a \uXXXX escape, combining surrogate pairs
+    fn parse_unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.parse_hex(4)?;
+        let code = if (0xD800..0xDC00).contains(&high) && self.rest().starts_with("\\u") {
+            let save = self.pos;
+            self.pos += 2;
+            let low = self.parse_hex(4)?;
+            if (0xDC00..0xE000).contains(&low) {
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            } else {
+                self.pos = save;
+                high
+            }
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| JsonError::LexicalError(format!("Invalid unicode escape: \\u{:04X}", code)))
+    }
+
+    fn parse_string(&mut self, quote: char) -> Result<String, JsonError> {
+        self.next(); // consume opening quote
+        let mut result = String::new();
+        loop {
+            let c = self.next().ok_or_else(|| JsonError::SyntaxError("Unterminated string".to_string()))?;
+            if c == quote {
+                return Ok(result);
+            }
+            match c {
+                '\\' => {
+                    let e = self.next().ok_or_else(|| JsonError::SyntaxError("Unterminated string".to_string()))?;
+                    match e {
+                        'b' => result.push('\u{0008}'),
+                        'f' => result.push('\u{000C}'),
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'v' => result.push('\u{000B}'),
+                        '0' => {
+                            if self.peek().is_some_and(|d| d.is_ascii_digit()) {
+                                return Err(JsonError::LexicalError("Octal escapes are not allowed".to_string()));
+                            }
+                            result.push('\0');
+                        }
+                        '1'..='9' => return Err(JsonError::LexicalError(format!("Invalid escape character : {}", e))),
+                        'x' => {
+                            let code = self.parse_hex(2)?;
+                            result.push(char::from_u32(code).unwrap());
+                        }
+                        'u' => result.push(self.parse_unicode_escape()?),
+                        // Line continuation
+                        '\r' => {
+                            self.eat('\n');
+                        }
+                        '\n' | '\u{2028}' | '\u{2029}' => {}
+                        other => result.push(other),
+                    }
+                }
+                '\n' | '\r' => return Err(JsonError::SyntaxError("Unescaped line terminator in string".to_string())),
+                other => result.push(other),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, JsonError> {
+        let negative = if self.eat('-') {
+            true
+        } else {
+            self.eat('+');
+            false
+        };
+        let sign = if negative { -1.0 } else { 1.0 };
+        let rest = self.rest();
+
+        for (word, value) in [("Infinity", f64::INFINITY), ("NaN", f64::NAN)] {
+            if rest.starts_with(word) && !rest[word.len()..].starts_with(is_id_continue) {
+                self.pos += word.len();
+                return Ok(Json::JsonNumber(sign * value));
+            }
+        }
+
+        if rest.starts_with("0x") || rest.starts_with("0X") {
+            let digits: &str = &rest[2..rest[2..].find(|c: char| !c.is_ascii_hexdigit()).map_or(rest.len(), |i| i + 2)];
+            if digits.is_empty() {
+                return Err(JsonError::SyntaxError("Expected digits after hexadecimal prefix".to_string()));
+            }
+            self.pos += 2 + digits.len();
+            let value = digits.chars().fold(0.0, |acc, c| acc * 16.0 + c.to_digit(16).unwrap() as f64);
+            return self.finish_number(sign * value);
+        }
+
+        let bytes = rest.as_bytes();
+        let mut end = 0;
+        let int_digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+        if int_digits > 1 && bytes[0] == b'0' {
+            return Err(JsonError::SyntaxError("Leading zeros are not allowed".to_string()));
+        }
+        end += int_digits;
+        let mut frac_digits = 0;
+        if bytes.get(end) == Some(&b'.') {
+            end += 1;
+            frac_digits = bytes[end..].iter().take_while(|b| b.is_ascii_digit()).count();
+            end += frac_digits;
+        }
+        if int_digits == 0 && frac_digits == 0 {
+            return Err(match self.peek() {
+                Some(c) => JsonError::SyntaxError(format!("Unexpected character: '{}'", c)),
+                None => JsonError::SyntaxError("No digits found in number".to_string()),
+            });
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            end += 1;
+            if matches!(bytes.get(end), Some(b'+' | b'-')) {
+                end += 1;
+            }
+            let exp_digits = bytes[end..].iter().take_while(|b| b.is_ascii_digit()).count();
+            if exp_digits == 0 {
+                return Err(JsonError::SyntaxError("Expected digits in exponent".to_string()));
+            }
+            end += exp_digits;
+        }
+        let value = rest[..end].parse::<f64>()
+            .map_err(|err| JsonError::SyntaxError(format!("{}", err)))?;
+        self.pos += end;
+        self.finish_number(sign * value)
+    }
+
+    // Numbers must not run into identifier characters, e.g. `1x` or `0x1g`
+    fn finish_number(&self, value: f64) -> Result<Json, JsonError> {
+        match self.peek() {
+            Some(c) if is_id_continue(c) => Err(JsonError::SyntaxError(format!("Unexpected character after number: '{}'", c))),
+            _ => Ok(Json::JsonNumber(value)),
+        }
+    }
+}
+
+// Of `options`, only `max_depth` applies: JSON5 already allows a byte order
+// mark as whitespace, and Infinity and NaN as numbers
+pub fn parse_json5_with_options(source: &str, options: &ParseOptions) -> Result<Json, JsonError> {
+    let mut parser = Json5Parser { source, pos: 0, options, depth: 0 };
+    let json = parser.parse_value()?;
+    parser.skip_ws()?;
+    if parser.pos < source.len() {
+        return Err(parser.unexpected("JSON5 document"));
+    }
+    Ok(json)
+}
+
+pub fn parse_json5(source: &str) -> Result<Json, JsonError> {
+    parse_json5_with_options(source, &ParseOptions::default())
+}
+
+pub(crate) fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(is_id_start)
+        && chars.all(is_id_continue)
+        && !RESERVED_WORDS.contains(&key)
+}
+
+fn dump_json5_string(s: &str, out: &mut String) {
+    // Prefer the quote that needs fewer escapes, as JSON5.stringify does
+    let quote = if s.matches('"').count() > s.matches('\'').count() { '\'' } else { '"' };
+    out.push(quote);
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\u{0008}' => out.push_str("\\b"),
+            '\u{000C}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{000B}' => out.push_str("\\v"),
+            '\0' => out.push_str("\\0"),
+            '\u{2028}' => out.push_str("\\u2028"),
+            '\u{2029}' => out.push_str("\\u2029"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c < ' ' => out.push_str(&format!("\\x{:02X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+}
+
+fn dump_json5_number(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        n.to_string()
+    }
+}
+
+fn dump_json5_value(json: &Json, out: &mut String) {
+    match json {
+        Json::JsonObject(fields) => {
+            out.push('{');
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                if is_identifier(key) {
+                    out.push_str(key);
+                } else {
+                    dump_json5_string(key, out);
+                }
+                out.push(':');
+                dump_json5_value(value, out);
+            }
+            out.push('}');
+        }
+        Json::JsonArray(elements) => {
+            out.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                dump_json5_value(element, out);
+            }
+            out.push(']');
+        }
+        Json::JsonString(s) => dump_json5_string(s, out),
+        Json::JsonNumber(n) => out.push_str(&dump_json5_number(*n)),
+        Json::JsonBoolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        Json::JsonNull => out.push_str("null"),
+    }
+}
+
+impl Json {
+    pub fn dump_json5(&self) -> String {
+        let mut out = String::new();
+        dump_json5_value(self, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::DEFAULT_MAX_DEPTH;
+
+    #[test]
+    fn test_parse_json5_spec_example() {
+        let src = r#"
+        // comments
+        {
+          unquoted: 'and you can quote me on that',
+          singleQuotes: 'I can use "double quotes" here',
+          lineBreaks: "Look, Mom! \
+No \\n's!",
+          hexadecimal: 0xdecaf,
+          leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+          positiveSign: +1,
+          trailingComma: 'in objects', andIn: ['arrays',],
+          "backwardsCompatible": "with JSON",
+          /* block */ $dollar_1: null,
+        }
+        "#;
+        let json = parse_json5(src).unwrap();
+        assert_eq!(json["unquoted"], Json::string("and you can quote me on that"));
+        assert_eq!(json["singleQuotes"], Json::string("I can use \"double quotes\" here"));
+        assert_eq!(json["lineBreaks"], Json::string("Look, Mom! No \\n's!"));
+        assert_eq!(json["hexadecimal"], Json::JsonNumber(912559.0));
+        assert_eq!(json["leadingDecimalPoint"], Json::JsonNumber(0.8675309));
+        assert_eq!(json["andTrailing"], Json::JsonNumber(8675309.0));
+        assert_eq!(json["positiveSign"], Json::JsonNumber(1.0));
+        assert_eq!(json["andIn"][0], Json::string("arrays"));
+        assert_eq!(json["backwardsCompatible"], Json::string("with JSON"));
+        assert!(json["$dollar_1"].is_null());
+    }
+
+    #[test]
+    fn test_parse_json5_numbers() {
+        assert_eq!(parse_json5("Infinity"), Ok(Json::JsonNumber(f64::INFINITY)));
+        assert_eq!(parse_json5("-Infinity"), Ok(Json::JsonNumber(f64::NEG_INFINITY)));
+        assert!(parse_json5("NaN").unwrap().as_f64().unwrap().is_nan());
+        assert_eq!(parse_json5("-0x1F"), Ok(Json::JsonNumber(-31.0)));
+        assert_eq!(parse_json5("+.5e1"), Ok(Json::JsonNumber(5.0)));
+        assert_eq!(parse_json5("5.e-1"), Ok(Json::JsonNumber(0.5)));
+        assert!(parse_json5("01").is_err());
+        assert!(parse_json5(".").is_err());
+        assert!(parse_json5("0x").is_err());
+        assert!(parse_json5("1e").is_err());
+        assert!(parse_json5("Infinityx").is_err());
+        assert!(parse_json5("12abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_json5_strings() {
+        assert_eq!(parse_json5(r"'\x41B\v\0'"), Ok(Json::string("AB\u{000B}\0")));
+        assert_eq!(parse_json5(r"'\a\''"), Ok(Json::string("a'")));
+        assert_eq!(parse_json5(r#""😀""#), Ok(Json::string("😀")));
+        assert_eq!(parse_json5("'a\\\r\nb'"), Ok(Json::string("ab")));
+        assert_eq!(parse_json5("'\u{2028}'"), Ok(Json::string("\u{2028}")));
+        assert!(parse_json5("'a\nb'").is_err());
+        assert!(parse_json5(r"'\1'").is_err());
+        assert!(parse_json5(r"'\01'").is_err());
+        assert!(parse_json5(r"'\xZ1'").is_err());
+        assert!(parse_json5("'open").is_err());
+    }
+
+    #[test]
+    fn test_parse_json5_whitespace_and_comments() {
+        let src = "\u{FEFF}\u{00A0}[\u{2028}1,\u{3000}// c\n2 /* x */\u{000B}]\u{000C}";
+        assert_eq!(parse_json5(src), Ok(json!([1, 2])));
+        assert!(parse_json5("[1 /* open").is_err());
+        assert!(parse_json5("// only a comment").is_err());
+    }
+
+    #[test]
+    fn test_parse_json5_keys() {
+        let json = parse_json5(r"{ a_b: 1, ünïcode: 2, abc: 3, 'x y': 4 }").unwrap();
+        assert_eq!(json["a_b"], Json::JsonNumber(1.0));
+        assert_eq!(json["ünïcode"], Json::JsonNumber(2.0));
+        assert_eq!(json["abc"], Json::JsonNumber(3.0));
+        assert_eq!(json["x y"], Json::JsonNumber(4.0));
+        assert!(parse_json5("{ 1a: 1 }").is_err());
+        assert!(parse_json5("{ a b: 1 }").is_err());
+        // Combining marks, connector punctuation and Other_ID_Start
+        let json = parse_json5("{ a\u{0301}: 1, x\u{203F}y: 2, \u{2118}: 3, \\u0061\u{0903}: 4 }").unwrap();
+        assert_eq!(json["a\u{0301}"], Json::JsonNumber(1.0));
+        assert_eq!(json["x\u{203F}y"], Json::JsonNumber(2.0));
+        assert_eq!(json["\u{2118}"], Json::JsonNumber(3.0));
+        assert_eq!(json["a\u{0903}"], Json::JsonNumber(4.0));
+        assert!(parse_json5("{ \u{0301}a: 1 }").is_err());
+        assert!(parse_json5("{ a\u{20AC}: 1 }").is_err());
+        assert_eq!(json!({"a\u{0301}": 1}).dump_json5(), "{a\u{0301}:1}");
+    }
+
+    #[test]
+    fn test_parse_json5_invalid_structure() {
+        assert!(parse_json5("[1,,]").is_err());
+        assert!(parse_json5("[,]").is_err());
+        assert!(parse_json5("{a:1,,}").is_err());
+        assert!(parse_json5("{a 1}").is_err());
+        assert!(parse_json5("[1] 2").is_err());
+        assert!(parse_json5("").is_err());
+    }
+
+    #[test]
+    fn test_parse_json5_depth_limit() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse_json5(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(parse_json5(&nested(DEFAULT_MAX_DEPTH + 1)), Err(JsonError::DepthError { max_depth: DEFAULT_MAX_DEPTH }));
+        let shallow = ParseOptions { max_depth: 2, ..ParseOptions::default() };
+        assert!(parse_json5_with_options("[{a: []}]", &shallow).is_err());
+        assert!(parse_json5_with_options("[{a: 1}]", &shallow).is_ok());
+        let deep = ParseOptions { max_depth: 500, ..ParseOptions::default() };
+        assert!(parse_json5_with_options(&nested(500), &deep).is_ok());
+        assert!(matches!(parse_json5(&"[{a:".repeat(100_000)), Err(JsonError::DepthError { .. })));
+    }
+
+    #[test]
+    fn test_dump_json5() {
+        let json = json!({"plain": 1, "with space": "it's", "class": [true, null], "q": "say \"hi\""});
+        assert_eq!(
+            json.dump_json5(),
+            r#"{plain:1,"with space":"it's","class":[true,null],q:'say "hi"'}"#
+        );
+        let special = json!([f64::NAN, f64::INFINITY, f64::NEG_INFINITY, "\u{2028}\n"]);
+        assert_eq!(special.dump_json5(), r#"[NaN,Infinity,-Infinity,"\u2028\n"]"#);
+    }
+
+    #[test]
+    fn test_json5_round_trip() {
+        let json = json!({"a": [1.5, "x'\"y"], "b c": {"$d": false}, "": null});
+        assert_eq!(parse_json5(&json.dump_json5()), Ok(json));
+    }
+}
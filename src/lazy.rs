@@ -0,0 +1,488 @@
+use std::borrow::Cow;
+
+use crate::core;
+use crate::core::{parse_value, strip_bom};
+use crate::utils;
+use crate::options;
+use crate::string;
+use crate::intern;
+
+use core::{Json, JsonError};
+use utils::{is_delim, is_string_end, skip_ws};
+use options::ParseOptions;
+use string::parse_quoted;
+use intern::{Interner, Keys};
+
+static DEFAULT_OPTIONS: ParseOptions = ParseOptions::new();
+
+// A value in JSON text that has not been parsed yet. Navigating only scans
+// the siblings on the way, without allocating or recursing, and a value is
+// fully checked only when it is read. Parts of the document that are never
+// visited are therefore not validated; `to_json` validates a whole subtree.
+#[derive(Debug, Clone, Copy)]
+pub struct LazyValue<'a> {
+    // From the first byte of the value to the end of the document
+    source: &'a str,
+    options: &'a ParseOptions,
+    depth: usize,
+}
+
+pub fn parse_lazy(source: &str) -> Result<LazyValue<'_>, JsonError> {
+    parse_lazy_with_options(source, &DEFAULT_OPTIONS)
+}
+
+// Only checks that a value starts here; trailing characters after the
+// document go unnoticed unless the root is read with `to_json`
+pub fn parse_lazy_with_options<'a>(source: &'a str, options: &'a ParseOptions) -> Result<LazyValue<'a>, JsonError> {
+    let source = strip_bom(source, options)?;
+    let start = skip_ws(source.as_bytes(), 0);
+    if start == source.len() {
+        return Err(JsonError::SyntaxError("Unexpected end of input".to_string()));
+    }
+    Ok(LazyValue { source: &source[start..], options, depth: 0 })
+}
+
+fn expected(kind: &str, found: &LazyValue) -> JsonError {
+    JsonError::TypeError(format!("Expected {} but found {}", kind, found.type_name()))
+}
+
+impl<'a> LazyValue<'a> {
+    fn first(&self) -> u8 {
+        self.source.as_bytes()[0]
+    }
+
+    // Byte offset of the value in `source`, the text the root came from
+    pub(crate) fn offset_in(&self, source: &str) -> usize {
+        source.len() - self.source.len()
+    }
+
+    // Judged from the first byte only
+    pub fn type_name(&self) -> &'static str {
+        match self.first() {
+            b'{' => "object",
+            b'[' => "array",
+            b'"' => "string",
+            b't' | b'f' => "boolean",
+            b'n' => "null",
+            _ => "number",
+        }
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.first() == b'{'
+    }
+    pub fn is_array(&self) -> bool {
+        self.first() == b'['
+    }
+
+    // The exact text of the value, found by skipping over it
+    pub fn raw(&self) -> Result<&'a str, JsonError> {
+        let end = skip_value(self.source.as_bytes(), 0, self.options, self.depth)?;
+        Ok(&self.source[..end])
+    }
+
+    // First member named `key`. Unlike `Json`, which keeps the last of
+    // duplicate keys, this stops at the first so the rest is never scanned.
+    pub fn get(&self, key: &str) -> Result<LazyValue<'a>, JsonError> {
+        let mut members = self.members()?;
+        while let Some((name, value)) = members.advance()? {
+            if name == key {
+                return Ok(value);
+            }
+        }
+        Err(JsonError::IndexError(format!("Key not found: {:?}", key)))
+    }
+
+    pub fn at(&self, idx: usize) -> Result<LazyValue<'a>, JsonError> {
+        let mut elements = self.iter()?;
+        let mut len = 0;
+        while let Some(value) = elements.advance()? {
+            if len == idx {
+                return Ok(value);
+            }
+            len += 1;
+        }
+        Err(JsonError::IndexError(format!("Index {} out of bounds for array of length {}", idx, len)))
+    }
+
+    pub fn iter(&self) -> Result<LazyElements<'a>, JsonError> {
+        if !self.is_array() {
+            return Err(expected("array", self));
+        }
+        Ok(LazyElements(Cursor::new(*self, b']')))
+    }
+
+    pub fn members(&self) -> Result<LazyMembers<'a>, JsonError> {
+        if !self.is_object() {
+            return Err(expected("object", self));
+        }
+        Ok(LazyMembers(Cursor::new(*self, b'}')))
+    }
+
+    // Number of elements or members, counted by scanning the container
+    pub fn len(&self) -> Result<usize, JsonError> {
+        let mut len = 0;
+        if self.is_object() {
+            let mut members = self.members()?;
+            while members.advance()?.is_some() {
+                len += 1;
+            }
+        } else {
+            let mut elements = self.iter()?;
+            while elements.advance()?.is_some() {
+                len += 1;
+            }
+        }
+        Ok(len)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, JsonError> {
+        Ok(self.len()? == 0)
+    }
+
+    // Borrowed from the source unless the string has escapes
+    pub fn as_str(&self) -> Result<Cow<'a, str>, JsonError> {
+        if self.first() != b'"' {
+            return Err(expected("string", self));
+        }
+        if let Some(raw) = plain_string(self.source) {
+            return Ok(Cow::Borrowed(raw));
+        }
+        let (s, _) = parse_quoted(self.source.as_bytes(), self.options)?;
+        Ok(Cow::Owned(s))
+    }
+
+    fn scalar(&self) -> Result<Json, JsonError> {
+        if self.is_object() || self.is_array() {
+            return Ok(Json::JsonNull);
+        }
+        let (json, _) = parse_value(self.source.as_bytes(), self.options, self.depth, &mut Keys::new(None))?;
+        Ok(json)
+    }
+
+    pub fn as_f64(&self) -> Result<f64, JsonError> {
+        self.scalar()?.as_f64().ok_or_else(|| expected("number", self))
+    }
+    pub fn as_i64(&self) -> Result<i64, JsonError> {
+        self.scalar()?.as_i64().ok_or_else(|| expected("i64", self))
+    }
+    pub fn as_u64(&self) -> Result<u64, JsonError> {
+        self.scalar()?.as_u64().ok_or_else(|| expected("u64", self))
+    }
+    pub fn as_bool(&self) -> Result<bool, JsonError> {
+        self.scalar()?.as_bool().ok_or_else(|| expected("boolean", self))
+    }
+    pub fn is_null(&self) -> Result<bool, JsonError> {
+        Ok(self.first() == b'n' && self.scalar()?.is_null())
+    }
+
+    // Parses and validates the whole subtree
+    pub fn to_json(&self) -> Result<Json, JsonError> {
+        let mut interner = Interner::new();
+        let mut keys = Keys::new(self.options.intern_keys.then_some(&mut interner));
+        let (json, _) = parse_value(self.source.as_bytes(), self.options, self.depth, &mut keys)?;
+        Ok(json)
+    }
+}
+
+// Contents of a string with no escapes, straight from the source
+fn plain_string(source: &str) -> Option<&str> {
+    let bytes = source.as_bytes();
+    let mut end = 1;
+    while end < bytes.len() && !is_string_end(bytes[end]) {
+        end += 1;
+    }
+    (bytes.get(end) == Some(&b'"')).then(|| &source[1..end])
+}
+
+// Position just past the string whose opening quote is at `pos`
+fn skip_string(bytes: &[u8], mut pos: usize) -> Result<usize, JsonError> {
+    pos += 1;
+    loop {
+        while pos < bytes.len() && !is_string_end(bytes[pos]) {
+            pos += 1;
+        }
+        match bytes.get(pos) {
+            Some(b'"') => return Ok(pos + 1),
+            Some(b'\\') => pos += 2,
+            Some(&b) => return Err(JsonError::LexicalError(format!("Unescaped control character U+{:04X} in string", b))),
+            None => return Err(JsonError::SyntaxError("Unexpected end of input while parsing string".to_string())),
+        }
+    }
+}
+
+// Which closer each open container expects, one bit per level. Only nesting
+// deeper than 64 levels allocates.
+struct Closers {
+    inline: u64,
+    spilled: Vec<bool>,
+    len: usize,
+}
+
+impl Closers {
+    fn push(&mut self, is_object: bool) {
+        if self.len < 64 {
+            self.inline = (self.inline & !(1 << self.len)) | ((is_object as u64) << self.len);
+        } else {
+            self.spilled.push(is_object);
+        }
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<bool> {
+        self.len = self.len.checked_sub(1)?;
+        if self.len < 64 {
+            Some(self.inline & (1 << self.len) != 0)
+        } else {
+            self.spilled.pop()
+        }
+    }
+}
+
+// Position just past the value starting at `pos`. Containers are skipped by
+// matching brackets outside of strings; their commas, colons and scalars
+// are left for whoever reads them.
+fn skip_value(bytes: &[u8], pos: usize, options: &ParseOptions, depth: usize) -> Result<usize, JsonError> {
+    match bytes.get(pos) {
+        Some(b'"') => skip_string(bytes, pos),
+        Some(b'{' | b'[') => {
+            let mut closers = Closers { inline: 0, spilled: Vec::new(), len: 0 };
+            let mut pos = pos;
+            loop {
+                match bytes.get(pos) {
+                    Some(b'"') => {
+                        pos = skip_string(bytes, pos)?;
+                        continue;
+                    }
+                    Some(&open @ (b'{' | b'[')) => {
+                        if depth + closers.len >= options.max_depth {
+                            return Err(JsonError::DepthError { max_depth: options.max_depth });
+                        }
+                        closers.push(open == b'{');
+                    }
+                    Some(&close @ (b'}' | b']')) => {
+                        if closers.pop() != Some(close == b'}') {
+                            return Err(JsonError::SyntaxError(format!("Unexpected '{}'", close as char)));
+                        }
+                        if closers.len == 0 {
+                            return Ok(pos + 1);
+                        }
+                    }
+                    Some(_) => {}
+                    None => return Err(JsonError::SyntaxError("Unexpected end of input".to_string())),
+                }
+                pos += 1;
+            }
+        }
+        Some(_) => {
+            let mut end = pos;
+            while !is_delim(bytes.get(end).copied()) {
+                end += 1;
+            }
+            if end == pos {
+                return Err(JsonError::SyntaxError(format!("Unexpected '{}'", bytes[pos] as char)));
+            }
+            Ok(end)
+        }
+        None => Err(JsonError::SyntaxError("Unexpected end of input".to_string())),
+    }
+}
+
+// Walks the items of one container, skipping each value only once the next
+// item is asked for
+struct Cursor<'a> {
+    container: LazyValue<'a>,
+    close: u8,
+    pos: usize,
+    // Start of the item handed out last, still to be skipped
+    pending: Option<usize>,
+    done: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(container: LazyValue<'a>, close: u8) -> Self {
+        Cursor { container, close, pos: 1, pending: None, done: false }
+    }
+
+    fn kind(&self) -> &'static str {
+        if self.close == b'}' { "Object" } else { "Array" }
+    }
+
+    // Position of the next item, or None past the closing bracket
+    fn next_item(&mut self) -> Result<Option<usize>, JsonError> {
+        if self.done {
+            return Ok(None);
+        }
+        let bytes = self.container.source.as_bytes();
+        let depth = self.container.depth + 1;
+        let mut after_comma = false;
+        match self.pending.take() {
+            None => self.pos = skip_ws(bytes, self.pos),
+            Some(start) => {
+                self.pos = skip_ws(bytes, skip_value(bytes, start, self.container.options, depth)?);
+                match bytes.get(self.pos) {
+                    Some(b',') => {
+                        self.pos = skip_ws(bytes, self.pos + 1);
+                        after_comma = true;
+                    }
+                    Some(&b) if b == self.close => {}
+                    Some(&b) => return Err(JsonError::SyntaxError(format!("Unexpected character in {}: '{}'", self.kind().to_lowercase(), b as char))),
+                    None => {}
+                }
+            }
+        }
+        match bytes.get(self.pos) {
+            None => Err(JsonError::SyntaxError(format!("Unexpected end of input in {}", self.kind().to_lowercase()))),
+            Some(&b) if b == self.close && after_comma => {
+                Err(JsonError::SyntaxError(format!("JSON {} should not end in a comma", self.kind())))
+            }
+            Some(&b) if b == self.close => {
+                self.done = true;
+                Ok(None)
+            }
+            Some(_) => Ok(Some(self.pos)),
+        }
+    }
+
+    fn value_at(&mut self, pos: usize) -> LazyValue<'a> {
+        self.pending = Some(pos);
+        LazyValue { source: &self.container.source[pos..], options: self.container.options, depth: self.container.depth + 1 }
+    }
+}
+
+pub struct LazyElements<'a>(Cursor<'a>);
+
+impl<'a> LazyElements<'a> {
+    // Text from the cursor on; starts at the closing bracket once exhausted
+    pub(crate) fn rest(&self) -> &'a str {
+        &self.0.container.source[self.0.pos..]
+    }
+
+    fn advance(&mut self) -> Result<Option<LazyValue<'a>>, JsonError> {
+        match self.0.next_item()? {
+            Some(pos) => Ok(Some(self.0.value_at(pos))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a> Iterator for LazyElements<'a> {
+    type Item = Result<LazyValue<'a>, JsonError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.advance();
+        if item.is_err() {
+            self.0.done = true;
+        }
+        item.transpose()
+    }
+}
+
+pub struct LazyMembers<'a>(Cursor<'a>);
+
+impl<'a> LazyMembers<'a> {
+    fn advance(&mut self) -> Result<Option<(Cow<'a, str>, LazyValue<'a>)>, JsonError> {
+        let Some(pos) = self.0.next_item()? else {
+            return Ok(None);
+        };
+        let source = self.0.container.source;
+        let bytes = source.as_bytes();
+        let key = LazyValue { source: &source[pos..], ..self.0.container };
+        if bytes[pos] != b'"' {
+            return Err(JsonError::SyntaxError(format!("Expected a '\"' as start of JSON object field but found '{}'", bytes[pos] as char)));
+        }
+        let name = key.as_str()?;
+        let colon = skip_ws(bytes, skip_string(bytes, pos)?);
+        if bytes.get(colon) != Some(&b':') {
+            return Err(JsonError::SyntaxError("Expected ':' after field name".to_string()));
+        }
+        let start = skip_ws(bytes, colon + 1);
+        if start == bytes.len() {
+            return Err(JsonError::SyntaxError("Unexpected end of input in object".to_string()));
+        }
+        Ok(Some((name, self.0.value_at(start))))
+    }
+}
+
+impl<'a> Iterator for LazyMembers<'a> {
+    type Item = Result<(Cow<'a, str>, LazyValue<'a>), JsonError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.advance();
+        if item.is_err() {
+            self.0.done = true;
+        }
+        item.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_with_options;
+
+    const DOC: &str = r#" {"meta": {"count": 2, "skip": [[{"}": "]"}], "\"x\""]},
+        "data": [{"id": 1, "name": "a"}, {"id": 2, "name": "bé\n"}, [], {"id": 18446744073709549568}],
+        "flag": true, "none": null} "#;
+
+    #[test]
+    fn test_lazy_navigation() -> Result<(), JsonError> {
+        let doc = parse_lazy(DOC)?;
+        assert_eq!(doc.get("data")?.at(3)?.get("id")?.as_u64()?, 18446744073709549568);
+        assert_eq!(doc.get("data")?.at(1)?.get("name")?.as_str()?, "bé\n");
+        assert!(matches!(doc.get("data")?.at(0)?.get("name")?.as_str()?, Cow::Borrowed("a")));
+        assert_eq!(doc.get("meta")?.get("count")?.as_i64()?, 2);
+        assert!(doc.get("flag")?.as_bool()?);
+        assert!(doc.get("none")?.is_null()?);
+        assert_eq!(doc.get("data")?.len()?, 4);
+        assert!(doc.get("data")?.at(2)?.is_empty()?);
+        assert_eq!(doc.get("meta")?.get("skip")?.raw()?, r#"[[{"}": "]"}], "\"x\""]"#);
+        let keys: Vec<Cow<str>> = doc.members()?.map(|m| m.map(|(k, _)| k)).collect::<Result<_, _>>()?;
+        assert_eq!(keys, ["meta", "data", "flag", "none"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lazy_errors() {
+        let doc = parse_lazy(DOC).unwrap();
+        assert!(matches!(doc.get("missing"), Err(JsonError::IndexError(_))));
+        assert!(matches!(doc.get("data").unwrap().at(4), Err(JsonError::IndexError(_))));
+        assert!(matches!(doc.at(0), Err(JsonError::TypeError(_))));
+        assert!(matches!(doc.get("flag").unwrap().as_u64(), Err(JsonError::TypeError(_))));
+        assert!(parse_lazy("  ").is_err());
+        assert!(parse_lazy(r#"{"a": [1, 2}, "b": 1}"#).unwrap().get("b").is_err());
+        assert!(parse_lazy(r#"{"a": "open, "b": 1}"#).unwrap().get("b").is_err());
+        assert!(parse_lazy(r#"[1, 2,]"#).unwrap().at(2).is_err());
+        assert!(parse_lazy(r#"[tru]"#).unwrap().at(0).unwrap().as_bool().is_err());
+        assert!(parse_lazy(r#"{"a" 1}"#).unwrap().get("a").is_err());
+        // Truncated input
+        assert!(matches!(parse_lazy("{").unwrap().get("a"), Err(JsonError::SyntaxError(_))));
+        assert!(matches!(parse_lazy("[").unwrap().at(0), Err(JsonError::SyntaxError(_))));
+        assert!(matches!(parse_lazy(r#"{"a":1,"#).unwrap().get("b"), Err(JsonError::SyntaxError(_))));
+        assert!(matches!(parse_lazy(r#"{"a":1 "#).unwrap().get("b"), Err(JsonError::SyntaxError(_))));
+        assert!(matches!(parse_lazy("[1,").unwrap().at(1), Err(JsonError::SyntaxError(_))));
+        assert!(matches!(parse_lazy("[1, ").unwrap().len(), Err(JsonError::SyntaxError(_))));
+    }
+
+    #[test]
+    fn test_lazy_skips_only_what_it_passes() {
+        // Garbage after the visited part is never looked at
+        let doc = parse_lazy(r#"[1, {"a": 2}, nonsense"#).unwrap();
+        assert_eq!(doc.at(1).unwrap().get("a").unwrap().as_f64().unwrap(), 2.0);
+        assert!(doc.at(2).unwrap().to_json().is_err());
+        assert!(doc.len().is_err());
+    }
+
+    #[test]
+    fn test_lazy_to_json_and_depth() {
+        let doc = parse_lazy(DOC).unwrap();
+        let json = parse_with_options(DOC, &ParseOptions::default()).unwrap();
+        assert_eq!(doc.to_json().unwrap(), json);
+        assert_eq!(doc.get("data").unwrap().to_json().unwrap(), json["data"]);
+
+        let shallow = ParseOptions { max_depth: 2, ..ParseOptions::default() };
+        let doc = parse_lazy_with_options("[[0], [[1]]]", &shallow).unwrap();
+        assert_eq!(doc.at(0).unwrap().at(0).unwrap().as_f64().unwrap(), 0.0);
+        assert!(doc.at(1).unwrap().raw().is_err());
+        let deep = format!("[{}{}]", "[".repeat(100), "]".repeat(100));
+        assert!(parse_lazy(&deep).unwrap().len().is_ok());
+    }
+}
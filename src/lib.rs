@@ -1,27 +1,88 @@
+#[macro_use]
+mod macros;
 mod core;
 mod utils;
+mod options;
+mod encoding;
+mod structural;
+mod tape;
+mod intern;
+mod lazy;
+mod parallel;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
 
 mod object;
 mod array;
 mod string;
 mod literals;
 mod number;
+mod accessors;
+mod convert;
+mod map;
+mod mutation;
+mod lookup;
+mod json5;
+mod unicode;
+mod diagnostic;
+mod recover;
+mod render;
+mod canonical;
+mod compare;
+mod pointer;
+mod visit;
+mod binary;
+mod cbor;
+mod msgpack;
+mod csv;
+mod flatten;
+mod gron;
 
-pub use core::{Json, JsonError, parse};
+pub use core::{Json, JsonError, parse, parse_with_interner, parse_with_options};
+pub use encoding::{parse_bytes, parse_bytes_with_options};
+pub use structural::{parse_indexed, parse_indexed_with_options, structural_index};
+pub use intern::Interner;
+pub use lazy::{LazyElements, LazyMembers, LazyValue, parse_lazy, parse_lazy_with_options};
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::{Mmap, MmapError};
+pub use parallel::{ParallelError, parse_array_parallel, parse_ndjson_parallel};
+pub use tape::{Elements, JsonTape, Members, TapeRef};
+pub use options::{DEFAULT_MAX_DEPTH, NumberOverflow, ParseOptions, SurrogatePolicy};
+pub use convert::ToJson;
+pub use map::{JsonMap, Entry, OccupiedEntry, VacantEntry};
+pub use lookup::Lookup;
+pub use pointer::JsonPointer;
+pub use binary::{BytesPolicy, KeyPolicy, NonFinitePolicy};
+pub use cbor::{CborOptions, TagPolicy, from_cbor, from_cbor_with_options, to_cbor};
+pub use msgpack::{ExtPolicy, MsgpackError, MsgpackOptions, MsgpackReader, from_msgpack, from_msgpack_with_options, to_msgpack};
+pub use csv::{ArrayPolicy, ColumnStyle, CsvOptions, from_csv, to_csv};
+pub use flatten::{EmptyContainers, FlatKeys, FlattenOptions, NumericSegments};
+pub use gron::from_gron;
+pub use visit::{Descendants, Visitor, VisitorMut, Walk};
+pub use json5::{parse_json5, parse_json5_with_options};
+pub use diagnostic::{Diagnostic, ErrorCode, Severity, Span};
+pub use recover::{Recovered, parse_tolerant, parse_tolerant_with_options};
+pub use render::{RenderMode, RenderOptions, line_col, render_diagnostic, render_diagnostics};
 
-impl From<&str> for Json {
-    fn from(val: &str) -> Json {
-        let result : Result<(Json, &str), JsonError> = parse(val);
-        if let Ok((json, _)) = result {
-            json
-        } else {
-            Json::JsonEmpty
-        }
+use std::str::FromStr;
+
+impl FromStr for Json {
+    type Err = JsonError;
+    fn from_str(val: &str) -> Result<Json, JsonError> {
+        parse_with_options(val, &ParseOptions::default())
+    }
+}
+
+impl TryFrom<&str> for Json {
+    type Error = JsonError;
+    fn try_from(val: &str) -> Result<Json, JsonError> {
+        val.parse()
     }
 }
 
-impl From<String> for Json {
-    fn from(val: String) -> Json {
-        Json::from(val.as_str())
+impl TryFrom<String> for Json {
+    type Error = JsonError;
+    fn try_from(val: String) -> Result<Json, JsonError> {
+        val.parse()
     }
 }
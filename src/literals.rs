@@ -1,44 +1,42 @@
 use crate::core;
+use crate::utils;
 
 use core::{Json, JsonError};
+use utils::{excerpt, is_delim};
 
-fn is_json_delim(c: Option<char>) -> bool {
-    matches!(c, None | Some(',') | Some('}') | Some(']') | Some(':') | Some(' ') | Some('\t') | Some('\n') | Some('\r'))
-}
-
-pub fn parse_true(source: &str) -> Result<(Json, &str), JsonError> {
-    if let Some(rest) = source.strip_prefix("true") {
-        if is_json_delim(rest.chars().next()) {
+pub fn parse_true(source: &[u8]) -> Result<(Json, &[u8]), JsonError> {
+    if let Some(rest) = source.strip_prefix(b"true") {
+        if is_delim(rest.first().copied()) {
             Ok((Json::JsonBoolean(true), rest))
         } else {
-            Err(JsonError::SyntaxError(format!("Expected 'true' but found '{}'", &source[..source.len().min(8)])))
+            Err(JsonError::SyntaxError(format!("Expected 'true' but found '{}'", excerpt(source, 8)?)))
         }
     } else {
-        Err(JsonError::SyntaxError(format!("Expected 'true' but found '{}'", &source[..source.len().min(4)])))
+        Err(JsonError::SyntaxError(format!("Expected 'true' but found '{}'", excerpt(source, 4)?)))
     }
 }
 
-pub fn parse_false(source: &str) -> Result<(Json, &str), JsonError> {
-    if let Some(rest) = source.strip_prefix("false") {
-        if is_json_delim(rest.chars().next()) {
+pub fn parse_false(source: &[u8]) -> Result<(Json, &[u8]), JsonError> {
+    if let Some(rest) = source.strip_prefix(b"false") {
+        if is_delim(rest.first().copied()) {
             Ok((Json::JsonBoolean(false), rest))
         } else {
-            Err(JsonError::SyntaxError(format!("Expected 'false' but found '{}'", &source[..source.len().min(9)])))
+            Err(JsonError::SyntaxError(format!("Expected 'false' but found '{}'", excerpt(source, 9)?)))
         }
     } else {
-        Err(JsonError::SyntaxError(format!("Expected 'false' but found '{}'", &source[..source.len().min(5)])))
+        Err(JsonError::SyntaxError(format!("Expected 'false' but found '{}'", excerpt(source, 5)?)))
     }
 }
 
-pub fn parse_null(source: &str) -> Result<(Json, &str), JsonError> {
-    if let Some(rest) = source.strip_prefix("null") {
-        if is_json_delim(rest.chars().next()) {
+pub fn parse_null(source: &[u8]) -> Result<(Json, &[u8]), JsonError> {
+    if let Some(rest) = source.strip_prefix(b"null") {
+        if is_delim(rest.first().copied()) {
             Ok((Json::JsonNull, rest))
         } else {
-            Err(JsonError::SyntaxError(format!("Expected 'null' but found '{}'", &source[..source.len().min(8)])))
+            Err(JsonError::SyntaxError(format!("Expected 'null' but found '{}'", excerpt(source, 8)?)))
         }
     } else {
-        Err(JsonError::SyntaxError(format!("Expected 'null' but found '{}'", &source[..source.len().min(4)])))
+        Err(JsonError::SyntaxError(format!("Expected 'null' but found '{}'", excerpt(source, 4)?)))
     }
 }
 
@@ -48,68 +46,78 @@ mod tests {
 
     #[test]
     fn test_parse_true_valid() {
-        let input = "true rest";
+        let input = b"true rest";
         let (val, tail) = parse_true(input).unwrap();
         assert_eq!(val, Json::JsonBoolean(true));
-        assert_eq!(tail, " rest");
+        assert_eq!(tail, b" rest");
     }
 
     #[test]
     fn test_parse_true_invalid() {
-        let input = "truX";
+        let input = b"truX";
         let err = parse_true(input).unwrap_err();
         assert!(matches!(err, JsonError::SyntaxError(_)));
     }
 
     #[test]
     fn test_parse_true_invalid_postfix() {
-        let input = "trueerr";
+        let input = b"trueerr";
         let err = parse_true(input).unwrap_err();
         assert!(matches!(err, JsonError::SyntaxError(_)));
     }
 
     #[test]
     fn test_parse_false_valid() {
-        let input = "false next";
+        let input = b"false next";
         let (val, tail) = parse_false(input).unwrap();
         assert_eq!(val, Json::JsonBoolean(false));
-        assert_eq!(tail, " next");
+        assert_eq!(tail, b" next");
     }
 
     #[test]
     fn test_parse_false_invalid() {
-        let input = "falsX";
+        let input = b"falsX";
         let err = parse_false(input).unwrap_err();
         assert!(matches!(err, JsonError::SyntaxError(_)));
     }
 
     #[test]
     fn test_parse_false_invalid_postfix() {
-        let input = "falseerr";
+        let input = b"falseerr";
         let err = parse_false(input).unwrap_err();
         assert!(matches!(err, JsonError::SyntaxError(_)));
     }
 
     #[test]
     fn test_parse_null_valid() {
-        let input = "null,";
+        let input = b"null,";
         let (val, tail) = parse_null(input).unwrap();
         assert_eq!(val, Json::JsonNull);
-        assert_eq!(tail, ",");
+        assert_eq!(tail, b",");
     }
 
     #[test]
     fn test_parse_null_invalid() {
-        let input = "nulX";
+        let input = b"nulX";
         let err = parse_null(input).unwrap_err();
         assert!(matches!(err, JsonError::SyntaxError(_)));
     }
 
     #[test]
     fn test_parse_null_invalid_postfix() {
-        let input = "nullos";
+        let input = b"nullos";
         let err = parse_null(input).unwrap_err();
         assert!(matches!(err, JsonError::SyntaxError(_)));
     }
-}
 
+    #[test]
+    fn test_invalid_literal_before_multibyte_char() {
+        for input in ["trué", "falsé", "nulé", "trueééé", "falseééé", "nullééé"] {
+            assert!(matches!(parse_true(input.as_bytes()), Err(JsonError::SyntaxError(_))), "{}", input);
+            assert!(matches!(parse_false(input.as_bytes()), Err(JsonError::SyntaxError(_))), "{}", input);
+            assert!(matches!(parse_null(input.as_bytes()), Err(JsonError::SyntaxError(_))), "{}", input);
+        }
+        assert_eq!(parse_true("trué".as_bytes()).unwrap_err(), JsonError::SyntaxError("Expected 'true' but found 'tru'".to_string()));
+        assert!(crate::parse("[nulé]").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::core;
+use crate::map;
+
+use core::Json;
+use map::JsonMap;
+
+// Result of indexing into a `Json` with `[]`. It holds either the found value
+// or nothing, so chains like `v["a"][0]["b"]` never panic and a missing member
+// stays distinguishable from an explicit `null`.
+//
+// Backed by a slice of length zero or one, which lets `Index` hand out a
+// reference to it without storing anything.
+#[repr(transparent)]
+pub struct Lookup([Json]);
+
+impl Lookup {
+    pub(crate) fn new(value: Option<&Json>) -> &Lookup {
+        let slice: &[Json] = match value {
+            Some(v) => std::slice::from_ref(v),
+            None => &[],
+        };
+        // Sound because Lookup is a transparent wrapper around [Json]
+        unsafe { &*(slice as *const [Json] as *const Lookup) }
+    }
+
+    pub(crate) fn new_mut(value: Option<&mut Json>) -> &mut Lookup {
+        let slice: &mut [Json] = match value {
+            Some(v) => std::slice::from_mut(v),
+            None => &mut [],
+        };
+        // Sound because Lookup is a transparent wrapper around [Json]
+        unsafe { &mut *(slice as *mut [Json] as *mut Lookup) }
+    }
+
+    pub fn value(&self) -> Option<&Json> {
+        self.0.first()
+    }
+
+    pub fn value_mut(&mut self) -> Option<&mut Json> {
+        self.0.first_mut()
+    }
+
+    pub fn cloned(&self) -> Option<Json> {
+        self.value().cloned()
+    }
+
+    pub fn exists(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    pub fn is_missing(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.value().is_some_and(Json::is_object)
+    }
+    pub fn is_array(&self) -> bool {
+        self.value().is_some_and(Json::is_array)
+    }
+    pub fn is_string(&self) -> bool {
+        self.value().is_some_and(Json::is_string)
+    }
+    pub fn is_number(&self) -> bool {
+        self.value().is_some_and(Json::is_number)
+    }
+    pub fn is_bool(&self) -> bool {
+        self.value().is_some_and(Json::is_bool)
+    }
+    pub fn is_null(&self) -> bool {
+        self.value().is_some_and(Json::is_null)
+    }
+    pub fn is_i64(&self) -> bool {
+        self.value().is_some_and(Json::is_i64)
+    }
+    pub fn is_u64(&self) -> bool {
+        self.value().is_some_and(Json::is_u64)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        self.value().and_then(|v| v.get(key))
+    }
+    pub fn get_index(&self, idx: usize) -> Option<&Json> {
+        self.value().and_then(|v| v.get_index(idx))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        self.value().and_then(Json::as_str)
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        self.value().and_then(Json::as_f64)
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        self.value().and_then(Json::as_i64)
+    }
+    pub fn as_u64(&self) -> Option<u64> {
+        self.value().and_then(Json::as_u64)
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        self.value().and_then(Json::as_bool)
+    }
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        self.value().and_then(Json::as_array)
+    }
+    pub fn as_object(&self) -> Option<&JsonMap> {
+        self.value().and_then(Json::as_object)
+    }
+}
+
+impl fmt::Debug for Lookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value() {
+            Some(value) => value.fmt(f),
+            None => f.write_str("<missing>"),
+        }
+    }
+}
+
+impl PartialEq for Lookup {
+    fn eq(&self, other: &Lookup) -> bool {
+        self.value() == other.value()
+    }
+}
+
+impl PartialEq<Json> for Lookup {
+    fn eq(&self, other: &Json) -> bool {
+        self.value() == Some(other)
+    }
+}
+
+impl PartialEq<Lookup> for Json {
+    fn eq(&self, other: &Lookup) -> bool {
+        other == self
+    }
+}
+
+impl Index<&str> for Lookup {
+    type Output = Lookup;
+    fn index(&self, key: &str) -> &Self::Output {
+        Lookup::new(self.value().and_then(|v| v.get(key)))
+    }
+}
+
+impl Index<usize> for Lookup {
+    type Output = Lookup;
+    fn index(&self, idx: usize) -> &Self::Output {
+        Lookup::new(self.value().and_then(|v| v.get_index(idx)))
+    }
+}
+
+impl IndexMut<&str> for Lookup {
+    fn index_mut(&mut self, key: &str) -> &mut Self::Output {
+        Lookup::new_mut(self.value_mut().and_then(|v| v.get_mut(key)))
+    }
+}
+
+impl IndexMut<usize> for Lookup {
+    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
+        Lookup::new_mut(self.value_mut().and_then(|v| v.get_index_mut(idx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_chain() {
+        let json = json!({"a": [{"b": 1}, null]});
+        assert_eq!(json["a"][0]["b"], Json::JsonNumber(1.0));
+        assert_eq!(json["a"][0]["b"].as_i64(), Some(1));
+        assert!(json["a"][0]["c"].is_missing());
+        assert!(json["a"][5]["b"].is_missing());
+        assert!(json["x"]["y"][0].is_missing());
+        assert_eq!(json["a"][0]["c"].value(), None);
+    }
+
+    #[test]
+    fn test_lookup_missing_vs_null() {
+        let json = json!({"a": [{"b": 1}, null]});
+        assert!(json["a"][1].exists());
+        assert!(json["a"][1].is_null());
+        assert!(!json["a"][2].is_null());
+        assert_ne!(json["a"][2], Json::JsonNull);
+        assert_eq!(json["a"][1], json["a"][1]);
+        assert_ne!(json["a"][1], json["a"][2]);
+    }
+
+    #[test]
+    fn test_lookup_mut() {
+        let mut json = json!({"a": [{"b": 1}]});
+        if let Some(value) = json["a"][0]["b"].value_mut() {
+            *value = json!("changed");
+        }
+        assert!(json["a"][3]["b"].value_mut().is_none());
+        assert_eq!(json["a"][0]["b"].as_str(), Some("changed"));
+    }
+
+    #[test]
+    fn test_lookup_debug() {
+        let json = json!({"a": true});
+        assert_eq!(format!("{:?}", &json["a"]), "JsonBoolean(true)");
+        assert_eq!(format!("{:?}", &json["b"]), "<missing>");
+    }
+}
@@ -0,0 +1,169 @@
+// Builds a `Json` value from JSON-like syntax. Any other Rust expression is
+// converted through `ToJson`, so `&str` and `String` become string values.
+//
+//     let name = "Alice";
+//     let value = json!({"name": name, "tags": ["a", 1, null], "nested": {"ok": true}});
+#[macro_export]
+macro_rules! json {
+    // Array elements, accumulated in brackets
+    (@array [$($elems:expr,)*]) => {
+        ::std::vec![$($elems,)*]
+    };
+    (@array [$($elems:expr),*]) => {
+        ::std::vec![$($elems),*]
+    };
+    (@array [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(null)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] true $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(true)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] false $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(false)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!([$($array)*])] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] {$($map:tt)*} $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!({$($map)*})] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($next),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($last)])
+    };
+    (@array [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)*] $($rest)*)
+    };
+
+    // Object entries: munch key tokens up to ':', then the value up to ','
+    (@object $object:ident () () ()) => {};
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $object.insert($crate::json!(@key $($key)+), $value);
+        $crate::json!(@object $object () ($($rest)*) ($($rest)*));
+    };
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $object.insert($crate::json!(@key $($key)+), $value);
+    };
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!(null)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!(true)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!(false)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!([$($array)*])) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!({$($map)*})) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!($value)) , $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!($value)));
+    };
+    (@object $object:ident ($($key:tt)+) (:) $copy:tt) => {
+        ::std::compile_error!("missing value in json! object");
+    };
+    (@object $object:ident () (: $($rest:tt)*) ($colon:tt $($copy:tt)*)) => {
+        ::std::compile_error!("missing key in json! object");
+    };
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    (@key $key:expr) => {
+        ::std::string::ToString::to_string(&$key)
+    };
+
+    (null) => {
+        $crate::Json::JsonNull
+    };
+    (true) => {
+        $crate::Json::JsonBoolean(true)
+    };
+    (false) => {
+        $crate::Json::JsonBoolean(false)
+    };
+    ([]) => {
+        $crate::Json::JsonArray(::std::vec::Vec::new())
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::Json::JsonArray($crate::json!(@array [] $($tt)+))
+    };
+    ({}) => {
+        $crate::Json::new_object()
+    };
+    ({ $($tt:tt)+ }) => {
+        $crate::Json::JsonObject({
+            let mut object = $crate::JsonMap::new();
+            $crate::json!(@object object () ($($tt)+) ($($tt)+));
+            object
+        })
+    };
+    ($other:expr) => {
+        $crate::ToJson::to_json(&$other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Json, parse};
+
+    #[test]
+    fn test_json_macro_literals() {
+        assert_eq!(json!(null), Json::JsonNull);
+        assert_eq!(json!(true), Json::JsonBoolean(true));
+        assert_eq!(json!(false), Json::JsonBoolean(false));
+        assert_eq!(json!(1.5), Json::JsonNumber(1.5));
+        assert_eq!(json!(-3), Json::JsonNumber(-3.0));
+        assert_eq!(json!("text"), Json::JsonString("text".to_string()));
+        assert_eq!(json!([]), Json::new_array());
+        assert_eq!(json!({}), Json::new_object());
+    }
+
+    #[test]
+    fn test_json_macro_nested() {
+        let value = json!({
+            "a": [1, true, null, "x"],
+            "b": {"c": false, "d": []},
+            "e": {}
+        });
+        let (expected, _) = parse(r#"{"a": [1, true, null, "x"], "b": {"c": false, "d": []}, "e": {}}"#).unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn test_json_macro_interpolation() {
+        let name = "Alice";
+        let age = 30;
+        let tags = vec!["a", "b"];
+        let key = String::from("dynamic");
+        let value = json!({
+            "name": name,
+            "age": age + 1,
+            "tags": tags,
+            "missing": None::<i32>,
+            (key): [age, name, {"inner": -age}],
+        });
+        assert_eq!(value["name"], Json::JsonString("Alice".to_string()));
+        assert_eq!(value["age"], Json::JsonNumber(31.0));
+        assert_eq!(value["tags"][1], Json::JsonString("b".to_string()));
+        assert_eq!(value["missing"], Json::JsonNull);
+        assert_eq!(value["dynamic"][1], Json::JsonString("Alice".to_string()));
+        assert_eq!(value["dynamic"][2]["inner"], Json::JsonNumber(-30.0));
+        // Interpolated values are borrowed, not moved
+        assert_eq!(tags.len(), 2);
+    }
+
+    #[test]
+    fn test_json_macro_trailing_commas() {
+        assert_eq!(json!([1, 2,]), json!([1, 2]));
+        assert_eq!(json!({"a": 1,}), json!({"a": 1}));
+    }
+}
@@ -0,0 +1,570 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::process::ExitCode;
+
+use json_parser::{
+    Json, JsonError, JsonMap, JsonPointer, ParseOptions, RenderMode, RenderOptions, Severity, Visitor, Walk, line_col,
+    parse_bytes_with_options, parse_tolerant_with_options, parse_with_options, render_diagnostics,
+};
+
+const USAGE: &str = "\
+Usage: json-parser <command> [options] [file...]
+
+Commands:
+  validate [--ndjson] [file...]         Report errors; exit with 1 if any file is invalid
+  fmt [--indent N] [--sort-keys] [-i] [file...]
+                                        Pretty-print to standard output, or in place with -i
+  minify [--ndjson] [--sort-keys] [file...]
+                                        Print without whitespace
+  get <pointer> [--indent N] [--ndjson] [file]
+                                        Print the value at an RFC 6901 pointer such as /a/0
+  keys [--pointer P] [--ndjson] [file]  List the keys of an object or the indices of an array
+  stats [--ndjson] [file]               Count values by type and measure the nesting
+  diff <a> <b>                          List differences; exit with 1 if there are any
+
+Files default to standard input, which can also be named `-`. With --ndjson
+every line is a separate document, handled as soon as it is read: `get`
+skips lines without the value and `keys` and `stats` combine all lines.
+";
+
+// Exit statuses besides 0: input that is invalid or differs, and failures
+// to run at all, such as bad arguments or unreadable files
+const INVALID: u8 = 1;
+const ERROR: u8 = 2;
+
+const STDIN: &str = "-";
+const DEFAULT_INDENT: usize = 2;
+
+// Ends a command early. `message` is printed to standard error as it is.
+struct Failure {
+    status: u8,
+    message: String,
+}
+
+impl Failure {
+    fn usage(message: impl AsRef<str>) -> Self {
+        Failure { status: ERROR, message: format!("json-parser: {}\nRun `json-parser --help` for usage.", message.as_ref()) }
+    }
+
+    fn io(name: &str, error: io::Error) -> Self {
+        Failure { status: ERROR, message: format!("json-parser: {}: {}", name, error) }
+    }
+
+    fn invalid(message: String) -> Self {
+        Failure { status: INVALID, message }
+    }
+}
+
+// For writes to standard output. A closed pipe, as in `json-parser ... | head`,
+// just ends the command.
+impl From<io::Error> for Failure {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::BrokenPipe => Failure { status: 0, message: String::new() },
+            _ => Failure::io("<stdout>", error),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    command: String,
+    operands: Vec<String>,
+    indent: Option<usize>,
+    sort_keys: bool,
+    in_place: bool,
+    ndjson: bool,
+    pointer: Option<String>,
+}
+
+impl Args {
+    fn files(&self) -> Vec<&str> {
+        match self.operands.as_slice() {
+            [] => vec![STDIN],
+            operands => operands.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(status) => ExitCode::from(status),
+        Err(failure) => {
+            if !failure.message.is_empty() {
+                eprintln!("{}", failure.message);
+            }
+            ExitCode::from(failure.status)
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<u8, Failure> {
+    let Some(args) = parse_args(args)? else {
+        print!("{}", USAGE);
+        return Ok(0);
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    let status = match args.command.as_str() {
+        "validate" => validate(&args)?,
+        "fmt" => fmt(&args, &mut out)?,
+        "minify" => minify(&args, &mut out)?,
+        "get" => get(&args, &mut out)?,
+        "keys" => keys(&args, &mut out)?,
+        "stats" => stats(&args, &mut out)?,
+        "diff" => diff(&args, &mut out)?,
+        _ => unreachable!("checked by parse_args"),
+    };
+    out.flush()?;
+    Ok(status)
+}
+
+// None when help was asked for
+fn parse_args(args: &[String]) -> Result<Option<Args>, Failure> {
+    let Some((command, rest)) = args.split_first() else {
+        return Err(Failure::usage("missing command"));
+    };
+    let allowed: &[&str] = match command.as_str() {
+        "-h" | "--help" | "help" => return Ok(None),
+        "validate" => &["--ndjson"],
+        "fmt" => &["--indent", "--sort-keys", "--in-place"],
+        "minify" => &["--ndjson", "--sort-keys"],
+        "get" => &["--indent", "--ndjson"],
+        "keys" => &["--pointer", "--ndjson"],
+        "stats" => &["--ndjson"],
+        "diff" => &[],
+        _ => return Err(Failure::usage(format!("unknown command `{}`", command))),
+    };
+    let mut parsed = Args { command: command.clone(), ..Args::default() };
+    let mut rest = rest.iter();
+    let mut options_done = false;
+    while let Some(arg) = rest.next() {
+        if options_done || arg == STDIN || !arg.starts_with('-') {
+            parsed.operands.push(arg.clone());
+            continue;
+        }
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let name = match name {
+            "-h" | "--help" => return Ok(None),
+            "--" => {
+                options_done = true;
+                continue;
+            }
+            "-i" => "--in-place",
+            name => name,
+        };
+        if !allowed.contains(&name) {
+            return Err(Failure::usage(format!("`{}` does not take {}", command, name)));
+        }
+        let mut value = || inline.clone().or_else(|| rest.next().cloned()).ok_or_else(|| Failure::usage(format!("{} needs a value", name)));
+        match name {
+            "--indent" => {
+                let indent = value()?;
+                parsed.indent = Some(indent.parse().map_err(|_| Failure::usage(format!("invalid indent `{}`", indent)))?);
+            }
+            "--pointer" => parsed.pointer = Some(value()?),
+            "--sort-keys" => parsed.sort_keys = true,
+            "--in-place" => parsed.in_place = true,
+            "--ndjson" => parsed.ndjson = true,
+            _ => unreachable!("only allowed options get here"),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+fn display_name(path: &str) -> &str {
+    if path == STDIN { "<stdin>" } else { path }
+}
+
+fn parse_options() -> ParseOptions {
+    ParseOptions { allow_bom: true, ..ParseOptions::default() }
+}
+
+fn describe(error: &JsonError) -> String {
+    match error {
+        JsonError::LexicalError(message)
+        | JsonError::SyntaxError(message)
+        | JsonError::TypeError(message)
+        | JsonError::IndexError(message) => message.clone(),
+        JsonError::EncodingError { offset, message } => format!("{} at byte {}", message, offset),
+        JsonError::DepthError { max_depth } => format!("Nesting exceeds the maximum depth of {}", max_depth),
+    }
+}
+
+fn render_mode() -> RenderMode {
+    if io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none() {
+        RenderMode::Ansi
+    } else {
+        RenderMode::Plain
+    }
+}
+
+// The tolerant parser's rendered diagnostics when it sees the error too,
+// otherwise the strict parser's message. Nesting past the limit is reported
+// in one line rather than by echoing the nested text
+fn report(text: &str, name: &str, error: &JsonError) -> String {
+    if let JsonError::DepthError { .. } = error {
+        return format!("{}: {}", name, describe(error));
+    }
+    let diagnostics = parse_tolerant_with_options(text, &parse_options()).diagnostics;
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        let options = RenderOptions { mode: render_mode(), file_name: Some(name) };
+        render_diagnostics(text, &diagnostics, &options).trim_end().to_string()
+    } else {
+        format!("{}: {}", name, describe(error))
+    }
+}
+
+// A whole file, which may also be UTF-16 or UTF-32
+fn load(path: &str) -> Result<Json, Failure> {
+    let name = display_name(path);
+    let bytes = if path == STDIN {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+    } else {
+        fs::read(path)
+    };
+    let bytes = bytes.map_err(|e| Failure::io(name, e))?;
+    match std::str::from_utf8(&bytes) {
+        Ok(text) => parse_with_options(text, &parse_options()).map_err(|e| Failure::invalid(report(text, name, &e))),
+        Err(_) => parse_bytes_with_options(&bytes, &parse_options()).map_err(|e| Failure::invalid(format!("{}: {}", name, describe(&e)))),
+    }
+}
+
+// Calls `f` with each line that is not blank and its number from 1, as soon
+// as the line has been read
+fn for_each_line<F>(path: &str, mut f: F) -> Result<(), Failure>
+where
+    F: FnMut(&str, usize) -> Result<(), Failure>,
+{
+    let name = display_name(path);
+    let mut reader: Box<dyn BufRead> = if path == STDIN {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(fs::File::open(path).map_err(|e| Failure::io(name, e))?))
+    };
+    let mut line = String::new();
+    for number in 1.. {
+        line.clear();
+        if reader.read_line(&mut line).map_err(|e| Failure::io(&format!("{}:{}", name, number), e))? == 0 {
+            break;
+        }
+        let text = line.trim_end();
+        if !text.trim_start().is_empty() {
+            f(text, number)?;
+        }
+    }
+    Ok(())
+}
+
+// Errors in NDJSON take one line each: `name:line:column: message`
+fn parse_record(text: &str, name: &str, number: usize) -> Result<Json, Failure> {
+    parse_with_options(text, &parse_options()).map_err(|error| {
+        let diagnostics = match error {
+            JsonError::DepthError { .. } => Vec::new(),
+            _ => parse_tolerant_with_options(text, &parse_options()).diagnostics,
+        };
+        Failure::invalid(match diagnostics.iter().find(|d| d.severity == Severity::Error) {
+            Some(d) => format!("{}:{}:{}: {}", name, number, line_col(text, d.span.start).1, d.message),
+            None => format!("{}:{}: {}", name, number, describe(&error)),
+        })
+    })
+}
+
+fn parse_pointer(pointer: &str) -> Result<JsonPointer, Failure> {
+    JsonPointer::parse(pointer).map_err(|e| Failure::usage(format!("invalid pointer {:?}: {}", pointer, describe(&e))))
+}
+
+fn single_file(operands: &[String]) -> Result<&str, Failure> {
+    match operands {
+        [] => Ok(STDIN),
+        [path] => Ok(path),
+        _ => Err(Failure::usage("expected at most one file")),
+    }
+}
+
+fn validate(args: &Args) -> Result<u8, Failure> {
+    let mut status = 0;
+    for path in args.files() {
+        let name = display_name(path);
+        let result = if args.ndjson {
+            for_each_line(path, |text, number| {
+                if let Err(failure) = parse_record(text, name, number) {
+                    eprintln!("{}", failure.message);
+                    status = status.max(failure.status);
+                }
+                Ok(())
+            })
+        } else {
+            load(path).map(drop)
+        };
+        if let Err(failure) = result {
+            eprintln!("{}", failure.message);
+            status = status.max(failure.status);
+        }
+    }
+    Ok(status)
+}
+
+fn fmt(args: &Args, out: &mut impl Write) -> Result<u8, Failure> {
+    let files = args.files();
+    if args.in_place && files.contains(&STDIN) {
+        return Err(Failure::usage("--in-place needs file names"));
+    }
+    for path in files {
+        let mut json = load(path)?;
+        if args.sort_keys {
+            json.sort_keys();
+        }
+        let text = json.dump_pretty(args.indent.unwrap_or(DEFAULT_INDENT)) + "\n";
+        if args.in_place {
+            fs::write(path, text).map_err(|e| Failure::io(path, e))?;
+        } else {
+            out.write_all(text.as_bytes())?;
+        }
+    }
+    Ok(0)
+}
+
+fn minify(args: &Args, out: &mut impl Write) -> Result<u8, Failure> {
+    let mut write = |mut json: Json| -> Result<(), Failure> {
+        if args.sort_keys {
+            json.sort_keys();
+        }
+        writeln!(out, "{}", json.dump())?;
+        Ok(())
+    };
+    for path in args.files() {
+        if args.ndjson {
+            for_each_line(path, |text, number| write(parse_record(text, display_name(path), number)?))?;
+        } else {
+            write(load(path)?)?;
+        }
+    }
+    Ok(0)
+}
+
+fn get(args: &Args, out: &mut impl Write) -> Result<u8, Failure> {
+    let Some((pointer, files)) = args.operands.split_first() else {
+        return Err(Failure::usage("`get` needs a pointer"));
+    };
+    let pointer = parse_pointer(pointer)?;
+    let path = single_file(files)?;
+    let name = display_name(path);
+    if args.ndjson {
+        for_each_line(path, |text, number| {
+            if let Some(value) = pointer.resolve(&parse_record(text, name, number)?) {
+                writeln!(out, "{}", value.dump())?;
+            }
+            Ok(())
+        })?;
+        return Ok(0);
+    }
+    let json = load(path)?;
+    let value = pointer
+        .resolve(&json)
+        .ok_or_else(|| Failure::invalid(format!("json-parser: {}: no value at {:?}", name, pointer.to_string())))?;
+    writeln!(out, "{}", value.dump_pretty(args.indent.unwrap_or(DEFAULT_INDENT)))?;
+    Ok(0)
+}
+
+fn key_list(value: &Json) -> Option<Vec<String>> {
+    match value {
+        Json::JsonObject(map) => Some(map.keys().map(str::to_string).collect()),
+        Json::JsonArray(values) => Some((0..values.len()).map(|i| i.to_string()).collect()),
+        _ => None,
+    }
+}
+
+fn keys(args: &Args, out: &mut impl Write) -> Result<u8, Failure> {
+    let pointer = parse_pointer(args.pointer.as_deref().unwrap_or(""))?;
+    let path = single_file(&args.operands)?;
+    let name = display_name(path);
+    if args.ndjson {
+        // Each key once, in the order it is first seen
+        let mut seen = HashSet::new();
+        for_each_line(path, |text, number| {
+            let json = parse_record(text, name, number)?;
+            for key in pointer.resolve(&json).and_then(key_list).unwrap_or_default() {
+                if !seen.contains(&key) {
+                    writeln!(out, "{}", key)?;
+                    seen.insert(key);
+                }
+            }
+            Ok(())
+        })?;
+        return Ok(0);
+    }
+    let json = load(path)?;
+    let value = pointer
+        .resolve(&json)
+        .ok_or_else(|| Failure::invalid(format!("json-parser: {}: no value at {:?}", name, pointer.to_string())))?;
+    let keys = key_list(value).ok_or_else(|| {
+        Failure::invalid(format!("json-parser: {}: {} at {:?} has no keys", name, value.type_name(), pointer.to_string()))
+    })?;
+    for key in keys {
+        writeln!(out, "{}", key)?;
+    }
+    Ok(0)
+}
+
+#[derive(Default)]
+struct Stats {
+    documents: usize,
+    values: usize,
+    objects: usize,
+    arrays: usize,
+    strings: usize,
+    numbers: usize,
+    booleans: usize,
+    nulls: usize,
+    members: usize,
+    max_depth: usize,
+    max_members: usize,
+    max_elements: usize,
+    string_bytes: usize,
+}
+
+impl Visitor for Stats {
+    fn enter(&mut self, path: &JsonPointer, value: &Json) -> Walk {
+        self.values += 1;
+        self.max_depth = self.max_depth.max(path.depth());
+        match value {
+            Json::JsonObject(map) => {
+                self.objects += 1;
+                self.members += map.len();
+                self.max_members = self.max_members.max(map.len());
+            }
+            Json::JsonArray(values) => {
+                self.arrays += 1;
+                self.max_elements = self.max_elements.max(values.len());
+            }
+            Json::JsonString(s) => {
+                self.strings += 1;
+                self.string_bytes += s.len();
+            }
+            Json::JsonNumber(_) => self.numbers += 1,
+            Json::JsonBoolean(_) => self.booleans += 1,
+            Json::JsonNull => self.nulls += 1,
+        }
+        Walk::Continue
+    }
+}
+
+impl Stats {
+    fn add(&mut self, json: &Json) {
+        self.documents += 1;
+        json.walk(self);
+    }
+
+    fn to_json(&self) -> Json {
+        let fields = [
+            ("documents", self.documents),
+            ("values", self.values),
+            ("objects", self.objects),
+            ("arrays", self.arrays),
+            ("strings", self.strings),
+            ("numbers", self.numbers),
+            ("booleans", self.booleans),
+            ("nulls", self.nulls),
+            ("members", self.members),
+            ("max_depth", self.max_depth),
+            ("max_members", self.max_members),
+            ("max_elements", self.max_elements),
+            ("string_bytes", self.string_bytes),
+        ];
+        let mut map = JsonMap::with_capacity(fields.len());
+        for (name, count) in fields {
+            map.insert(name, Json::JsonNumber(count as f64));
+        }
+        Json::JsonObject(map)
+    }
+}
+
+fn stats(args: &Args, out: &mut impl Write) -> Result<u8, Failure> {
+    let path = single_file(&args.operands)?;
+    let mut stats = Stats::default();
+    if args.ndjson {
+        for_each_line(path, |text, number| {
+            stats.add(&parse_record(text, display_name(path), number)?);
+            Ok(())
+        })?;
+    } else {
+        stats.add(&load(path)?);
+    }
+    writeln!(out, "{}", stats.to_json().dump_pretty(DEFAULT_INDENT))?;
+    Ok(0)
+}
+
+fn diff(args: &Args, out: &mut impl Write) -> Result<u8, Failure> {
+    let [a, b] = args.operands.as_slice() else {
+        return Err(Failure::usage("`diff` needs two files"));
+    };
+    if a == STDIN && b == STDIN {
+        return Err(Failure::usage("only one file can be standard input"));
+    }
+    let (a, b) = (load(a)?, load(b)?);
+    let changes = compare(&a, &b, &mut JsonPointer::root(), out)?;
+    Ok(if changes == 0 { 0 } else { INVALID })
+}
+
+fn show(path: &JsonPointer) -> String {
+    if path.is_root() { "(root)".to_string() } else { path.to_string() }
+}
+
+// Writes one line per difference and returns how many there were: `-` for
+// a value only in `a`, `+` for one only in `b` and `~` for one that changed.
+// Arrays are compared index by index.
+fn compare(a: &Json, b: &Json, path: &mut JsonPointer, out: &mut impl Write) -> io::Result<usize> {
+    let mut changes = 0;
+    match (a, b) {
+        (Json::JsonObject(x), Json::JsonObject(y)) => {
+            for (key, value) in x.iter() {
+                path.push(key);
+                changes += match y.get(key) {
+                    Some(other) => compare(value, other, path, out)?,
+                    None => {
+                        writeln!(out, "- {}: {}", show(path), value.dump())?;
+                        1
+                    }
+                };
+                path.pop();
+            }
+            for (key, value) in y.iter().filter(|(key, _)| !x.contains_key(key)) {
+                path.push(key);
+                writeln!(out, "+ {}: {}", show(path), value.dump())?;
+                path.pop();
+                changes += 1;
+            }
+        }
+        (Json::JsonArray(x), Json::JsonArray(y)) => {
+            for i in 0..x.len().max(y.len()) {
+                path.push(i.to_string());
+                changes += match (x.get(i), y.get(i)) {
+                    (Some(value), Some(other)) => compare(value, other, path, out)?,
+                    (Some(value), None) => {
+                        writeln!(out, "- {}: {}", show(path), value.dump())?;
+                        1
+                    }
+                    (None, Some(other)) => {
+                        writeln!(out, "+ {}: {}", show(path), other.dump())?;
+                        1
+                    }
+                    (None, None) => unreachable!("i is below one of the lengths"),
+                };
+                path.pop();
+            }
+        }
+        _ if a == b => {}
+        _ => {
+            writeln!(out, "~ {}: {} -> {}", show(path), a.dump(), b.dump())?;
+            changes += 1;
+        }
+    }
+    Ok(changes)
+}
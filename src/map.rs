@@ -0,0 +1,471 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+use crate::core;
+
+use core::Json;
+
+// Insertion-ordered storage for JSON object members. Small objects, the vast
+// majority, are searched linearly; past INDEX_THRESHOLD members keys are also
+// looked up through a hash index. Iteration and serialization follow `entries`.
+// Keys are shared strings so that an `Interner` can hand the same allocation
+// to every object that uses a key.
+#[derive(Clone, Default)]
+pub struct JsonMap {
+    entries: Vec<(Arc<str>, Json)>,
+    // Boxed so an object costs no more than an array inside every `Json`
+    #[allow(clippy::box_collection)]
+    index: Option<Box<HashMap<Arc<str>, usize>>>,
+}
+
+pub(crate) const INDEX_THRESHOLD: usize = 16;
+
+impl JsonMap {
+    pub fn new() -> Self {
+        JsonMap::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        JsonMap {
+            entries: Vec::with_capacity(capacity),
+            index: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index = None;
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        self.position(key).map(|i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Json> {
+        match self.position(key) {
+            Some(i) => Some(&mut self.entries[i].1),
+            None => None,
+        }
+    }
+
+    pub fn get_index(&self, idx: usize) -> Option<(&str, &Json)> {
+        self.entries.get(idx).map(|(k, v)| (&**k, v))
+    }
+
+    pub fn get_index_mut(&mut self, idx: usize) -> Option<(&str, &mut Json)> {
+        self.entries.get_mut(idx).map(|(k, v)| (&**k, v))
+    }
+
+    // Replaces the value in place when the key exists, keeping its position
+    pub fn insert<K: Into<Arc<str>>>(&mut self, key: K, value: Json) -> Option<Json> {
+        let key = key.into();
+        match self.position(&key) {
+            Some(i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.push(key, value);
+                None
+            }
+        }
+    }
+
+    // Removes the entry and shifts the following ones, preserving order
+    pub fn remove(&mut self, key: &str) -> Option<Json> {
+        let i = self.position(key)?;
+        let (_, value) = self.entries.remove(i);
+        if let Some(index) = &mut self.index {
+            index.remove(key);
+            for (k, _) in &self.entries[i..] {
+                if let Some(pos) = index.get_mut(k) {
+                    *pos -= 1;
+                }
+            }
+        }
+        Some(value)
+    }
+
+    // Removes the entry by moving the last one into its place
+    pub fn swap_remove(&mut self, key: &str) -> Option<Json> {
+        let i = self.position(key)?;
+        let (_, value) = self.entries.swap_remove(i);
+        if let Some(index) = &mut self.index {
+            index.remove(key);
+            if let Some((k, _)) = self.entries.get(i) {
+                index.insert(k.clone(), i);
+            }
+        }
+        Some(value)
+    }
+
+    pub fn entry<K: Into<Arc<str>>>(&mut self, key: K) -> Entry<'_> {
+        let key = key.into();
+        match self.position(&key) {
+            Some(idx) => Entry::Occupied(OccupiedEntry { map: self, idx }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    pub fn retain<F: FnMut(&str, &mut Json) -> bool>(&mut self, mut keep: F) {
+        self.entries.retain_mut(|(k, v)| keep(k, v));
+        self.reindex();
+    }
+
+    pub fn sort_keys(&mut self) {
+        self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.reindex();
+    }
+
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&str, &Json, &str, &Json) -> Ordering,
+    {
+        self.entries.sort_by(|(ka, va), (kb, vb)| compare(ka, va, kb, vb));
+        self.reindex();
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.entries.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(self.entries.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(k, _)| &**k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Json> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Json> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        match &self.index {
+            Some(index) => index.get(key).copied(),
+            None => self.entries.iter().position(|(k, _)| **k == *key),
+        }
+    }
+
+    // Appends a key known to be absent
+    fn push(&mut self, key: Arc<str>, value: Json) {
+        if let Some(index) = &mut self.index {
+            index.insert(key.clone(), self.entries.len());
+        }
+        self.entries.push((key, value));
+        if self.index.is_none() && self.entries.len() > INDEX_THRESHOLD {
+            self.reindex();
+        }
+    }
+
+    fn reindex(&mut self) {
+        self.index = if self.entries.len() > INDEX_THRESHOLD {
+            let index = self.entries.iter().enumerate().map(|(i, (k, _))| (k.clone(), i)).collect();
+            Some(Box::new(index))
+        } else {
+            None
+        };
+    }
+}
+
+// Objects compare equal regardless of member order
+impl PartialEq for JsonMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl fmt::Debug for JsonMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Index<&str> for JsonMap {
+    type Output = Json;
+    fn index(&self, key: &str) -> &Self::Output {
+        self.get(key).unwrap_or_else(|| panic!("Key not found: {:?}", key))
+    }
+}
+
+impl IndexMut<&str> for JsonMap {
+    fn index_mut(&mut self, key: &str) -> &mut Self::Output {
+        self.get_mut(key).unwrap_or_else(|| panic!("Key not found: {:?}", key))
+    }
+}
+
+impl<K: Into<Arc<str>>> FromIterator<(K, Json)> for JsonMap {
+    fn from_iter<I: IntoIterator<Item = (K, Json)>>(iter: I) -> Self {
+        let mut map = JsonMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Into<Arc<str>>> Extend<(K, Json)> for JsonMap {
+    fn extend<I: IntoIterator<Item = (K, Json)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+pub struct Iter<'a>(std::slice::Iter<'a, (Arc<str>, Json)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a Json);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (&**k, v))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, v)| (&**k, v))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+pub struct IterMut<'a>(std::slice::IterMut<'a, (Arc<str>, Json)>);
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a str, &'a mut Json);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (&**k, v))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+impl<'a> IntoIterator for &'a JsonMap {
+    type Item = (&'a str, &'a Json);
+    type IntoIter = Iter<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut JsonMap {
+    type Item = (&'a str, &'a mut Json);
+    type IntoIter = IterMut<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl IntoIterator for JsonMap {
+    type Item = (Arc<str>, Json);
+    type IntoIter = std::vec::IntoIter<(Arc<str>, Json)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+pub struct OccupiedEntry<'a> {
+    map: &'a mut JsonMap,
+    idx: usize,
+}
+
+pub struct VacantEntry<'a> {
+    map: &'a mut JsonMap,
+    key: Arc<str>,
+}
+
+impl<'a> Entry<'a> {
+    pub fn key(&self) -> &str {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => &entry.key,
+        }
+    }
+
+    pub fn or_insert(self, default: Json) -> &'a mut Json {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> Json>(self, default: F) -> &'a mut Json {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut Json {
+        self.or_insert_with(Json::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut Json)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    pub fn key(&self) -> &str {
+        &self.map.entries[self.idx].0
+    }
+    pub fn get(&self) -> &Json {
+        &self.map.entries[self.idx].1
+    }
+    pub fn get_mut(&mut self) -> &mut Json {
+        &mut self.map.entries[self.idx].1
+    }
+    pub fn into_mut(self) -> &'a mut Json {
+        &mut self.map.entries[self.idx].1
+    }
+    pub fn insert(&mut self, value: Json) -> Json {
+        std::mem::replace(self.get_mut(), value)
+    }
+    pub fn remove(self) -> Json {
+        let key = self.map.entries[self.idx].0.clone();
+        self.map.remove(&key).expect("occupied entry must exist")
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+    pub fn insert(self, value: Json) -> &'a mut Json {
+        let idx = self.map.entries.len();
+        self.map.push(self.key, value);
+        &mut self.map.entries[idx].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> JsonMap {
+        vec![
+            ("b", Json::JsonNumber(2.0)),
+            ("a", Json::JsonNumber(1.0)),
+            ("c", Json::JsonNumber(3.0)),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn keys(map: &JsonMap) -> Vec<&str> {
+        map.keys().collect()
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map = sample();
+        assert_eq!(keys(&map), ["b", "a", "c"]);
+        assert_eq!(map.insert("a", Json::JsonNull), Some(Json::JsonNumber(1.0)));
+        assert_eq!(keys(&map), ["b", "a", "c"]);
+        assert_eq!(map["a"], Json::JsonNull);
+    }
+
+    #[test]
+    fn test_remove_preserves_order() {
+        let mut map = sample();
+        assert_eq!(map.remove("b"), Some(Json::JsonNumber(2.0)));
+        assert_eq!(map.remove("b"), None);
+        assert_eq!(keys(&map), ["a", "c"]);
+        assert_eq!(map["c"], Json::JsonNumber(3.0));
+    }
+
+    #[test]
+    fn test_swap_remove() {
+        let mut map = sample();
+        assert_eq!(map.swap_remove("b"), Some(Json::JsonNumber(2.0)));
+        assert_eq!(keys(&map), ["c", "a"]);
+        assert_eq!(map.get("c"), Some(&Json::JsonNumber(3.0)));
+        assert_eq!(map.get("a"), Some(&Json::JsonNumber(1.0)));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut map = sample();
+        *map.entry("a").or_insert(Json::JsonNull) = Json::JsonBoolean(true);
+        map.entry("d").or_insert_with(|| Json::JsonNumber(4.0));
+        map.entry("c").and_modify(|v| *v = Json::JsonNull).or_default();
+        assert_eq!(map["a"], Json::JsonBoolean(true));
+        assert_eq!(map["c"], Json::JsonNull);
+        assert_eq!(keys(&map), ["b", "a", "c", "d"]);
+        if let Entry::Occupied(entry) = map.entry("b") {
+            assert_eq!(entry.remove(), Json::JsonNumber(2.0));
+        } else {
+            panic!("Expected occupied entry");
+        }
+        assert_eq!(map.entry("b").key(), "b");
+        assert!(!map.contains_key("b"));
+    }
+
+    #[test]
+    fn test_retain_and_sort() {
+        let mut map = sample();
+        map.retain(|k, _| k != "a");
+        assert_eq!(keys(&map), ["b", "c"]);
+        map.insert("a", Json::JsonNumber(0.0));
+        map.sort_keys();
+        assert_eq!(keys(&map), ["a", "b", "c"]);
+        map.sort_by(|_, va, _, vb| vb.as_f64().partial_cmp(&va.as_f64()).unwrap());
+        assert_eq!(keys(&map), ["c", "b", "a"]);
+        assert_eq!(map["a"], Json::JsonNumber(0.0));
+    }
+
+    #[test]
+    fn test_eq_ignores_order() {
+        let mut other = sample();
+        other.sort_keys();
+        assert_eq!(sample(), other);
+        other.remove("a");
+        assert_ne!(sample(), other);
+    }
+
+    #[test]
+    fn test_large_map_uses_index() {
+        let mut map: JsonMap = (0..40).map(|i| (format!("k{}", i), Json::JsonNumber(i as f64))).collect();
+        assert!(map.index.is_some());
+        assert_eq!(map.get("k39"), Some(&Json::JsonNumber(39.0)));
+        assert_eq!(map.remove("k0"), Some(Json::JsonNumber(0.0)));
+        assert_eq!(map.swap_remove("k5"), Some(Json::JsonNumber(5.0)));
+        assert_eq!(map.get_index(4).map(|(k, _)| k), Some("k39"));
+        assert_eq!(map.get("k39"), Some(&Json::JsonNumber(39.0)));
+        assert_eq!(map.get("k38"), Some(&Json::JsonNumber(38.0)));
+        *map.entry("k40").or_default() = Json::JsonBoolean(true);
+        assert_eq!(map.get("k40"), Some(&Json::JsonBoolean(true)));
+        assert!(!map.contains_key("k0"));
+        map.retain(|k, _| k.len() == 2);
+        assert_eq!(map.len(), 8);
+        assert!(map.index.is_none());
+        assert_eq!(map.get("k9"), Some(&Json::JsonNumber(9.0)));
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::os::raw::{c_int, c_void};
+use std::path::Path;
+use std::ptr;
+
+use crate::core;
+use crate::options;
+use crate::encoding;
+use crate::lazy;
+use crate::tape;
+use crate::utils;
+
+use core::{Json, JsonError};
+use options::ParseOptions;
+use encoding::parse_bytes_with_options;
+use lazy::{LazyValue, parse_lazy_with_options};
+use tape::JsonTape;
+use utils::{from_start, invalid_utf8};
+
+// The libc std already links; `off_t` is pointer-sized on the platforms
+// where it is not 64-bit
+unsafe extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: isize) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+
+// A whole file mapped read-only. Everything parsed from it borrows from the
+// mapping, not from a copy of the file.
+pub struct Mmap {
+    ptr: *const u8,
+    len: usize,
+}
+
+// The mapping is read-only and owned by this value alone
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+#[derive(Debug)]
+pub enum MmapError {
+    Io(io::Error),
+    Json(JsonError),
+}
+
+impl From<io::Error> for MmapError {
+    fn from(err: io::Error) -> Self {
+        MmapError::Io(err)
+    }
+}
+
+impl From<JsonError> for MmapError {
+    fn from(err: JsonError) -> Self {
+        MmapError::Json(err)
+    }
+}
+
+impl Mmap {
+    /// # Safety
+    /// The file must not be truncated or written to while the mapping
+    /// exists, by this process or any other. Either changes what borrowed
+    /// `&str`s point at, and truncation makes reading them fault.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Mmap> {
+        let file = File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file is too large to map"))?;
+        if len == 0 {
+            // mmap rejects empty lengths
+            return Ok(Mmap { ptr: ptr::NonNull::dangling().as_ptr(), len: 0 });
+        }
+        // The mapping keeps its own reference to the file, so `file` can close
+        let ptr = unsafe { mmap(ptr::null_mut(), len, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap { ptr: ptr as *const u8, len })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Validates UTF-8 in place; errors carry the offset of the bad byte
+    pub fn as_str(&self) -> Result<&str, JsonError> {
+        let bytes = self.as_bytes();
+        std::str::from_utf8(bytes).map_err(|err| from_start(invalid_utf8(&bytes[err.valid_up_to()..]), bytes.len()))
+    }
+
+    // UTF-8 files are parsed in place; UTF-16 and UTF-32 are decoded first
+    pub fn parse(&self, options: &ParseOptions) -> Result<Json, JsonError> {
+        parse_bytes_with_options(self.as_bytes(), options)
+    }
+
+    // On-demand navigation over the mapping; strings read without escapes
+    // borrow from it
+    pub fn lazy<'a>(&'a self, options: &'a ParseOptions) -> Result<LazyValue<'a>, JsonError> {
+        parse_lazy_with_options(self.as_str()?, options)
+    }
+
+    pub fn tape(&self, options: &ParseOptions) -> Result<JsonTape, JsonError> {
+        JsonTape::parse_with_options(self.as_str()?, options)
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                munmap(self.ptr as *mut c_void, self.len);
+            }
+        }
+    }
+}
+
+impl Json {
+    /// Maps the file, parses it and unmaps it again.
+    ///
+    /// # Safety
+    /// As for `Mmap::open`, for the duration of the call.
+    pub unsafe fn from_mmap<P: AsRef<Path>>(path: P) -> Result<Json, MmapError> {
+        let map = unsafe { Mmap::open(path)? };
+        Ok(map.parse(&ParseOptions::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // A file in the temp directory that is removed again on drop
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &[u8]) -> TempFile {
+            let path = std::env::temp_dir().join(format!("json-parser-{}-{}", std::process::id(), name));
+            fs::write(&path, contents).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_mmap_parse() {
+        let file = TempFile::new("parse.json", br#"{"data": [{"id": 7, "name": "x"}], "s": "a\nb"}"#);
+        let map = unsafe { Mmap::open(&file.0).unwrap() };
+        let options = ParseOptions::default();
+        let json = map.parse(&options).unwrap();
+        assert_eq!(json["data"][0]["id"], Json::JsonNumber(7.0));
+        assert_eq!(unsafe { Json::from_mmap(&file.0).unwrap() }, json);
+        assert_eq!(map.tape(&options).unwrap().to_json(), json);
+
+        let doc = map.lazy(&options).unwrap();
+        let name = doc.get("data").unwrap().at(0).unwrap().get("name").unwrap().as_str().unwrap();
+        match name {
+            Cow::Borrowed(s) => assert!(map.as_bytes().as_ptr_range().contains(&s.as_ptr())),
+            Cow::Owned(_) => panic!("Expected a string borrowed from the mapping"),
+        }
+    }
+
+    #[test]
+    fn test_mmap_errors() {
+        let file = TempFile::new("invalid.json", b"[\"ok\", \"\xFF\"]");
+        let map = unsafe { Mmap::open(&file.0).unwrap() };
+        assert!(matches!(map.as_str(), Err(JsonError::EncodingError { offset: 8, .. })));
+        assert!(matches!(unsafe { Json::from_mmap(&file.0) }, Err(MmapError::Json(JsonError::EncodingError { .. }))));
+
+        let empty = TempFile::new("empty.json", b"");
+        let map = unsafe { Mmap::open(&empty.0).unwrap() };
+        assert!(map.is_empty());
+        assert!(map.parse(&ParseOptions::default()).is_err());
+
+        let missing = std::env::temp_dir().join("json-parser-missing-file.json");
+        assert!(matches!(unsafe { Json::from_mmap(missing) }, Err(MmapError::Io(_))));
+    }
+}
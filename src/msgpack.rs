@@ -0,0 +1,683 @@
+use std::io;
+use std::io::Read;
+
+use crate::core;
+use crate::map;
+use crate::options;
+use crate::binary;
+
+use core::{Json, JsonError};
+use map::JsonMap;
+use options::DEFAULT_MAX_DEPTH;
+use binary::{BytesPolicy, KeyPolicy, NonFinitePolicy, bytes_to_json, is_integer, number_to_json};
+
+// How MessagePack data without a JSON counterpart is decoded. The limits
+// match the JSON parser's defaults, so untrusted input is no more expensive
+// to take in one format than the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsgpackOptions {
+    // Deepest allowed nesting of arrays and maps. Decoding is recursive, as
+    // with `ParseOptions::max_depth`.
+    pub max_depth: usize,
+    pub bin: BytesPolicy,
+    pub ext: ExtPolicy,
+    pub keys: KeyPolicy,
+    pub non_finite: NonFinitePolicy,
+}
+
+// What to do with extension types other than the timestamp (-1), which
+// always becomes a number of seconds since the Unix epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExtPolicy {
+    // Replace the value with `{"type": <number>, "data": <bytes>}`, the data
+    // written as `bin` says
+    #[default]
+    Wrap,
+    Reject,
+}
+
+impl MsgpackOptions {
+    pub const fn new() -> Self {
+        MsgpackOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            bin: BytesPolicy::Base64Url,
+            ext: ExtPolicy::Wrap,
+            keys: KeyPolicy::Stringify,
+            non_finite: NonFinitePolicy::Keep,
+        }
+    }
+}
+
+impl Default for MsgpackOptions {
+    fn default() -> Self {
+        MsgpackOptions::new()
+    }
+}
+
+#[derive(Debug)]
+pub enum MsgpackError {
+    Io(io::Error),
+    Json(JsonError),
+}
+
+impl From<io::Error> for MsgpackError {
+    fn from(err: io::Error) -> Self {
+        MsgpackError::Io(err)
+    }
+}
+
+impl From<JsonError> for MsgpackError {
+    fn from(err: JsonError) -> Self {
+        MsgpackError::Json(err)
+    }
+}
+
+const TWO_POW_63: f64 = 9223372036854775808.0;
+const TWO_POW_64: f64 = 18446744073709551616.0;
+
+const TIMESTAMP: i8 = -1;
+
+// Integral numbers take the smallest integer format that holds them and all
+// others float 32 when it is exact, float 64 otherwise. Members keep their
+// order. Panics on a string, array or object of 2^32 or more elements,
+// which MessagePack cannot represent.
+pub fn to_msgpack(json: &Json) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_value(json, &mut out);
+    out
+}
+
+fn write_value(json: &Json, out: &mut Vec<u8>) {
+    match json {
+        Json::JsonObject(map) => {
+            write_len(map.len(), 0x80, 0xDE, out);
+            for (key, value) in map.iter() {
+                write_str(key, out);
+                write_value(value, out);
+            }
+        }
+        Json::JsonArray(values) => {
+            write_len(values.len(), 0x90, 0xDC, out);
+            for value in values {
+                write_value(value, out);
+            }
+        }
+        Json::JsonString(s) => write_str(s, out),
+        Json::JsonNumber(n) => write_number(*n, out),
+        Json::JsonBoolean(b) => out.push(if *b { 0xC3 } else { 0xC2 }),
+        Json::JsonNull => out.push(0xC0),
+    }
+}
+
+fn length(len: usize) -> u32 {
+    u32::try_from(len).expect("MessagePack lengths are below 2^32")
+}
+
+// Array and map lengths: a fix format below 16, then 16 and 32 bits
+fn write_len(len: usize, fix: u8, marker16: u8, out: &mut Vec<u8>) {
+    match length(len) {
+        len @ 0..16 => out.push(fix | len as u8),
+        len @ 16..0x1_0000 => {
+            out.push(marker16);
+            out.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(marker16 + 1);
+            out.extend(len.to_be_bytes());
+        }
+    }
+}
+
+fn write_str(s: &str, out: &mut Vec<u8>) {
+    match length(s.len()) {
+        len @ 0..32 => out.push(0xA0 | len as u8),
+        len @ 32..0x100 => out.extend([0xD9, len as u8]),
+        len @ 0x100..0x1_0000 => {
+            out.push(0xDA);
+            out.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(0xDB);
+            out.extend(len.to_be_bytes());
+        }
+    }
+    out.extend(s.as_bytes());
+}
+
+fn write_number(n: f64, out: &mut Vec<u8>) {
+    let integral = is_integer(n);
+    if integral && (0.0..TWO_POW_64).contains(&n) {
+        match n as u64 {
+            n @ 0..0x80 => out.push(n as u8),
+            n @ 0x80..0x100 => out.extend([0xCC, n as u8]),
+            n @ 0x100..0x1_0000 => {
+                out.push(0xCD);
+                out.extend((n as u16).to_be_bytes());
+            }
+            n @ 0x1_0000..0x1_0000_0000 => {
+                out.push(0xCE);
+                out.extend((n as u32).to_be_bytes());
+            }
+            n => {
+                out.push(0xCF);
+                out.extend(n.to_be_bytes());
+            }
+        }
+    } else if integral && (-TWO_POW_63..0.0).contains(&n) {
+        match n as i64 {
+            n @ -32..0 => out.push(n as u8),
+            n @ -0x80..-32 => out.extend([0xD0, n as u8]),
+            n @ -0x8000..-0x80 => {
+                out.push(0xD1);
+                out.extend((n as i16).to_be_bytes());
+            }
+            n @ -0x8000_0000..-0x8000 => {
+                out.push(0xD2);
+                out.extend((n as i32).to_be_bytes());
+            }
+            n => {
+                out.push(0xD3);
+                out.extend(n.to_be_bytes());
+            }
+        }
+    } else if n.is_nan() || (n as f32) as f64 == n {
+        out.push(0xCA);
+        out.extend((n as f32).to_bits().to_be_bytes());
+    } else {
+        out.push(0xCB);
+        out.extend(n.to_bits().to_be_bytes());
+    }
+}
+
+pub fn from_msgpack(bytes: &[u8]) -> Result<Json, JsonError> {
+    from_msgpack_with_options(bytes, &MsgpackOptions::default())
+}
+
+// Decodes exactly one message; bytes left after it are an error
+pub fn from_msgpack_with_options(bytes: &[u8], options: &MsgpackOptions) -> Result<Json, JsonError> {
+    let mut decoder = Decoder { reader: bytes, offset: 0, options: *options };
+    let result = decoder.next_value().and_then(|value| match value {
+        Some(value) if decoder.reader.is_empty() => Ok(value),
+        Some(_) => Err(decoder.error(decoder.offset, "Unexpected trailing bytes after MessagePack message")),
+        None => Err(decoder.error(0, "Unexpected end of MessagePack data")),
+    });
+    result.map_err(|err| match err {
+        MsgpackError::Json(err) => err,
+        MsgpackError::Io(err) => unreachable!("reading from a slice cannot fail: {}", err),
+    })
+}
+
+// Decodes a stream of concatenated messages, one `Json` per message, until
+// the reader is exhausted. Values are read a byte at a time, so wrap
+// unbuffered readers such as files and sockets in a `BufReader`. After an
+// error the position in the stream is unknown and iteration ends.
+pub struct MsgpackReader<R> {
+    decoder: Decoder<R>,
+    failed: bool,
+}
+
+impl<R: Read> MsgpackReader<R> {
+    pub fn new(reader: R) -> Self {
+        MsgpackReader::with_options(reader, &MsgpackOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: &MsgpackOptions) -> Self {
+        MsgpackReader { decoder: Decoder { reader, offset: 0, options: *options }, failed: false }
+    }
+
+    // Bytes consumed so far
+    pub fn offset(&self) -> usize {
+        self.decoder.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.decoder.reader
+    }
+}
+
+impl<R: Read> Iterator for MsgpackReader<R> {
+    type Item = Result<Json, MsgpackError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.decoder.next_value();
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
+
+struct Decoder<R> {
+    reader: R,
+    offset: usize,
+    options: MsgpackOptions,
+}
+
+// Elements reserved up front for a claimed length, which untrusted input
+// can set to anything
+const MAX_PREALLOCATION: usize = 4096;
+
+fn is_str(marker: u8) -> bool {
+    matches!(marker, 0xA0..=0xBF | 0xD9..=0xDB)
+}
+
+impl<R: Read> Decoder<R> {
+    fn error(&self, offset: usize, message: &str) -> MsgpackError {
+        MsgpackError::Json(JsonError::EncodingError { offset, message: message.to_string() })
+    }
+
+    fn eof(&self) -> MsgpackError {
+        self.error(self.offset, "Unexpected end of MessagePack data")
+    }
+
+    // None at the end of the input
+    fn byte(&mut self) -> Result<Option<u8>, MsgpackError> {
+        let mut byte = [0];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {
+                    self.offset += 1;
+                    return Ok(Some(byte[0]));
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N], MsgpackError> {
+        let mut bytes = [0; N];
+        let mut filled = 0;
+        while filled < N {
+            match self.reader.read(&mut bytes[filled..]) {
+                Ok(0) => return Err(self.eof()),
+                Ok(n) => {
+                    filled += n;
+                    self.offset += n;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(bytes)
+    }
+
+    // Grows the buffer as data arrives rather than trusting `len`
+    fn take(&mut self, len: u64) -> Result<Vec<u8>, MsgpackError> {
+        let mut bytes = Vec::new();
+        let read = (&mut self.reader).take(len).read_to_end(&mut bytes)?;
+        self.offset += read;
+        if (read as u64) < len {
+            return Err(self.eof());
+        }
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u64, MsgpackError> {
+        Ok(self.fixed::<1>()?[0] as u64)
+    }
+
+    fn u16(&mut self) -> Result<u64, MsgpackError> {
+        Ok(u16::from_be_bytes(self.fixed()?) as u64)
+    }
+
+    fn u32(&mut self) -> Result<u64, MsgpackError> {
+        Ok(u32::from_be_bytes(self.fixed()?) as u64)
+    }
+
+    // None at a clean end of input, between messages
+    fn next_value(&mut self) -> Result<Option<Json>, MsgpackError> {
+        match self.byte()? {
+            Some(marker) => self.value(marker, 0).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn value(&mut self, marker: u8, depth: usize) -> Result<Json, MsgpackError> {
+        let offset = self.offset - 1;
+        let number = |n: f64| Ok(Json::JsonNumber(n));
+        match marker {
+            0x00..=0x7F => number(marker as f64),
+            0x80..=0x8F => self.map((marker & 0x0F) as u64, depth, offset),
+            0x90..=0x9F => self.array((marker & 0x0F) as u64, depth, offset),
+            0xA0..=0xBF => self.str((marker & 0x1F) as u64),
+            0xC0 => Ok(Json::JsonNull),
+            0xC1 => Err(self.error(offset, "Invalid MessagePack marker 0xc1")),
+            0xC2 => Ok(Json::JsonBoolean(false)),
+            0xC3 => Ok(Json::JsonBoolean(true)),
+            0xC4 => {
+                let len = self.u8()?;
+                self.bin(len, offset)
+            }
+            0xC5 => {
+                let len = self.u16()?;
+                self.bin(len, offset)
+            }
+            0xC6 => {
+                let len = self.u32()?;
+                self.bin(len, offset)
+            }
+            0xC7 => {
+                let len = self.u8()?;
+                self.ext(len, offset)
+            }
+            0xC8 => {
+                let len = self.u16()?;
+                self.ext(len, offset)
+            }
+            0xC9 => {
+                let len = self.u32()?;
+                self.ext(len, offset)
+            }
+            0xCA => {
+                let n = f32::from_be_bytes(self.fixed()?);
+                self.float(n as f64, offset)
+            }
+            0xCB => {
+                let n = f64::from_be_bytes(self.fixed()?);
+                self.float(n, offset)
+            }
+            0xCC => number(self.u8()? as f64),
+            0xCD => number(self.u16()? as f64),
+            0xCE => number(self.u32()? as f64),
+            0xCF => number(u64::from_be_bytes(self.fixed()?) as f64),
+            0xD0 => number(i8::from_be_bytes(self.fixed()?) as f64),
+            0xD1 => number(i16::from_be_bytes(self.fixed()?) as f64),
+            0xD2 => number(i32::from_be_bytes(self.fixed()?) as f64),
+            0xD3 => number(i64::from_be_bytes(self.fixed()?) as f64),
+            // fixext 1, 2, 4, 8 and 16
+            0xD4..=0xD8 => self.ext(1 << (marker - 0xD4), offset),
+            0xD9 => {
+                let len = self.u8()?;
+                self.str(len)
+            }
+            0xDA => {
+                let len = self.u16()?;
+                self.str(len)
+            }
+            0xDB => {
+                let len = self.u32()?;
+                self.str(len)
+            }
+            0xDC => {
+                let len = self.u16()?;
+                self.array(len, depth, offset)
+            }
+            0xDD => {
+                let len = self.u32()?;
+                self.array(len, depth, offset)
+            }
+            0xDE => {
+                let len = self.u16()?;
+                self.map(len, depth, offset)
+            }
+            0xDF => {
+                let len = self.u32()?;
+                self.map(len, depth, offset)
+            }
+            0xE0..=0xFF => number(marker as i8 as f64),
+        }
+    }
+
+    // Like `byte`, but the input may not end here
+    fn marker(&mut self) -> Result<u8, MsgpackError> {
+        self.byte()?.ok_or_else(|| self.eof())
+    }
+
+    fn enter(&self, depth: usize, offset: usize) -> Result<usize, MsgpackError> {
+        if depth >= self.options.max_depth {
+            return Err(self.error(offset, "Maximum nesting depth exceeded"));
+        }
+        Ok(depth + 1)
+    }
+
+    fn array(&mut self, len: u64, depth: usize, offset: usize) -> Result<Json, MsgpackError> {
+        let depth = self.enter(depth, offset)?;
+        let mut values = Vec::with_capacity((len as usize).min(MAX_PREALLOCATION));
+        for _ in 0..len {
+            let marker = self.marker()?;
+            values.push(self.value(marker, depth)?);
+        }
+        Ok(Json::JsonArray(values))
+    }
+
+    fn map(&mut self, len: u64, depth: usize, offset: usize) -> Result<Json, MsgpackError> {
+        let depth = self.enter(depth, offset)?;
+        let mut map = JsonMap::with_capacity((len as usize).min(MAX_PREALLOCATION));
+        for _ in 0..len {
+            let key = self.key(depth)?;
+            let marker = self.marker()?;
+            map.insert(key, self.value(marker, depth)?);
+        }
+        Ok(Json::JsonObject(map))
+    }
+
+    fn float(&self, n: f64, offset: usize) -> Result<Json, MsgpackError> {
+        number_to_json(n, self.options.non_finite).ok_or_else(|| JsonError::TypeError(format!("MessagePack number at offset {} has no JSON equivalent", offset)).into())
+    }
+
+    fn key(&mut self, depth: usize) -> Result<String, MsgpackError> {
+        let marker = self.marker()?;
+        let offset = self.offset - 1;
+        let key = self.value(marker, depth)?;
+        match (key, self.options.keys) {
+            (Json::JsonString(s), _) if is_str(marker) => Ok(s),
+            (_, KeyPolicy::Reject) => Err(JsonError::TypeError(format!("MessagePack map key at offset {} is not a string", offset)).into()),
+            (Json::JsonString(s), KeyPolicy::Stringify) => Ok(s),
+            (key, KeyPolicy::Stringify) => Ok(key.dump()),
+        }
+    }
+
+    fn str(&mut self, len: u64) -> Result<Json, MsgpackError> {
+        let start = self.offset;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes)
+            .map(Json::JsonString)
+            .map_err(|err| self.error(start + err.utf8_error().valid_up_to(), "Invalid UTF-8 in MessagePack string"))
+    }
+
+    fn bin(&mut self, len: u64, offset: usize) -> Result<Json, MsgpackError> {
+        let bytes = self.take(len)?;
+        bytes_to_json(&bytes, self.options.bin)
+            .ok_or_else(|| JsonError::TypeError(format!("MessagePack bin at offset {} has no JSON equivalent", offset)).into())
+    }
+
+    fn ext(&mut self, len: u64, offset: usize) -> Result<Json, MsgpackError> {
+        let kind = i8::from_be_bytes(self.fixed()?);
+        let data = self.take(len)?;
+        if kind == TIMESTAMP {
+            return self.timestamp(&data, offset);
+        }
+        let rejected = || JsonError::TypeError(format!("MessagePack ext type {} at offset {} has no JSON equivalent", kind, offset)).into();
+        match self.options.ext {
+            ExtPolicy::Wrap => {
+                let data = bytes_to_json(&data, self.options.bin).ok_or_else(rejected)?;
+                Ok(Json::from_iter([("type", Json::JsonNumber(kind as f64)), ("data", data)]))
+            }
+            ExtPolicy::Reject => Err(rejected()),
+        }
+    }
+
+    // Seconds since the epoch, with the nanoseconds as a fraction
+    fn timestamp(&self, data: &[u8], offset: usize) -> Result<Json, MsgpackError> {
+        let (seconds, nanoseconds) = match *data {
+            [a, b, c, d] => (u32::from_be_bytes([a, b, c, d]) as f64, 0),
+            [..] if data.len() == 8 => {
+                let packed = u64::from_be_bytes(data.try_into().unwrap());
+                ((packed & 0x3_FFFF_FFFF) as f64, (packed >> 34) as u32)
+            }
+            [..] if data.len() == 12 => {
+                let nanoseconds = u32::from_be_bytes(data[..4].try_into().unwrap());
+                (i64::from_be_bytes(data[4..].try_into().unwrap()) as f64, nanoseconds)
+            }
+            _ => return Err(self.error(offset, "MessagePack timestamp must be 4, 8 or 12 bytes")),
+        };
+        if nanoseconds >= 1_000_000_000 {
+            return Err(self.error(offset, "MessagePack timestamp nanoseconds out of range"));
+        }
+        Ok(Json::JsonNumber(seconds + nanoseconds as f64 / 1e9))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::from_hex;
+    use std::io::Cursor;
+
+    fn parse(source: &str) -> Json {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn test_smallest_encodings() {
+        let examples = [
+            ("0", "00"), ("127", "7f"), ("128", "cc80"), ("255", "ccff"), ("256", "cd0100"), ("65535", "cdffff"),
+            ("65536", "ce00010000"), ("4294967295", "ceffffffff"), ("4294967296", "cf0000000100000000"),
+            ("-1", "ff"), ("-32", "e0"), ("-33", "d0df"), ("-128", "d080"), ("-129", "d1ff7f"), ("-32768", "d18000"),
+            ("-32769", "d2ffff7fff"), ("-2147483648", "d280000000"), ("-2147483649", "d3ffffffff7fffffff"),
+            ("-9223372036854775808", "d38000000000000000"), ("18446744073709549568", "cffffffffffffff800"),
+            ("1.5", "ca3fc00000"), ("0.1", "cb3fb999999999999a"), ("-9223372036854777856", "cbc3e0000000000001"),
+            ("null", "c0"), ("false", "c2"), ("true", "c3"), (r#""""#, "a0"), (r#""abc""#, "a3616263"),
+            ("[]", "90"), ("[1, [2]]", "92019102"), ("{}", "80"), (r#"{"b": 1, "a": null}"#, "82a16201a161c0"),
+        ];
+        for (json, msgpack) in examples {
+            let json = parse(json);
+            assert_eq!(to_msgpack(&json), from_hex(msgpack), "{}", msgpack);
+            assert_eq!(from_msgpack(&from_hex(msgpack)).unwrap(), json, "{}", msgpack);
+        }
+        assert_eq!(to_msgpack(&Json::JsonNumber(-0.0)), from_hex("ca80000000"));
+        assert_eq!(to_msgpack(&Json::JsonNumber(f64::INFINITY)), from_hex("ca7f800000"));
+        assert!(from_msgpack(&to_msgpack(&Json::JsonNumber(f64::NAN))).unwrap().as_f64().unwrap().is_nan());
+
+        let long = "x".repeat(40);
+        assert_eq!(to_msgpack(&Json::JsonString(long.clone()))[..2], [0xD9, 40]);
+        let array = Json::JsonArray(vec![Json::JsonNull; 16]);
+        assert_eq!(to_msgpack(&array)[..3], [0xDC, 0x00, 0x10]);
+        let map: Json = (0..70000).map(|i| (i.to_string(), Json::JsonNull)).collect();
+        assert_eq!(to_msgpack(&map)[..5], [0xDF, 0x00, 0x01, 0x11, 0x70]);
+        assert_eq!(from_msgpack(&to_msgpack(&map)).unwrap(), map);
+    }
+
+    #[test]
+    fn test_wider_formats_decode() {
+        let examples = [
+            ("cc05", "5"), ("cd0005", "5"), ("ce00000005", "5"), ("cf0000000000000005", "5"),
+            ("d0fb", "-5"), ("d1fffb", "-5"), ("d2fffffffb", "-5"), ("d3fffffffffffffffb", "-5"),
+            ("d90161", r#""a""#), ("da000161", r#""a""#), ("db0000000161", r#""a""#),
+            ("dc000101", "[1]"), ("dd0000000101", "[1]"), ("de0001a16101", r#"{"a": 1}"#), ("df00000001a16101", r#"{"a": 1}"#),
+            ("cb3ff8000000000000", "1.5"),
+        ];
+        for (msgpack, json) in examples {
+            assert_eq!(from_msgpack(&from_hex(msgpack)).unwrap(), parse(json), "{}", msgpack);
+        }
+    }
+
+    #[test]
+    fn test_bin_ext_and_keys() {
+        let bin = from_hex("c403010203");
+        assert_eq!(from_msgpack(&bin).unwrap(), parse(r#""AQID""#));
+        let options = MsgpackOptions { bin: BytesPolicy::Array, ..MsgpackOptions::default() };
+        assert_eq!(from_msgpack_with_options(&bin, &options).unwrap(), parse("[1, 2, 3]"));
+        let options = MsgpackOptions { bin: BytesPolicy::Reject, ..MsgpackOptions::default() };
+        assert!(matches!(from_msgpack_with_options(&bin, &options), Err(JsonError::TypeError(_))));
+
+        let ext = from_hex("d5070102");
+        assert_eq!(from_msgpack(&ext).unwrap(), parse(r#"{"type": 7, "data": "AQI"}"#));
+        let options = MsgpackOptions { ext: ExtPolicy::Reject, ..MsgpackOptions::default() };
+        assert!(matches!(from_msgpack_with_options(&ext, &options), Err(JsonError::TypeError(_))));
+        assert_eq!(from_msgpack(&from_hex("c70380ffffff")).unwrap(), parse(r#"{"type": -128, "data": "____"}"#));
+
+        // Timestamps 32, 64 and 96
+        assert_eq!(from_msgpack(&from_hex("d6ff5a4ecdc0")).unwrap(), Json::JsonNumber(1515113920.0));
+        assert_eq!(from_msgpack(&from_hex("d7ff773594005a4ecdc0")).unwrap(), Json::JsonNumber(1515113920.5));
+        assert_eq!(from_msgpack(&from_hex("c70cff1dcd6500fffffffffffffff6")).unwrap(), Json::JsonNumber(-9.5));
+        assert!(from_msgpack(&from_hex("d7ffffffffff5a4ec9c0")).is_err());
+        assert!(from_msgpack(&from_hex("d4ff00")).is_err());
+
+        let map = from_hex("8201c3c0a0");
+        assert_eq!(from_msgpack(&map).unwrap(), parse(r#"{"1": true, "null": ""}"#));
+        let options = MsgpackOptions { keys: KeyPolicy::Reject, ..MsgpackOptions::default() };
+        assert!(matches!(from_msgpack_with_options(&map, &options), Err(JsonError::TypeError(_))));
+
+        // [Infinity, NaN]
+        let non_finite = from_hex("92ca7f800000cb7ff8000000000000");
+        let values = from_msgpack(&non_finite).unwrap();
+        assert_eq!(values[0], Json::JsonNumber(f64::INFINITY));
+        assert!(values[1].as_f64().unwrap().is_nan());
+        let options = MsgpackOptions { non_finite: NonFinitePolicy::Null, ..MsgpackOptions::default() };
+        assert_eq!(from_msgpack_with_options(&non_finite, &options).unwrap(), parse("[null, null]"));
+        let options = MsgpackOptions { non_finite: NonFinitePolicy::Reject, ..MsgpackOptions::default() };
+        assert_eq!(from_msgpack_with_options(&non_finite, &options), Err(JsonError::TypeError("MessagePack number at offset 1 has no JSON equivalent".to_string())));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let malformed = [("", 0), ("c1", 0), ("cd01", 2), ("a2ff", 2), ("a2c328", 1), ("9201", 2), ("8101", 2), ("c0c0", 1), ("c5ffff00", 4)];
+        for (msgpack, offset) in malformed {
+            match from_msgpack(&from_hex(msgpack)) {
+                Err(JsonError::EncodingError { offset: at, .. }) => assert_eq!(at, offset, "{}", msgpack),
+                other => panic!("{}: expected an encoding error, got {:?}", msgpack, other),
+            }
+        }
+        // Claims 2^32 - 1 elements and bytes without the data to back them
+        assert!(from_msgpack(&from_hex("ddffffffff")).is_err());
+        assert!(from_msgpack(&from_hex("dbffffffff61")).is_err());
+        let nested = [vec![0x91; 200], vec![0x00]].concat();
+        assert!(from_msgpack(&nested).is_err());
+        let options = MsgpackOptions { max_depth: 300, ..MsgpackOptions::default() };
+        assert!(from_msgpack_with_options(&nested, &options).is_ok());
+    }
+
+    #[test]
+    fn test_reader_streams_messages() {
+        let messages = [parse(r#"{"id": 1, "tags": ["a", "b"]}"#), parse("null"), parse("-7.25"), parse("[]")];
+        let stream: Vec<u8> = messages.iter().flat_map(to_msgpack).collect();
+        let mut reader = MsgpackReader::new(Cursor::new(&stream));
+        for expected in &messages {
+            assert_eq!(&reader.next().unwrap().unwrap(), expected);
+        }
+        assert!(reader.next().is_none());
+        assert_eq!(reader.offset(), stream.len());
+
+        let mut reader = MsgpackReader::new(Cursor::new(&stream[..stream.len() - 2]));
+        assert_eq!(reader.by_ref().take_while(Result::is_ok).count(), 2);
+        assert!(reader.next().is_none());
+
+        // Cut inside the float
+        let mut reader = MsgpackReader::new(&stream[..stream.len() - 4]);
+        reader.next();
+        reader.next();
+        match reader.next() {
+            Some(Err(MsgpackError::Json(JsonError::EncodingError { offset, .. }))) => assert_eq!(offset, stream.len() - 4),
+            other => panic!("expected a truncation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reader_passes_on_io_errors() {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disconnected"))
+            }
+        }
+        let mut reader = MsgpackReader::new(Failing);
+        assert!(matches!(reader.next(), Some(Err(MsgpackError::Io(_)))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = parse(r#"{
+            "id": 9007199254740993, "neg": -42, "ratio": 0.1, "small": 1.5e-10, "big": 1e300,
+            "name": "rpc é😀", "tags": ["a", "", null, true, false],
+            "nested": {"deep": [[], {}, [-0.0, 65504, -2147483649, 3.0e38]]}
+        }"#);
+        let msgpack = to_msgpack(&json);
+        assert_eq!(from_msgpack(&msgpack).unwrap(), json);
+        assert_eq!(to_msgpack(&from_msgpack(&msgpack).unwrap()), msgpack);
+    }
+}
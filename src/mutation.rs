@@ -0,0 +1,211 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::core;
+use crate::map;
+
+use core::{Json, JsonError};
+use map::Entry;
+
+fn expected(kind: &str, found: &Json) -> JsonError {
+    JsonError::TypeError(format!("Expected {} but found {}", kind, found.type_name()))
+}
+
+impl Json {
+    // Object members
+
+    pub fn insert<K: Into<Arc<str>>>(&mut self, key: K, value: Json) -> Result<Option<Json>, JsonError> {
+        match self {
+            Json::JsonObject(map) => Ok(map.insert(key, value)),
+            other => Err(expected("object", other)),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Json> {
+        self.as_object_mut().and_then(|map| map.remove(key))
+    }
+
+    pub fn entry<K: Into<Arc<str>>>(&mut self, key: K) -> Result<Entry<'_>, JsonError> {
+        match self {
+            Json::JsonObject(map) => Ok(map.entry(key)),
+            other => Err(expected("object", other)),
+        }
+    }
+
+    // Array elements
+
+    pub fn push(&mut self, value: Json) -> Result<(), JsonError> {
+        match self {
+            Json::JsonArray(vec) => {
+                vec.push(value);
+                Ok(())
+            }
+            other => Err(expected("array", other)),
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Json> {
+        self.as_array_mut().and_then(Vec::pop)
+    }
+
+    pub fn insert_at(&mut self, idx: usize, value: Json) -> Result<(), JsonError> {
+        match self {
+            Json::JsonArray(vec) if idx <= vec.len() => {
+                vec.insert(idx, value);
+                Ok(())
+            }
+            Json::JsonArray(vec) => Err(JsonError::IndexError(format!("Index {} out of bounds for array of length {}", idx, vec.len()))),
+            other => Err(expected("array", other)),
+        }
+    }
+
+    pub fn remove_at(&mut self, idx: usize) -> Option<Json> {
+        match self {
+            Json::JsonArray(vec) if idx < vec.len() => Some(vec.remove(idx)),
+            _ => None,
+        }
+    }
+
+    pub fn swap_remove(&mut self, idx: usize) -> Option<Json> {
+        match self {
+            Json::JsonArray(vec) if idx < vec.len() => Some(vec.swap_remove(idx)),
+            _ => None,
+        }
+    }
+
+    // Containers
+
+    // Keeps array elements, or object values, for which `keep` returns true
+    pub fn retain<F: FnMut(&Json) -> bool>(&mut self, mut keep: F) -> Result<(), JsonError> {
+        match self {
+            Json::JsonArray(vec) => {
+                vec.retain(|v| keep(v));
+                Ok(())
+            }
+            Json::JsonObject(map) => {
+                map.retain(|_, v| keep(v));
+                Ok(())
+            }
+            other => Err(expected("array or object", other)),
+        }
+    }
+
+    // Replaces the value with null and returns the previous one
+    pub fn take(&mut self) -> Json {
+        std::mem::replace(self, Json::JsonNull)
+    }
+
+    // Sorts object keys at every depth of the tree
+    pub fn sort_keys(&mut self) {
+        match self {
+            Json::JsonObject(map) => {
+                map.sort_keys();
+                map.values_mut().for_each(Json::sort_keys);
+            }
+            Json::JsonArray(vec) => vec.iter_mut().for_each(Json::sort_keys),
+            _ => {}
+        }
+    }
+
+    pub fn sort_by<F: FnMut(&Json, &Json) -> Ordering>(&mut self, compare: F) -> Result<(), JsonError> {
+        match self {
+            Json::JsonArray(vec) => {
+                vec.sort_by(compare);
+                Ok(())
+            }
+            other => Err(expected("array", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_insert_remove() {
+        let mut json = json!({"a": 1});
+        assert_eq!(json.insert("b", json!(2)), Ok(None));
+        assert_eq!(json.insert("a", json!(3)), Ok(Some(json!(1))));
+        assert_eq!(json.remove("a"), Some(json!(3)));
+        assert_eq!(json.remove("a"), None);
+        assert_eq!(json, json!({"b": 2}));
+        assert!(json!([]).insert("a", json!(null)).is_err());
+        assert_eq!(json!(1).remove("a"), None);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut json = json!({"count": 1});
+        *json.entry("count").unwrap().or_insert(json!(0)).as_f64_mut().unwrap() += 1.0;
+        json.entry("list").unwrap().or_insert_with(Json::new_array).push(json!("x")).unwrap();
+        assert_eq!(json, json!({"count": 2, "list": ["x"]}));
+        assert!(json!(null).entry("a").is_err());
+    }
+
+    #[test]
+    fn test_array_push_pop() {
+        let mut json = json!([1]);
+        json.push(json!(2)).unwrap();
+        assert_eq!(json.pop(), Some(json!(2)));
+        assert_eq!(json.pop(), Some(json!(1)));
+        assert_eq!(json.pop(), None);
+        assert!(json!({}).push(json!(1)).is_err());
+        assert_eq!(json!("s").pop(), None);
+    }
+
+    #[test]
+    fn test_array_insert_at_and_remove() {
+        let mut json = json!([1, 3]);
+        json.insert_at(1, json!(2)).unwrap();
+        json.insert_at(3, json!(4)).unwrap();
+        assert_eq!(json, json!([1, 2, 3, 4]));
+        assert!(matches!(json.insert_at(9, json!(0)), Err(JsonError::IndexError(_))));
+        assert!(matches!(json!({}).insert_at(0, json!(0)), Err(JsonError::TypeError(_))));
+        assert_eq!(json.swap_remove(0), Some(json!(1)));
+        assert_eq!(json, json!([4, 2, 3]));
+        assert_eq!(json.remove_at(1), Some(json!(2)));
+        assert_eq!(json, json!([4, 3]));
+        assert_eq!(json.swap_remove(5), None);
+        assert_eq!(json.remove_at(5), None);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut arr = json!([1, null, 2, null]);
+        arr.retain(|v| !v.is_null()).unwrap();
+        assert_eq!(arr, json!([1, 2]));
+        let mut obj = json!({"a": null, "b": 1});
+        obj.retain(|v| !v.is_null()).unwrap();
+        assert_eq!(obj, json!({"b": 1}));
+        assert!(json!(true).retain(|_| true).is_err());
+    }
+
+    #[test]
+    fn test_take() {
+        let mut json = json!({"a": [1, 2]});
+        let taken = json.get_mut("a").unwrap().take();
+        assert_eq!(taken, json!([1, 2]));
+        assert_eq!(json, json!({"a": null}));
+    }
+
+    #[test]
+    fn test_sort_keys_recursive() {
+        let mut json = json!({"b": {"y": 1, "x": 2}, "a": [{"d": 1, "c": 2}]});
+        json.sort_keys();
+        let keys: Vec<&str> = json.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["a", "b"]);
+        let inner: Vec<&str> = json["b"].as_object().unwrap().keys().collect();
+        assert_eq!(inner, ["x", "y"]);
+        let nested: Vec<&str> = json["a"][0].as_object().unwrap().keys().collect();
+        assert_eq!(nested, ["c", "d"]);
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let mut json = json!([3, 1, 2]);
+        json.sort_by(|a, b| a.as_f64().partial_cmp(&b.as_f64()).unwrap()).unwrap();
+        assert_eq!(json, json!([1, 2, 3]));
+        assert!(json!({}).sort_by(|_, _| Ordering::Equal).is_err());
+    }
+}
@@ -1,234 +1,335 @@
 
 use crate::core;
+use crate::options;
+use crate::utils;
 
 use core::{Json, JsonError};
+use options::{NumberOverflow, ParseOptions};
+use utils::peek_char;
 
-pub fn parse_number(source: &str) -> Result<(Json, &str), JsonError> {
-    let mut chars = source.char_indices().peekable();
-    let mut end = 0;
-    let mut has_digits = false;
+// Powers of ten that are exact in an f64
+const POW10: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11,
+    1e12, 1e13, 1e14, 1e15, 1e16, 1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+// Largest integer up to which every integer is exact in an f64
+const MAX_EXACT: u64 = 1 << 53;
+// Decimal exponents beyond this are infinity or zero whatever the digits
+const MAX_EXPONENT: i64 = 1 << 20;
+
+fn digit_at(bytes: &[u8], pos: usize) -> Option<u64> {
+    match bytes.get(pos) {
+        Some(b) if b.is_ascii_digit() => Some((b - b'0') as u64),
+        _ => None,
+    }
+}
+
+// Validates the grammar and accumulates the value in one pass. Numbers with at
+// most 19 significant digits and a small exponent are converted exactly with
+// a single multiplication or division; the rest go to the standard library,
+// which rounds correctly in every case.
+pub fn parse_number<'a>(bytes: &'a [u8], options: &ParseOptions) -> Result<(Json, &'a [u8]), JsonError> {
+    let mut pos = 0;
 
     // Special early error return for leading dot
-    if let Some((_, '.')) = chars.peek() {
+    if bytes.first() == Some(&b'.') {
         return Err(JsonError::SyntaxError("Leading dot is not allowed".to_string()));
     }
-    
 
     // Optional minus
-    if let Some((_, '-')) = chars.peek() {
-        end += 1;
-        chars.next();
+    let negative = bytes.first() == Some(&b'-');
+    if negative {
+        pos += 1;
     }
 
+    let mut mantissa: u64 = 0;
+    let mut exponent: i64 = 0;
+    // Set once a non-zero digit no longer fits in the mantissa
+    let mut truncated = false;
+
     // Integer part
-    if let Some((_, '0')) = chars.peek() {
-        end += 1;
-        chars.next();
-        has_digits = true;
-        // Leading zero must not be followed by digit
-        if let Some((_, c)) = chars.peek() {
-            if c.is_ascii_digit() {
+    match bytes.get(pos) {
+        Some(b'0') => {
+            pos += 1;
+            // Leading zero must not be followed by digit
+            if digit_at(bytes, pos).is_some() {
                 return Err(JsonError::SyntaxError("Leading zeros are not allowed".to_string()));
             }
         }
-    } else if let Some((_, c)) = chars.peek() {
-        if c.is_ascii_digit() {
-            has_digits = true;
-            while let Some((i, c)) = chars.peek() {
-                if c.is_ascii_digit() {
-                    end = *i + c.len_utf8();
-                    chars.next();
+        Some(b'1'..=b'9') => {
+            while let Some(d) = digit_at(bytes, pos) {
+                if mantissa < 1_000_000_000_000_000_000 {
+                    mantissa = mantissa * 10 + d;
                 } else {
-                    break;
+                    exponent += 1;
+                    truncated |= d != 0;
                 }
+                pos += 1;
             }
         }
+        _ => {
+            // A fraction or exponent needs an integer part, even after a minus
+            peek_char(&bytes[pos..])?;
+            return Err(JsonError::SyntaxError("Expected a digit at start of number".to_string()));
+        }
     }
 
     // Fractional part
-    if let Some((_, '.')) = chars.peek() {
-        end += 1;
-        chars.next();
-        let mut frac_digits = false;
-        while let Some((i, c)) = chars.peek() {
-            if c.is_ascii_digit() {
-                end = *i + c.len_utf8();
-                chars.next();
-                frac_digits = true;
+    if bytes.get(pos) == Some(&b'.') {
+        pos += 1;
+        let frac_start = pos;
+        while let Some(d) = digit_at(bytes, pos) {
+            if mantissa < 1_000_000_000_000_000_000 {
+                mantissa = mantissa * 10 + d;
+                exponent -= 1;
             } else {
-                break;
+                truncated |= d != 0;
             }
+            pos += 1;
         }
-        if !frac_digits {
+        if pos == frac_start {
+            peek_char(&bytes[pos..])?;
             return Err(JsonError::SyntaxError("Expected digits after decimal point".to_string()));
         }
-        has_digits = true;
     }
 
     // Exponent part
-    if let Some((_, c)) = chars.peek() {
-        if *c == 'e' || *c == 'E' {
-            end += 1;
-            chars.next();
-            // Optional sign
-            if let Some((_, c2)) = chars.peek() {
-                if *c2 == '+' || *c2 == '-' {
-                    end += 1;
-                    chars.next();
-                }
-            }
-            let mut exp_digits = false;
-            while let Some((i, c)) = chars.peek() {
-                if c.is_ascii_digit() {
-                    end = *i + c.len_utf8();
-                    chars.next();
-                    exp_digits = true;
-                } else {
-                    break;
-                }
-            }
-            if !exp_digits {
-                return Err(JsonError::SyntaxError("Expected digits in exponent".to_string()));
-            }
-            has_digits = true;
+    if let Some(b'e' | b'E') = bytes.get(pos) {
+        pos += 1;
+        // Optional sign
+        let exp_negative = bytes.get(pos) == Some(&b'-');
+        if let Some(b'+' | b'-') = bytes.get(pos) {
+            pos += 1;
+        }
+        let exp_start = pos;
+        let mut exp_value: i64 = 0;
+        while let Some(d) = digit_at(bytes, pos) {
+            exp_value = (exp_value * 10 + d as i64).min(MAX_EXPONENT);
+            pos += 1;
         }
+        if pos == exp_start {
+            peek_char(&bytes[pos..])?;
+            return Err(JsonError::SyntaxError("Expected digits in exponent".to_string()));
+        }
+        exponent += if exp_negative { -exp_value } else { exp_value };
     }
 
-    if !has_digits {
-        return Err(JsonError::SyntaxError("No digits found in number".to_string()));
+    let (number, rest) = bytes.split_at(pos);
+    let magnitude = if mantissa == 0 && !truncated {
+        0.0
+    } else if !truncated && mantissa <= MAX_EXACT && exponent.abs() < POW10.len() as i64 {
+        if exponent < 0 {
+            mantissa as f64 / POW10[-exponent as usize]
+        } else {
+            mantissa as f64 * POW10[exponent as usize]
+        }
+    } else {
+        // Rounding more digits than the mantissa holds, or a power of ten
+        // that is not exact, correctly takes arbitrary precision. The
+        // standard library's parser has that (Eisel-Lemire, then big
+        // decimals when that cannot decide) and reads the scanned bytes in
+        // place, so it stays rather than a second copy of that algorithm.
+        // The bytes are ASCII digits by now, so neither step can fail.
+        std::str::from_utf8(&number[negative as usize..]).map_err(|err| err.to_string())
+            .and_then(|digits| digits.parse::<f64>().map_err(|err| err.to_string()))
+            .map_err(JsonError::SyntaxError)?
+    };
+    let num = if negative { -magnitude } else { magnitude };
+    if num.is_infinite() && options.number_overflow == NumberOverflow::Reject {
+        return Err(JsonError::SyntaxError(format!("Number out of range: {}", String::from_utf8_lossy(number))));
     }
-
-    let (number_str, rest) = source.split_at(end);
-    let num = number_str.parse::<f64>()
-        .map_err(|err| JsonError::SyntaxError(format!("{}", err)))?;
     Ok((Json::JsonNumber(num), rest))
 }
 
+// JSON has no spelling for NaN or the infinities, so like JavaScript's
+// `JSON.stringify` this writes them as null
+pub fn dump_number(n: f64, out: &mut String) {
+    if n.is_finite() {
+        out.push_str(&n.to_string());
+    } else {
+        out.push_str("null");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_parse_number_integer() {
-        let input = "42 rest";
-        let (val, tail) = parse_number(input).unwrap();
+        let input = b"42 rest";
+        let (val, tail) = parse_number(input, &ParseOptions::default()).unwrap();
         assert_eq!(val, Json::JsonNumber(42.0));
-        assert_eq!(tail, " rest");
+        assert_eq!(tail, b" rest");
     }
 
     #[test]
     fn test_parse_number_negative_integer() {
-        let input = "-123,";
-        let (val, tail) = parse_number(input).unwrap();
+        let input = b"-123,";
+        let (val, tail) = parse_number(input, &ParseOptions::default()).unwrap();
         assert_eq!(val, Json::JsonNumber(-123.0));
-        assert_eq!(tail, ",");
+        assert_eq!(tail, b",");
     }
 
     #[test]
     fn test_parse_number_zero() {
-        let input = "0]";
-        let (val, tail) = parse_number(input).unwrap();
+        let input = b"0]";
+        let (val, tail) = parse_number(input, &ParseOptions::default()).unwrap();
         assert_eq!(val, Json::JsonNumber(0.0));
-        assert_eq!(tail, "]");
+        assert_eq!(tail, b"]");
     }
 
     #[test]
     fn test_parse_number_leading_zero_float() {
-        let input = "0.123}";
-        let (val, tail) = parse_number(input).unwrap();
+        let input = b"0.123}";
+        let (val, tail) = parse_number(input, &ParseOptions::default()).unwrap();
         assert_eq!(val, Json::JsonNumber(0.123));
-        assert_eq!(tail, "}");
+        assert_eq!(tail, b"}");
     }
 
     #[test]
     fn test_parse_number_float() {
-        let input = "-12.34 ";
-        let (val, tail) = parse_number(input).unwrap();
+        let input = b"-12.34 ";
+        let (val, tail) = parse_number(input, &ParseOptions::default()).unwrap();
         assert_eq!(val, Json::JsonNumber(-12.34));
-        assert_eq!(tail, " ");
+        assert_eq!(tail, b" ");
     }
 
     #[test]
     fn test_parse_number_exponent() {
-        let input = "6.022e23,";
-        let (val, tail) = parse_number(input).unwrap();
+        let input = b"6.022e23,";
+        let (val, tail) = parse_number(input, &ParseOptions::default()).unwrap();
         assert_eq!(val, Json::JsonNumber(6.022e23));
-        assert_eq!(tail, ",");
+        assert_eq!(tail, b",");
     }
 
     #[test]
     fn test_parse_number_exponent_negative() {
-        let input = "1e-10]";
-        let (val, tail) = parse_number(input).unwrap();
+        let input = b"1e-10]";
+        let (val, tail) = parse_number(input, &ParseOptions::default()).unwrap();
         assert_eq!(val, Json::JsonNumber(1e-10));
-        assert_eq!(tail, "]");
+        assert_eq!(tail, b"]");
     }
 
     #[test]
     fn test_parse_number_exponent_positive_sign() {
-        let input = "2E+2 ";
-        let (val, tail) = parse_number(input).unwrap();
+        let input = b"2E+2 ";
+        let (val, tail) = parse_number(input, &ParseOptions::default()).unwrap();
         assert_eq!(val, Json::JsonNumber(200.0));
-        assert_eq!(tail, " ");
+        assert_eq!(tail, b" ");
     }
 
     #[test]
     fn test_parse_number_only_minus() {
-        let input = "- rest";
-        assert!(parse_number(input).is_err());
+        let input = b"- rest";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_letter() {
-        let input = "a0123";
-        assert!(parse_number(input).is_err());
+        let input = b"a0123";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_leading_zero() {
-        let input = "0123";
-        assert!(parse_number(input).is_err());
+        let input = b"0123";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_leading_exponent() {
-        let input = "e0123";
-        assert!(parse_number(input).is_err());
+        let input = b"e0123";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_leading_dot() {
-        let input = ".123";
-        assert!(parse_number(input).is_err());
+        let input = b".123";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_double_dot() {
-        let input = "1..2";
-        assert!(parse_number(input).is_err());
+        let input = b"1..2";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_exponent() {
-        let input = "1e";
-        assert!(parse_number(input).is_err());
+        let input = b"1e";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_inf() {
-        let input = "inf";
-        assert!(parse_number(input).is_err());
+        let input = b"inf";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_ninf() {
-        let input = "ninf";
-        assert!(parse_number(input).is_err());
+        let input = b"ninf";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_number_invalid_nan() {
-        let input = "NaN";
-        assert!(parse_number(input).is_err());
+        let input = b"NaN";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_number_invalid_minus_dot() {
+        assert!(parse_number(b"-.123", &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_number_matches_std() {
+        let inputs = [
+            "0.1", "0.3", "123.456", "-0.0", "9007199254740993", "9007199254740992.5", "1e22", "1e23",
+            "12345678901234567890123", "0.1234567890123456789", "2.2250738585072014e-308", "4.9e-324",
+            "1.7976931348623157e308", "179769313486231580793728971405303415079934132710037826936173778980444968292764750946649017977587207096330286416692887910946555547851940402630657488671505820681908902000708383676273854845817711531764475730270069855571366959622842914819860834936475292719074168444365510704342711559699508093042880177904174497791.9999999999999999999999999999999999999999999999999999999999999999999999", "0.000000000000000000000000000000000000000000000001e48", "1e-400",
+        ];
+        for input in inputs {
+            let (val, tail) = parse_number(input.as_bytes(), &ParseOptions::default()).unwrap();
+            assert_eq!(tail, b"");
+            let expected: f64 = input.parse().unwrap();
+            assert_eq!(val.as_f64().map(f64::to_bits), Some(expected.to_bits()), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_number_overflow() {
+        let input = b"1.5e+9999]";
+        assert!(parse_number(input, &ParseOptions::default()).is_err());
+        let infinity = ParseOptions { number_overflow: NumberOverflow::Infinity, ..ParseOptions::default() };
+        let (val, tail) = parse_number(input, &infinity).unwrap();
+        assert_eq!(val, Json::JsonNumber(f64::INFINITY));
+        assert_eq!(tail, b"]");
+        let (val, _) = parse_number(b"-123123e100000", &infinity).unwrap();
+        assert_eq!(val, Json::JsonNumber(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn test_dump_number() {
+        let dumped = |n: f64| {
+            let mut out = String::new();
+            dump_number(n, &mut out);
+            out
+        };
+        assert_eq!(dumped(-1.5), "-1.5");
+        assert_eq!(dumped(1e21), "1000000000000000000000");
+        assert_eq!(dumped(f64::INFINITY), "null");
+        assert_eq!(dumped(f64::NEG_INFINITY), "null");
+        assert_eq!(dumped(f64::NAN), "null");
+    }
+
+    #[test]
+    fn test_parse_number_underflow_and_big_integers() {
+        let (val, _) = parse_number(b"123e-10000000", &ParseOptions::default()).unwrap();
+        assert_eq!(val, Json::JsonNumber(0.0));
+        let (val, _) = parse_number(b"100000000000000000000", &ParseOptions::default()).unwrap();
+        assert_eq!(val, Json::JsonNumber(1e20));
+    }
+}
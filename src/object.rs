@@ -1,98 +1,93 @@
 use std::ops::{Index, IndexMut};
+use std::sync::Arc;
 
 use crate::core;
-use crate::core::parse;
+use crate::core::parse_value;
 use crate::utils;
+use crate::options;
+use crate::map;
+use crate::lookup;
+use crate::intern;
 
 use core::{Json, JsonError};
-use utils::ignore_ws;
-
-fn parse_field(source: &str) -> Result<(String, &str), JsonError> {
-    let s = ignore_ws(source);
-    let mut chars = s.chars();
-    if let Some(c) = chars.next() {
-        if !(c=='"') {
-            return Err(JsonError::SyntaxError(format!("Expected a '\"' as start of JSON object field but found '{}'", c)));
-        }
-    } else {
-        return Err(JsonError::SyntaxError("Expected a '\"' start of JSON object field but found end of string".to_string()));
-    }
-    let mut field = String::new();
-    let mut idx = 0;
-    while let Some(c) = chars.next() {
-        idx += c.len_utf8();
-        if c == '"' {
-            break;
-        }
-        field.push(c);
+use utils::{ignore_ws_bytes, peek_char};
+use options::ParseOptions;
+use map::JsonMap;
+use lookup::Lookup;
+use intern::Keys;
+
+fn parse_field<'a>(source: &'a [u8], options: &ParseOptions, keys: &mut Keys) -> Result<(Arc<str>, &'a [u8]), JsonError> {
+    let s = ignore_ws_bytes(source);
+    if s.first() == Some(&b'"') {
+        return keys.parse(s, options);
     }
-    if !s[idx..].starts_with('"') {
-        return Err(JsonError::SyntaxError("Expected a '\"' but reached end of string".to_string()));
+    match peek_char(s)? {
+        Some(c) => Err(JsonError::SyntaxError(format!("Expected a '\"' as start of JSON object field but found '{}'", c))),
+        None => Err(JsonError::SyntaxError("Expected a '\"' start of JSON object field but found end of string".to_string())),
     }
-    idx += '"'.len_utf8();
-    let tail = &s[idx..];
-    Ok((field, tail))
 }
 
-pub fn parse_object(source: &str) -> Result<(Json, &str), JsonError> {
-    let mut s: &str = ignore_ws(source);
-    if !s.starts_with('{') {
+pub fn parse_object<'a>(source: &'a [u8], options: &ParseOptions, depth: usize, keys: &mut Keys) -> Result<(Json, &'a [u8]), JsonError> {
+    let mut s: &[u8] = ignore_ws_bytes(source);
+    if !s.starts_with(b"{") {
         return Err(JsonError::SyntaxError("Expected '{' at start of object".to_string()));
     }
     s = &s[1..]; // consume '{'
-    let mut json: Json = Json::new_object();
+    let mut map: JsonMap = JsonMap::new();
+
+    s = ignore_ws_bytes(s);
+    if s.starts_with(b"}") {
+        // Empty object
+        s = &s[1..];
+        return Ok((Json::JsonObject(map), s));
+    }
 
     loop {
-        s = ignore_ws(s);
-        if s.starts_with('}') {
-            s = &s[1..]; // consume '}'
-            break;
-        }
         // Parse field
-        let (field, tail) = parse_field(s)?;
-        s = ignore_ws(tail);
-        if !s.starts_with(':') {
+        let (field, tail) = parse_field(s, options, keys)?;
+        s = ignore_ws_bytes(tail);
+        if !s.starts_with(b":") {
+            peek_char(s)?;
             return Err(JsonError::SyntaxError("Expected ':' after field name".to_string()));
         }
-        s = ignore_ws(&s[1..]);
+        s = ignore_ws_bytes(&s[1..]);
         // Parse value
-        let (value, tail) = parse(s)?;
-        json[field] = value;
-        s = ignore_ws(tail);
-        if s.starts_with(',') {
-            s = &s[1..];
-            continue;
-        } else if s.starts_with('}') {
+        let (value, tail) = parse_value(s, options, depth, keys)?;
+        map.insert(field, value);
+        s = ignore_ws_bytes(tail);
+        if s.starts_with(b",") {
+            s = ignore_ws_bytes(&s[1..]);
+            if s.starts_with(b"}") {
+                return Err(JsonError::SyntaxError("JSON Object should not end in a comma".to_string()));
+            }
+        } else if s.starts_with(b"}") {
             s = &s[1..];
             break;
         } else if s.is_empty() {
             return Err(JsonError::SyntaxError("Unexpected end of input in object".to_string()));
         } else {
-            return Err(JsonError::SyntaxError(format!("Unexpected character in object: '{}'", s.chars().next().unwrap())));
+            return Err(JsonError::SyntaxError(format!("Unexpected character in object: '{}'", peek_char(s)?.unwrap_or_default())));
         }
     }
-    Ok((json, s))
+    Ok((Json::JsonObject(map), s))
 }
 
 impl Index<&str> for Json {
-    type Output = Json;
+    type Output = Lookup;
     fn index(&self, key: &str) -> &Self::Output {
-        match self {
-            Json::JsonObject(map) => map.get(key).unwrap_or(&Json::JsonEmpty),
-            _ => &Json::JsonEmpty,
-        }
+        Lookup::new(self.get(key))
     }
 }
 
 impl Index<String> for Json {
-    type Output = Json;
+    type Output = Lookup;
     fn index(&self, key: String) -> &Self::Output {
         self.index(key.as_str())
     }
 }
 
 impl Index<&String> for Json {
-    type Output = Json;
+    type Output = Lookup;
     fn index(&self, key: &String) -> &Self::Output {
         self.index(key.as_str())
     }
@@ -101,10 +96,7 @@ impl Index<&String> for Json {
 
 impl IndexMut<&str> for Json {
     fn index_mut(&mut self, key: &str) -> &mut Self::Output {
-        match self {
-            Json::JsonObject(map) => map.entry(key.to_string()).or_insert(Json::JsonEmpty),
-            _ => panic!("Not a JsonObject"),
-        }
+        Lookup::new_mut(self.get_mut(key))
     }
 }
 
@@ -127,54 +119,54 @@ mod tests {
     #[test]
     fn test_parse_field_valid() {
         let valid = "\"field\"";
-        let (field, tail) = parse_field(valid).unwrap();
-        assert_eq!(field, "field");
-        assert_eq!(tail, "");
+        let (field, tail) = parse_field(valid.as_bytes(), &ParseOptions::default(), &mut Keys::new(None)).unwrap();
+        assert_eq!(&*field, "field");
+        assert_eq!(tail, b"");
     }
 
     #[test]
     fn test_parse_field_valid_ws() {
         let valid = "  \"field\"   ";
-        let (field, tail) = parse_field(valid).unwrap();
-        assert_eq!(field, "field");
-        assert_eq!(tail, "   ");
+        let (field, tail) = parse_field(valid.as_bytes(), &ParseOptions::default(), &mut Keys::new(None)).unwrap();
+        assert_eq!(&*field, "field");
+        assert_eq!(tail, b"   ");
     }
 
     #[test]
     fn test_parse_field_valid_tail() {
         let valid = "  \"field\"  : rest ";
-        let (field, tail) = parse_field(valid).unwrap();
-        assert_eq!(field, "field");
-        assert_eq!(tail, "  : rest ");
+        let (field, tail) = parse_field(valid.as_bytes(), &ParseOptions::default(), &mut Keys::new(None)).unwrap();
+        assert_eq!(&*field, "field");
+        assert_eq!(tail, b"  : rest ");
     }
 
     #[test]
     fn test_parse_field_valid_ws_inside() {
         let valid = "\" field  \"";
-        let (field, tail) = parse_field(valid).unwrap();
-        assert_eq!(field, " field  ");
-        assert_eq!(tail, "");
+        let (field, tail) = parse_field(valid.as_bytes(), &ParseOptions::default(), &mut Keys::new(None)).unwrap();
+        assert_eq!(&*field, " field  ");
+        assert_eq!(tail, b"");
     }
 
     #[test]
     fn test_parse_field_syntax_error_1st_quote() {
         let valid = " field\"  ";
-        let result = parse_field(valid);
+        let result = parse_field(valid.as_bytes(), &ParseOptions::default(), &mut Keys::new(None));
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_field_syntax_error_last_quote() {
         let valid = "  \"field ";
-        let result = parse_field(valid);
+        let result = parse_field(valid.as_bytes(), &ParseOptions::default(), &mut Keys::new(None));
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_object_valid() {
         let valid = r#"{"type": "type1", "type2": 0, "type3" : null}"#;
-        let (obj, tail) = parse_object(valid).unwrap();
-        assert_eq!(tail, "");
+        let (obj, tail) = parse_object(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
+        assert_eq!(tail, b"");
         assert_eq!(obj["type"], Json::JsonString("type1".to_string()));
         assert_eq!(obj["type2"], Json::JsonNumber(0.0));
         assert_eq!(obj["type3"], Json::JsonNull);
@@ -183,8 +175,8 @@ mod tests {
     #[test]
     fn test_parse_object_empty() {
         let valid = r#"{}"#;
-        let (obj, tail) = parse_object(valid).unwrap();
-        assert_eq!(tail, "");
+        let (obj, tail) = parse_object(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
+        assert_eq!(tail, b"");
         // Should be an empty object
         if let Json::JsonObject(map) = obj {
             assert!(map.is_empty());
@@ -196,38 +188,54 @@ mod tests {
     #[test]
     fn test_parse_object_trailing_ws() {
         let valid = r#"{   "a": 1   }   "#;
-        let (obj, tail) = parse_object(valid).unwrap();
+        let (obj, tail) = parse_object(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
         assert_eq!(obj["a"], Json::JsonNumber(1.0));
-        assert_eq!(tail.trim(), "");
+        assert_eq!(tail.trim_ascii(), b"");
     }
 
     #[test]
     fn test_parse_object_missing_colon() {
         let invalid = r#"{"a" 1}"#;
-        let result = parse_object(invalid);
+        let result = parse_object(invalid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None));
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_object_missing_value() {
         let invalid = r#"{"a": }"#;
-        let result = parse_object(invalid);
+        let result = parse_object(invalid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None));
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_object_missing_field_quote() {
         let invalid = r#"{a: 1}"#;
-        let result = parse_object(invalid);
+        let result = parse_object(invalid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None));
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_object_nested() {
         let valid = r#"{"outer": {"inner": 42}}"#;
-        let (obj, tail) = parse_object(valid).unwrap();
-        assert_eq!(tail, "");
+        let (obj, tail) = parse_object(valid.as_bytes(), &ParseOptions::default(), 1, &mut Keys::new(None)).unwrap();
+        assert_eq!(tail, b"");
         let inner = &obj["outer"];
         assert_eq!(inner["inner"], Json::JsonNumber(42.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_field_escapes() {
+        let valid = r#""a\"b\u0041\n": 1"#;
+        let (field, tail) = parse_field(valid.as_bytes(), &ParseOptions::default(), &mut Keys::new(None)).unwrap();
+        assert_eq!(&*field, "a\"bA\n");
+        assert_eq!(tail, b": 1");
+        assert!(parse_field(b"\"a\tb\"", &ParseOptions::default(), &mut Keys::new(None)).is_err());
+    }
+
+    #[test]
+    fn test_parse_object_trailing_comma() {
+        assert!(parse_object(br#"{"id": 0,}"#, &ParseOptions::default(), 1, &mut Keys::new(None)).is_err());
+        assert!(parse_object(br#"{"id": 0,,}"#, &ParseOptions::default(), 1, &mut Keys::new(None)).is_err());
+        assert!(parse_object(b"{,}", &ParseOptions::default(), 1, &mut Keys::new(None)).is_err());
+    }
+}
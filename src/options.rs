@@ -0,0 +1,59 @@
+// Behavior of the strict parser for inputs RFC 8259 leaves to the
+// implementation (the `i_` cases of JSONTestSuite). The defaults reject
+// anything that cannot round-trip through `Json` unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    // Deepest allowed nesting of arrays and objects. Parsing is recursive,
+    // so this is also what keeps hostile input from overflowing the stack;
+    // raise it only along with the stack size of the parsing thread.
+    pub max_depth: usize,
+    // Skip a leading U+FEFF byte order mark instead of rejecting it
+    pub allow_bom: bool,
+    pub lone_surrogates: SurrogatePolicy,
+    pub number_overflow: NumberOverflow,
+    // Share one allocation per distinct object key within the document, see
+    // `Interner`
+    pub intern_keys: bool,
+}
+
+// What to do with a `\uD800`-style escape that is not part of a valid
+// surrogate pair. Rust strings cannot hold one as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SurrogatePolicy {
+    #[default]
+    Reject,
+    // Substitute U+FFFD REPLACEMENT CHARACTER
+    Replace,
+}
+
+// What to do with a number whose magnitude does not fit in an f64, e.g.
+// `1e400`. Numbers too small to represent always become zero, and integers
+// beyond 2^53 are always rounded to the nearest f64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberOverflow {
+    #[default]
+    Reject,
+    // Keep the value as positive or negative infinity, which `Json::dump`
+    // writes as null
+    Infinity,
+}
+
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+impl ParseOptions {
+    pub const fn new() -> Self {
+        ParseOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            allow_bom: false,
+            lone_surrogates: SurrogatePolicy::Reject,
+            number_overflow: NumberOverflow::Reject,
+            intern_keys: false,
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions::new()
+    }
+}
@@ -0,0 +1,262 @@
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::core;
+use crate::core::{parse_value, strip_bom};
+use crate::utils;
+use crate::options;
+use crate::intern;
+use crate::lazy;
+use crate::recover;
+use crate::diagnostic;
+
+use core::{Json, JsonError};
+use utils::{ignore_ws, ignore_ws_bytes};
+use options::ParseOptions;
+use intern::{Interner, Keys};
+use lazy::parse_lazy_with_options;
+use recover::parse_tolerant_with_options;
+use diagnostic::Severity;
+
+// A failure in a parallel parse. `offset` is a byte offset into the whole
+// input: the error itself when it can be pinned down, otherwise the start of
+// the element or line that contains it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParallelError {
+    pub offset: usize,
+    pub error: JsonError,
+}
+
+fn worker_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        n => n,
+    }
+}
+
+// Where in `text`, parsed `depth` levels down, the tolerant parser first
+// finds an error; 0 when it finds none, e.g. for options only the strict
+// parser knows about
+fn locate(text: &str, options: &ParseOptions, depth: usize) -> usize {
+    let options = ParseOptions { max_depth: options.max_depth.saturating_sub(depth), ..*options };
+    parse_tolerant_with_options(text, &options)
+        .diagnostics
+        .iter()
+        .find(|d| d.severity == Severity::Error)
+        .map_or(0, |d| d.span.start)
+}
+
+fn failure(offset: usize, text: &str, options: &ParseOptions, depth: usize, error: JsonError) -> ParallelError {
+    ParallelError { offset: offset + locate(text, options, depth), error }
+}
+
+// Splits `items` into at most `workers` runs of roughly equal input size;
+// `size` gives the bytes an item covers
+fn partition<T>(items: &[T], workers: usize, total: usize, size: impl Fn(&T) -> usize) -> Vec<&[T]> {
+    let target = total.div_ceil(workers).max(1);
+    let mut chunks = Vec::with_capacity(workers);
+    let (mut start, mut filled) = (0, 0);
+    for (i, item) in items.iter().enumerate() {
+        filled += size(item);
+        if filled >= target {
+            chunks.push(&items[start..=i]);
+            (start, filled) = (i + 1, 0);
+        }
+    }
+    if start < items.len() {
+        chunks.push(&items[start..]);
+    }
+    chunks
+}
+
+// Runs `job` on every chunk on its own thread and concatenates the results
+// in order. Each chunk stops at its first error, so the error of the
+// earliest failing chunk is the first one in the input.
+fn run_chunks<T: Sync>(chunks: Vec<&[T]>, job: impl Fn(&[T]) -> Result<Vec<Json>, ParallelError> + Sync) -> Result<Vec<Json>, ParallelError> {
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = chunks.into_iter().map(|chunk| scope.spawn(|| job(chunk))).collect();
+        handles.into_iter().map(|handle| handle.join().expect("parser thread panicked")).collect()
+    });
+    let mut values = Vec::new();
+    for result in results {
+        values.extend(result?);
+    }
+    Ok(values)
+}
+
+// Parses a document whose root is an array, splitting the elements between
+// `threads` threads (0 for one per available core). A sequential scan that
+// only matches brackets and quotes finds the element boundaries first.
+pub fn parse_array_parallel(source: &str, options: &ParseOptions, threads: usize) -> Result<Json, ParallelError> {
+    let base = source.len();
+    let source = strip_bom(source, options).map_err(|error| ParallelError { offset: 0, error })?;
+    let origin = base - source.len();
+    let root = parse_lazy_with_options(source, options).map_err(|error| ParallelError { offset: origin, error })?;
+    let mut elements = root.iter().map_err(|error| ParallelError { offset: origin + root.offset_in(source), error })?;
+
+    // Byte ranges of the elements, each running up to the next one
+    let mut starts = Vec::new();
+    for element in elements.by_ref() {
+        match element {
+            Ok(value) => starts.push(value.offset_in(source)),
+            // Skipping an element only finds that it nests too deep, so
+            // that is the one error worth pinning down inside it
+            Err(error @ JsonError::DepthError { .. }) => {
+                let (offset, depth) = starts.last().map_or((root.offset_in(source), 0), |&start| (start, 1));
+                return Err(failure(origin + offset, &source[offset..], options, depth, error));
+            }
+            Err(error) => {
+                let offset = starts.last().map_or(root.offset_in(source), |&start| start);
+                return Err(ParallelError { offset: origin + offset, error });
+            }
+        }
+    }
+    let close = source.len() - elements.rest().len();
+    let after = close + 1;
+    if !ignore_ws(&source[after..]).is_empty() {
+        let error = JsonError::SyntaxError("Unexpected trailing characters after JSON value".to_string());
+        return Err(ParallelError { offset: origin + source.len() - ignore_ws(&source[after..]).len(), error });
+    }
+    let ranges: Vec<(usize, usize)> = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| (start, starts.get(i + 1).copied().unwrap_or(close)))
+        .collect();
+
+    let chunks = partition(&ranges, worker_count(threads), close, |(start, end)| end - start);
+    let values = run_chunks(chunks, |chunk| {
+        let mut interner = Interner::new();
+        let mut keys = Keys::new(options.intern_keys.then_some(&mut interner));
+        let mut values = Vec::with_capacity(chunk.len());
+        for &(start, end) in chunk {
+            let text = &source[start..end];
+            let element = text.trim_end_matches([' ', '\t', '\n', '\r']);
+            let element = element.strip_suffix(',').unwrap_or(element);
+            let (value, tail) = parse_value(text.as_bytes(), options, 1, &mut keys).map_err(|error| failure(origin + start, element, options, 1, error))?;
+            if !matches!(ignore_ws_bytes(tail), [] | [b',', ..]) {
+                let error = JsonError::SyntaxError("JSON Array should separate values with commas".to_string());
+                return Err(ParallelError { offset: origin + end - tail.len(), error });
+            }
+            values.push(value);
+        }
+        Ok(values)
+    })?;
+    Ok(Json::JsonArray(values))
+}
+
+// Parses newline-delimited JSON, one value per line, across `threads`
+// threads (0 for one per available core). Blank lines are skipped. Raw
+// newlines cannot occur inside a JSON value, so every line break is a safe
+// place to split.
+pub fn parse_ndjson_parallel(source: &str, options: &ParseOptions, threads: usize) -> Result<Vec<Json>, ParallelError> {
+    let base = source.len();
+    let source = strip_bom(source, options).map_err(|error| ParallelError { offset: 0, error })?;
+    let origin = base - source.len();
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in source.split_inclusive('\n') {
+        if !ignore_ws(line).is_empty() {
+            lines.push((start, start + line.len()));
+        }
+        start += line.len();
+    }
+
+    let chunks = partition(&lines, worker_count(threads), source.len(), |(start, end)| end - start);
+    run_chunks(chunks, |chunk| {
+        let mut interner = Interner::new();
+        let mut keys = Keys::new(options.intern_keys.then_some(&mut interner));
+        let mut values = Vec::with_capacity(chunk.len());
+        for &(start, end) in chunk {
+            let line = &source[start..end];
+            let (value, tail) = parse_value(line.as_bytes(), options, 0, &mut keys).map_err(|error| failure(origin + start, line, options, 0, error))?;
+            if !ignore_ws_bytes(tail).is_empty() {
+                let error = JsonError::SyntaxError("Unexpected trailing characters after JSON value".to_string());
+                return Err(ParallelError { offset: origin + end - ignore_ws_bytes(tail).len(), error });
+            }
+            values.push(value);
+        }
+        Ok(values)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_with_options;
+
+    fn records(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!(r#"{{"id": {}, "name": "user {}", "tags": ["a", "b\n"], "nested": [[{{}}], []]}}"#, i, i)).collect()
+    }
+
+    #[test]
+    fn test_parse_array_parallel_matches_sequential() {
+        let options = ParseOptions::default();
+        for n in [0, 1, 2, 7, 100] {
+            let source = format!(" [ {} ] ", records(n).join(" ,\n"));
+            let expected = parse_with_options(&source, &options).unwrap();
+            for threads in [0, 1, 3, 8] {
+                assert_eq!(parse_array_parallel(&source, &options, threads).unwrap(), expected);
+            }
+        }
+        let interned = ParseOptions { intern_keys: true, ..options };
+        let source = format!("[{}]", records(10).join(","));
+        assert_eq!(parse_array_parallel(&source, &interned, 4).unwrap(), parse_with_options(&source, &options).unwrap());
+    }
+
+    #[test]
+    fn test_parse_array_parallel_errors() {
+        let options = ParseOptions::default();
+        let err = parse_array_parallel(r#"[1, 2, {"a": tru}, 4]"#, &options, 2).unwrap_err();
+        assert_eq!(err.offset, 13);
+        let err = parse_array_parallel("[1, 2, 3", &options, 2).unwrap_err();
+        assert_eq!(err.offset, 7);
+        let err = parse_array_parallel("[1, 2] 3", &options, 2).unwrap_err();
+        assert_eq!(err.offset, 7);
+        let err = parse_array_parallel("[1, 2,]", &options, 2).unwrap_err();
+        assert_eq!(err.offset, 4);
+        assert!(parse_array_parallel("{}", &options, 2).is_err());
+        assert!(parse_array_parallel("[1 2]", &options, 2).is_err());
+        assert!(parse_array_parallel(r#"["\uD800"]"#, &options, 2).is_err());
+    }
+
+    #[test]
+    fn test_parallel_depth_errors() {
+        let options = ParseOptions::default();
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        for depth in [options.max_depth, 200_000] {
+            let source = format!("[1, {}, 3]", nested(depth));
+            let err = parse_array_parallel(&source, &options, 2).unwrap_err();
+            assert_eq!(err.offset, 4 + options.max_depth - 1);
+            assert_eq!(err.error, JsonError::DepthError { max_depth: options.max_depth });
+            let source = format!("1\n{}\n3\n", nested(depth + 1));
+            let err = parse_ndjson_parallel(&source, &options, 2).unwrap_err();
+            assert_eq!(err.offset, 2 + options.max_depth);
+        }
+        let deeper = ParseOptions { max_depth: 200, ..options };
+        let source = format!("[{}]", nested(150));
+        assert_eq!(parse_array_parallel(&source, &deeper, 2).unwrap(), parse_with_options(&source, &deeper).unwrap());
+    }
+
+    #[test]
+    fn test_parse_ndjson_parallel() {
+        let options = ParseOptions::default();
+        let lines = records(50);
+        let source = format!("{}\n\n{}\r\n", lines[..20].join("\n"), lines[20..].join("\n"));
+        let expected: Vec<Json> = lines.iter().map(|l| parse_with_options(l, &options).unwrap()).collect();
+        for threads in [0, 1, 4, 64] {
+            assert_eq!(parse_ndjson_parallel(&source, &options, threads).unwrap(), expected);
+        }
+        assert_eq!(parse_ndjson_parallel("", &options, 4).unwrap(), Vec::<Json>::new());
+    }
+
+    #[test]
+    fn test_parse_ndjson_parallel_reports_first_error() {
+        let options = ParseOptions::default();
+        let source = "1\n2\n[3,]\n4\n{\"a\" 5}\n";
+        let err = parse_ndjson_parallel(source, &options, 4).unwrap_err();
+        assert_eq!(err.offset, 6);
+        let err = parse_ndjson_parallel("1\n2 3\n", &options, 1).unwrap_err();
+        assert_eq!(err.offset, 4);
+    }
+}
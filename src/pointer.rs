@@ -0,0 +1,184 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core;
+
+use core::{Json, JsonError};
+
+// An RFC 6901 JSON Pointer held as its unescaped reference tokens. Whether a
+// token names a member or an element is decided by the value it is applied
+// to, so "/0" works for both `{"0": ..}` and `[..]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    // The empty pointer, which refers to the whole document
+    pub fn root() -> Self {
+        JsonPointer::default()
+    }
+
+    pub fn parse(pointer: &str) -> Result<Self, JsonError> {
+        if pointer.is_empty() {
+            return Ok(JsonPointer::root());
+        }
+        let rest = pointer
+            .strip_prefix('/')
+            .ok_or_else(|| JsonError::SyntaxError(format!("JSON Pointer should start with '/': {:?}", pointer)))?;
+        let mut tokens = Vec::new();
+        for raw in rest.split('/') {
+            let mut token = String::with_capacity(raw.len());
+            let mut chars = raw.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '~' => match chars.next() {
+                        Some('0') => token.push('~'),
+                        Some('1') => token.push('/'),
+                        _ => return Err(JsonError::SyntaxError(format!("Invalid escape in JSON Pointer {:?}", pointer))),
+                    },
+                    _ => token.push(c),
+                }
+            }
+            tokens.push(token);
+        }
+        Ok(JsonPointer { tokens })
+    }
+
+    pub fn push<T: Into<String>>(&mut self, token: T) {
+        self.tokens.push(token.into());
+    }
+
+    pub fn pop(&mut self) -> Option<String> {
+        self.tokens.pop()
+    }
+
+    pub fn join<T: Into<String>>(&self, token: T) -> JsonPointer {
+        let mut pointer = self.clone();
+        pointer.push(token);
+        pointer
+    }
+
+    // None for the root, which has no parent
+    pub fn parent(&self) -> Option<JsonPointer> {
+        let (_, init) = self.tokens.split_last()?;
+        Some(JsonPointer { tokens: init.to_vec() })
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &str> {
+        self.tokens.iter().map(String::as_str)
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.tokens.last().map(String::as_str)
+    }
+
+    // Number of tokens, i.e. how deep the referenced value is nested
+    pub fn depth(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn resolve<'a>(&self, json: &'a Json) -> Option<&'a Json> {
+        self.tokens.iter().try_fold(json, |current, token| match current {
+            Json::JsonObject(map) => map.get(token),
+            Json::JsonArray(values) => values.get(parse_array_index(token)?),
+            _ => None,
+        })
+    }
+
+    pub fn resolve_mut<'a>(&self, json: &'a mut Json) -> Option<&'a mut Json> {
+        self.tokens.iter().try_fold(json, |current, token| match current {
+            Json::JsonObject(map) => map.get_mut(token),
+            Json::JsonArray(values) => values.get_mut(parse_array_index(token)?),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = JsonError;
+    fn from_str(pointer: &str) -> Result<Self, Self::Err> {
+        JsonPointer::parse(pointer)
+    }
+}
+
+impl Json {
+    // Looks up an RFC 6901 pointer such as "/items/0/name"; "" is the value
+    // itself. None when the pointer is malformed or names nothing.
+    pub fn pointer(&self, pointer: &str) -> Option<&Json> {
+        JsonPointer::parse(pointer).ok()?.resolve(self)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Json> {
+        JsonPointer::parse(pointer).ok()?.resolve_mut(self)
+    }
+}
+
+// Decimal digits without leading zeros, as RFC 6901 requires
+pub(crate) fn parse_array_index(token: &str) -> Option<usize> {
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer_round_trip() {
+        for source in ["", "/", "/a", "/a~1b/m~0n/0", "//x/", "/~01"] {
+            let pointer = JsonPointer::parse(source).unwrap();
+            assert_eq!(pointer.to_string(), source);
+        }
+        let pointer: JsonPointer = "/a~1b/~01".parse().unwrap();
+        assert_eq!(pointer.tokens().collect::<Vec<_>>(), ["a/b", "~1"]);
+        assert_eq!(pointer.last(), Some("~1"));
+        assert_eq!(pointer.parent().unwrap().to_string(), "/a~1b");
+        assert_eq!(JsonPointer::root().parent(), None);
+        assert_eq!(JsonPointer::root().join("x").join("0").to_string(), "/x/0");
+        assert!(JsonPointer::parse("a").is_err());
+        assert!(JsonPointer::parse("/a~2").is_err());
+        assert!(JsonPointer::parse("/a~").is_err());
+    }
+
+    // RFC 6901 section 5
+    #[test]
+    fn test_pointer_rfc_examples() {
+        let doc: Json = r#"{
+            "foo": ["bar", "baz"], "": 0, "a/b": 1, "c%d": 2, "e^f": 3,
+            "g|h": 4, "i\\j": 5, "k\"l": 6, " ": 7, "m~n": 8
+        }"#.parse().unwrap();
+        assert_eq!(doc.pointer(""), Some(&doc));
+        assert_eq!(doc.pointer("/foo/0"), Some(&Json::JsonString("bar".to_string())));
+        let expected = [("/", 0), ("/a~1b", 1), ("/c%d", 2), ("/e^f", 3), ("/g|h", 4), ("/i\\j", 5), ("/k\"l", 6), ("/ ", 7), ("/m~0n", 8)];
+        for (pointer, n) in expected {
+            assert_eq!(doc.pointer(pointer).and_then(Json::as_i64), Some(n), "{}", pointer);
+        }
+        assert_eq!(doc.pointer("/foo/01"), None);
+        assert_eq!(doc.pointer("/foo/-"), None);
+        assert_eq!(doc.pointer("/foo/0/x"), None);
+    }
+
+    #[test]
+    fn test_pointer_mut() {
+        let mut doc: Json = r#"{"a": [1, {"b": 2}]}"#.parse().unwrap();
+        *doc.pointer_mut("/a/1/b").unwrap() = Json::JsonBoolean(true);
+        assert_eq!(doc, r#"{"a": [1, {"b": true}]}"#.parse::<Json>().unwrap());
+        assert!(doc.pointer_mut("/a/2").is_none());
+    }
+}
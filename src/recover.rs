@@ -0,0 +1,650 @@
+use crate::core;
+use crate::map;
+use crate::diagnostic;
+use crate::options;
+
+use core::Json;
+use map::JsonMap;
+use diagnostic::{Diagnostic, ErrorCode, Severity, Span};
+use options::ParseOptions;
+
+// Outcome of a tolerant parse: the best-effort tree plus everything that was
+// wrong with the input. Unparseable values are replaced by null.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovered {
+    pub json: Json,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Recovered {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+}
+
+struct TolerantParser<'a> {
+    source: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    options: &'a ParseOptions,
+    // Arrays and objects enclosing the current position
+    depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn is_key_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+// Strict RFC 8259 number grammar, used to flag tokens we could still read
+fn is_valid_number(token: &str) -> bool {
+    let b = token.as_bytes();
+    let mut i = 0;
+    if b.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    match b.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => {
+            while b.get(i).is_some_and(u8::is_ascii_digit) {
+                i += 1;
+            }
+        }
+        _ => return false,
+    }
+    if b.get(i) == Some(&b'.') {
+        i += 1;
+        let start = i;
+        while b.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        if i == start {
+            return false;
+        }
+    }
+    if matches!(b.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(b.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        let start = i;
+        while b.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        if i == start {
+            return false;
+        }
+    }
+    i == b.len()
+}
+
+impl<'a> TolerantParser<'a> {
+    fn report(&mut self, code: ErrorCode, span: Span, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic::new(code, span, message));
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn char_span(&self) -> Span {
+        Span::new(self.pos, self.pos + self.peek_char().map_or(0, char::len_utf8))
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    // Skips a run of unparseable input up to the next separator or closer
+    fn skip_garbage(&mut self) -> Span {
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            if matches!(b, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r') {
+                break;
+            }
+            self.pos += self.peek_char().map_or(1, char::len_utf8);
+        }
+        Span::new(start, self.pos)
+    }
+
+    // Skips the array or object starting here, along with everything nested
+    // in it, without building anything, so depth costs no stack
+    fn skip_nested(&mut self) -> Span {
+        let start = self.pos;
+        let mut open = 0usize;
+        let mut in_string = false;
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            match b {
+                b'\\' if in_string => self.pos = (self.pos + 1).min(self.bytes.len()),
+                b'"' => in_string = !in_string,
+                _ if in_string => {}
+                b'[' | b'{' => open += 1,
+                b']' | b'}' => {
+                    open -= 1;
+                    if open == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        // A skipped escape may have stopped inside a multibyte character
+        while !self.source.is_char_boundary(self.pos) {
+            self.pos += 1;
+        }
+        Span::new(start, self.pos)
+    }
+
+    fn parse_value(&mut self) -> Json {
+        self.skip_ws();
+        match self.peek() {
+            None => {
+                self.report(ErrorCode::UnexpectedEof, Span::at(self.pos), "Expected a value but found end of input");
+                Json::JsonNull
+            }
+            Some(b'{' | b'[') if self.depth >= self.options.max_depth => {
+                let span = self.skip_nested();
+                let message = format!("Nesting exceeds the maximum depth of {}", self.options.max_depth);
+                self.report(ErrorCode::NestingTooDeep, span, message);
+                Json::JsonNull
+            }
+            Some(b'{') => {
+                self.depth += 1;
+                let json = self.parse_object();
+                self.depth -= 1;
+                json
+            }
+            Some(b'[') => {
+                self.depth += 1;
+                let json = self.parse_array();
+                self.depth -= 1;
+                json
+            }
+            Some(b'"') => Json::JsonString(self.parse_string(b'"')),
+            Some(b'\'') => {
+                let start = self.pos;
+                let s = self.parse_string(b'\'');
+                self.report(ErrorCode::UnexpectedCharacter, Span::new(start, self.pos), "Strings must use double quotes");
+                Json::JsonString(s)
+            }
+            Some(b'-' | b'+' | b'.' | b'0'..=b'9') => self.parse_number(),
+            Some(b) if b.is_ascii_alphabetic() => self.parse_word(),
+            Some(b',' | b']' | b'}' | b':') => {
+                self.report(ErrorCode::MissingValue, Span::at(self.pos), "Expected a value");
+                Json::JsonNull
+            }
+            Some(_) => {
+                let c = self.peek_char().unwrap();
+                let span = self.skip_garbage();
+                self.report(ErrorCode::UnexpectedCharacter, span, format!("Unexpected character '{}'", c));
+                Json::JsonNull
+            }
+        }
+    }
+
+    fn parse_word(&mut self) -> Json {
+        let start = self.pos;
+        while self.peek().is_some_and(is_key_char) {
+            self.pos += 1;
+        }
+        match &self.source[start..self.pos] {
+            "true" => Json::JsonBoolean(true),
+            "false" => Json::JsonBoolean(false),
+            "null" => Json::JsonNull,
+            word => {
+                let message = format!("Unknown literal '{}'", word);
+                self.report(ErrorCode::InvalidLiteral, Span::new(start, self.pos), message);
+                Json::JsonNull
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Json {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let token = &self.source[start..self.pos];
+        if is_valid_number(token) {
+            return Json::JsonNumber(token.parse().unwrap());
+        }
+        let message = format!("Invalid number '{}'", token);
+        self.report(ErrorCode::InvalidNumber, Span::new(start, self.pos), message);
+        token.parse().map_or(Json::JsonNull, Json::JsonNumber)
+    }
+
+    fn parse_hex4(&mut self) -> Option<u32> {
+        let hex = self.source.get(self.pos..self.pos + 4)?;
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.pos += 4;
+        u32::from_str_radix(hex, 16).ok()
+    }
+
+    fn parse_unicode_escape(&mut self, escape_start: usize) -> char {
+        let Some(high) = self.parse_hex4() else {
+            self.report(ErrorCode::InvalidEscape, Span::new(escape_start, self.pos), "Expected 4 hex digits after '\\u'");
+            return char::REPLACEMENT_CHARACTER;
+        };
+        if (0xD800..0xDC00).contains(&high) && self.source[self.pos..].starts_with("\\u") {
+            let save = self.pos;
+            self.pos += 2;
+            if let Some(low) = self.parse_hex4()
+                && (0xDC00..0xE000).contains(&low) {
+                return char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).unwrap();
+            }
+            self.pos = save;
+        }
+        char::from_u32(high).unwrap_or_else(|| {
+            self.report(ErrorCode::InvalidEscape, Span::new(escape_start, self.pos), "Unpaired surrogate in unicode escape");
+            char::REPLACEMENT_CHARACTER
+        })
+    }
+
+    fn parse_string(&mut self, quote: u8) -> String {
+        let start = self.pos;
+        self.pos += 1;
+        let mut result = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    self.report(ErrorCode::UnterminatedString, Span::new(start, self.pos), "Unterminated string");
+                    break;
+                }
+                Some(b) if b == quote => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\n' | b'\r') => {
+                    self.report(ErrorCode::UnterminatedString, Span::new(start, self.pos), "Unterminated string: missing closing quote before end of line");
+                    break;
+                }
+                Some(b'\\') => {
+                    let escape_start = self.pos;
+                    self.pos += 1;
+                    let Some(e) = self.peek_char() else {
+                        continue;
+                    };
+                    self.pos += e.len_utf8();
+                    match e {
+                        '"' | '\\' | '/' => result.push(e),
+                        'b' => result.push('\u{0008}'),
+                        'f' => result.push('\u{000C}'),
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'u' => {
+                            let c = self.parse_unicode_escape(escape_start);
+                            result.push(c);
+                        }
+                        other => {
+                            let message = format!("Invalid escape character '{}'", other);
+                            self.report(ErrorCode::InvalidEscape, Span::new(escape_start, self.pos), message);
+                            result.push(other);
+                        }
+                    }
+                }
+                Some(b) if b < 0x20 => {
+                    let message = format!("Unescaped control character U+{:04X} in string", b);
+                    self.report(ErrorCode::ControlCharacter, Span::new(self.pos, self.pos + 1), message);
+                    result.push(b as char);
+                    self.pos += 1;
+                }
+                Some(_) => {
+                    let c = self.peek_char().unwrap();
+                    result.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+        result
+    }
+
+    fn parse_key(&mut self) -> Option<String> {
+        let start = self.pos;
+        match self.peek() {
+            Some(b'"') => Some(self.parse_string(b'"')),
+            Some(b'\'') => {
+                let key = self.parse_string(b'\'');
+                self.report(ErrorCode::UnquotedKey, Span::new(start, self.pos), "Object keys must be double-quoted");
+                Some(key)
+            }
+            Some(b) if is_key_char(b) => {
+                while self.peek().is_some_and(is_key_char) {
+                    self.pos += self.peek_char().map_or(1, char::len_utf8);
+                }
+                self.report(ErrorCode::UnquotedKey, Span::new(start, self.pos), "Object keys must be double-quoted");
+                Some(self.source[start..self.pos].to_string())
+            }
+            _ => {
+                let span = self.skip_garbage();
+                self.report(ErrorCode::UnexpectedCharacter, span, "Expected an object key");
+                None
+            }
+        }
+    }
+
+    // Consumes a ',' and flags it when a closer follows directly
+    fn parse_comma(&mut self, close: u8, container: &str) {
+        let comma = Span::new(self.pos, self.pos + 1);
+        self.pos += 1;
+        self.skip_ws();
+        if self.peek() == Some(close) {
+            self.report(ErrorCode::TrailingComma, comma, format!("Trailing comma in {}", container));
+        }
+    }
+
+    fn mismatched_close(&mut self, expected: char) {
+        let found = self.peek_char().unwrap();
+        let message = format!("Expected '{}' but found '{}'", expected, found);
+        self.report(ErrorCode::UnexpectedCharacter, self.char_span(), message);
+        self.pos += 1;
+    }
+
+    fn parse_object(&mut self) -> Json {
+        let open = Span::new(self.pos, self.pos + 1);
+        self.pos += 1; // consume '{'
+        let mut map = JsonMap::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => {
+                    self.report(ErrorCode::UnclosedContainer, open, "Unclosed object: expected '}'");
+                    break;
+                }
+                Some(b'}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b']') => {
+                    self.mismatched_close('}');
+                    break;
+                }
+                Some(b',') => {
+                    self.report(ErrorCode::UnexpectedCharacter, self.char_span(), "Expected an object key but found ','");
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let key_start = self.pos;
+            let Some(key) = self.parse_key() else {
+                continue;
+            };
+            let key_span = Span::new(key_start, self.pos);
+            self.skip_ws();
+            let value = if self.peek() == Some(b':') {
+                self.pos += 1;
+                self.parse_value()
+            } else {
+                self.report(ErrorCode::MissingColon, Span::at(key_span.end), "Expected ':' after object key");
+                if matches!(self.peek(), None | Some(b',' | b'}' | b']')) {
+                    Json::JsonNull
+                } else {
+                    self.parse_value()
+                }
+            };
+            if map.insert(key.clone(), value).is_some() {
+                self.report(ErrorCode::DuplicateKey, key_span, format!("Duplicate key '{}'", key));
+            }
+
+            let value_end = self.pos;
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.parse_comma(b'}', "object"),
+                Some(b'}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b']') => {
+                    self.mismatched_close('}');
+                    break;
+                }
+                None => {
+                    self.report(ErrorCode::UnclosedContainer, open, "Unclosed object: expected '}'");
+                    break;
+                }
+                Some(_) => {
+                    self.report(ErrorCode::MissingComma, Span::at(value_end), "Expected ',' or '}' after object member");
+                }
+            }
+        }
+        Json::JsonObject(map)
+    }
+
+    fn parse_array(&mut self) -> Json {
+        let open = Span::new(self.pos, self.pos + 1);
+        self.pos += 1; // consume '['
+        let mut vec = Vec::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => {
+                    self.report(ErrorCode::UnclosedContainer, open, "Unclosed array: expected ']'");
+                    break;
+                }
+                Some(b']') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'}') => {
+                    self.mismatched_close(']');
+                    break;
+                }
+                Some(b',' | b':') => {
+                    self.report(ErrorCode::MissingValue, self.char_span(), "Expected a value");
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            vec.push(self.parse_value());
+
+            let value_end = self.pos;
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.parse_comma(b']', "array"),
+                Some(b']') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'}') => {
+                    self.mismatched_close(']');
+                    break;
+                }
+                None => {
+                    self.report(ErrorCode::UnclosedContainer, open, "Unclosed array: expected ']'");
+                    break;
+                }
+                Some(_) => {
+                    self.report(ErrorCode::MissingComma, Span::at(value_end), "Expected ',' or ']' after array element");
+                }
+            }
+        }
+        Json::JsonArray(vec)
+    }
+}
+
+// Of `options`, `max_depth` and `allow_bom` apply; the tolerant parser
+// already reads what the others would make the strict parser reject
+pub fn parse_tolerant_with_options(source: &str, options: &ParseOptions) -> Recovered {
+    let mut parser = TolerantParser {
+        source,
+        bytes: source.as_bytes(),
+        pos: 0,
+        options,
+        depth: 0,
+        diagnostics: Vec::new(),
+    };
+    if options.allow_bom && source.starts_with('\u{FEFF}') {
+        parser.pos = '\u{FEFF}'.len_utf8();
+    }
+    let json = parser.parse_value();
+    parser.skip_ws();
+    if parser.pos < source.len() {
+        let span = Span::new(parser.pos, source.len());
+        parser.report(ErrorCode::TrailingCharacters, span, "Unexpected trailing characters after JSON value");
+    }
+    Recovered { json, diagnostics: parser.diagnostics }
+}
+
+pub fn parse_tolerant(source: &str) -> Recovered {
+    parse_tolerant_with_options(source, &ParseOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::DEFAULT_MAX_DEPTH;
+
+    fn codes(recovered: &Recovered) -> Vec<ErrorCode> {
+        recovered.diagnostics.iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_tolerant_valid_input() {
+        let recovered = parse_tolerant(r#"{"a": [1, 2.5e3, "xA"], "b": {"c": null, "d": true}}"#);
+        assert!(recovered.diagnostics.is_empty());
+        assert!(!recovered.has_errors());
+        assert_eq!(recovered.json, json!({"a": [1, 2500, "xA"], "b": {"c": null, "d": true}}));
+    }
+
+    #[test]
+    fn test_tolerant_missing_commas() {
+        let src = "{\"a\": 1 \"b\": [1 2 3]}";
+        let recovered = parse_tolerant(src);
+        assert_eq!(recovered.json, json!({"a": 1, "b": [1, 2, 3]}));
+        assert_eq!(codes(&recovered), [ErrorCode::MissingComma, ErrorCode::MissingComma, ErrorCode::MissingComma]);
+        assert_eq!(recovered.diagnostics[0].span, Span::at(7));
+    }
+
+    #[test]
+    fn test_tolerant_unterminated_string() {
+        let src = "{\"a\": \"open,\n \"b\": 2}";
+        let recovered = parse_tolerant(src);
+        assert_eq!(recovered.json, json!({"a": "open,", "b": 2}));
+        assert_eq!(codes(&recovered), [ErrorCode::UnterminatedString, ErrorCode::MissingComma]);
+        assert_eq!(recovered.diagnostics[0].span, Span::new(6, 12));
+    }
+
+    #[test]
+    fn test_tolerant_stray_tokens() {
+        let recovered = parse_tolerant("[1, @@, 2, ture]");
+        assert_eq!(recovered.json, json!([1, null, 2, null]));
+        assert_eq!(codes(&recovered), [ErrorCode::UnexpectedCharacter, ErrorCode::InvalidLiteral]);
+        assert_eq!(recovered.diagnostics[0].span, Span::new(4, 6));
+        assert_eq!(recovered.diagnostics[1].span, Span::new(11, 15));
+    }
+
+    #[test]
+    fn test_tolerant_keys_and_colons() {
+        let recovered = parse_tolerant("{a: 1, 'b': 2, \"c\" 3, \"a\": 4}");
+        assert_eq!(recovered.json, json!({"a": 4, "b": 2, "c": 3}));
+        assert_eq!(
+            codes(&recovered),
+            [ErrorCode::UnquotedKey, ErrorCode::UnquotedKey, ErrorCode::MissingColon, ErrorCode::DuplicateKey]
+        );
+        assert_eq!(recovered.diagnostics[3].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_tolerant_trailing_commas_and_missing_values() {
+        let recovered = parse_tolerant("[1, , 2,]");
+        assert_eq!(recovered.json, json!([1, 2]));
+        assert_eq!(codes(&recovered), [ErrorCode::MissingValue, ErrorCode::TrailingComma]);
+
+        let recovered = parse_tolerant(r#"{"a": , "b": 1,}"#);
+        assert_eq!(recovered.json, json!({"a": null, "b": 1}));
+        assert_eq!(codes(&recovered), [ErrorCode::MissingValue, ErrorCode::TrailingComma]);
+    }
+
+    #[test]
+    fn test_tolerant_unclosed_and_mismatched() {
+        let recovered = parse_tolerant(r#"{"a": [1, 2"#);
+        assert_eq!(recovered.json, json!({"a": [1, 2]}));
+        assert_eq!(codes(&recovered), [ErrorCode::UnclosedContainer, ErrorCode::UnclosedContainer]);
+        assert_eq!(recovered.diagnostics[0].span, Span::new(6, 7));
+        assert_eq!(recovered.diagnostics[1].span, Span::new(0, 1));
+
+        let recovered = parse_tolerant(r#"[{"a": 1]"#);
+        assert_eq!(recovered.json, json!([{"a": 1}]));
+        assert_eq!(codes(&recovered), [ErrorCode::UnexpectedCharacter, ErrorCode::UnclosedContainer]);
+    }
+
+    #[test]
+    fn test_tolerant_strings_and_numbers() {
+        let recovered = parse_tolerant("[\"a\\qb\", \"tab\there\", 01, .5, -, 'single']");
+        assert_eq!(recovered.json, json!(["aqb", "tab\there", 1, 0.5, null, "single"]));
+        assert_eq!(
+            codes(&recovered),
+            [
+                ErrorCode::InvalidEscape,
+                ErrorCode::ControlCharacter,
+                ErrorCode::InvalidNumber,
+                ErrorCode::InvalidNumber,
+                ErrorCode::InvalidNumber,
+                ErrorCode::UnexpectedCharacter,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tolerant_empty_and_trailing() {
+        let recovered = parse_tolerant("  ");
+        assert_eq!(recovered.json, Json::JsonNull);
+        assert_eq!(codes(&recovered), [ErrorCode::UnexpectedEof]);
+
+        let recovered = parse_tolerant("[1] [2]");
+        assert_eq!(recovered.json, json!([1]));
+        assert_eq!(codes(&recovered), [ErrorCode::TrailingCharacters]);
+        assert_eq!(recovered.diagnostics[0].span, Span::new(4, 7));
+    }
+
+    #[test]
+    fn test_tolerant_depth_limit() {
+        let recovered = parse_tolerant(&"[".repeat(200_000));
+        assert_eq!(recovered.diagnostics[0].code, ErrorCode::NestingTooDeep);
+        assert_eq!(recovered.diagnostics[0].span, Span::new(DEFAULT_MAX_DEPTH, 200_000));
+        assert_eq!(recovered.diagnostics.len(), DEFAULT_MAX_DEPTH + 1);
+        assert!(recovered.has_errors());
+
+        let deep = format!("{}\"]}}\\\"[\"{}", "[".repeat(DEFAULT_MAX_DEPTH + 1), "]".repeat(DEFAULT_MAX_DEPTH + 1));
+        let recovered = parse_tolerant(&format!("{{\"a\": {}, \"b\": [1 2]}}", deep));
+        assert_eq!(codes(&recovered), [ErrorCode::NestingTooDeep, ErrorCode::MissingComma]);
+        assert_eq!(recovered.json["b"], json!([1, 2]));
+        let recovered = parse_tolerant(&"[".repeat(DEFAULT_MAX_DEPTH).chars().chain("]".repeat(DEFAULT_MAX_DEPTH).chars()).collect::<String>());
+        assert!(recovered.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_tolerant_with_options() {
+        let shallow = ParseOptions { max_depth: 2, ..ParseOptions::default() };
+        let recovered = parse_tolerant_with_options("[[1], [[2]], 3", &shallow);
+        assert_eq!(codes(&recovered), [ErrorCode::NestingTooDeep, ErrorCode::UnclosedContainer]);
+        assert_eq!(recovered.diagnostics[0].span, Span::new(7, 10));
+        assert_eq!(recovered.diagnostics[0].message, "Nesting exceeds the maximum depth of 2");
+        assert_eq!(recovered.json, json!([[1], [null], 3]));
+        let deep = ParseOptions { max_depth: 300, ..ParseOptions::default() };
+        assert!(parse_tolerant_with_options(&format!("{}{}", "[".repeat(300), "]".repeat(300)), &deep).diagnostics.is_empty());
+
+        let bom = ParseOptions { allow_bom: true, ..ParseOptions::default() };
+        assert_eq!(parse_tolerant_with_options("\u{FEFF}[1]", &bom), Recovered { json: json!([1]), diagnostics: Vec::new() });
+        assert!(parse_tolerant("\u{FEFF}[1]").has_errors());
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let recovered = parse_tolerant("[1 2]");
+        assert_eq!(recovered.diagnostics[0].to_string(), "error[E003]: Expected ',' or ']' after array element at 2..2");
+    }
+}
@@ -0,0 +1,268 @@
+use crate::diagnostic;
+
+use diagnostic::{Diagnostic, ErrorCode, Severity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    #[default]
+    Plain,
+    Ansi,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderOptions<'a> {
+    pub mode: RenderMode,
+    // Shown in the location line, e.g. `config.json:3:12`
+    pub file_name: Option<&'a str>,
+}
+
+// Spans longer than this many lines are cut in the middle
+const MAX_SPAN_LINES: usize = 6;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+
+fn hint(code: ErrorCode) -> Option<&'static str> {
+    match code {
+        ErrorCode::MissingComma => Some("did you forget a comma after the previous value?"),
+        ErrorCode::UnquotedKey => Some("object keys must be double-quoted"),
+        ErrorCode::MissingColon => Some("separate each object key from its value with ':'"),
+        ErrorCode::MissingValue => Some("a value is expected here; use null if it is intentionally empty"),
+        ErrorCode::TrailingComma => Some("remove the comma; JSON does not allow one before a closing bracket"),
+        ErrorCode::UnclosedContainer => Some("the bracket opened here is never closed"),
+        ErrorCode::UnterminatedString => Some("add the closing '\"'; line breaks inside strings must be written as \\n"),
+        ErrorCode::InvalidEscape => Some("valid escapes are \\\" \\\\ \\/ \\b \\f \\n \\r \\t and \\uXXXX"),
+        ErrorCode::ControlCharacter => Some("control characters must be escaped, e.g. \\t or \\u0000"),
+        ErrorCode::InvalidNumber => Some("numbers may not have leading zeros, a '+' sign, or a bare '.'"),
+        ErrorCode::InvalidLiteral => Some("the only literals are true, false and null, in lowercase"),
+        ErrorCode::TrailingCharacters => Some("a document holds a single value; wrap several values in an array"),
+        ErrorCode::UnexpectedEof => Some("the document ends before the value is complete"),
+        ErrorCode::DuplicateKey => Some("only the last value for a repeated key is kept"),
+        ErrorCode::NestingTooDeep => Some("the nested value is skipped without being checked"),
+        ErrorCode::UnexpectedCharacter => None,
+    }
+}
+
+// 1-based line and column (in characters) of a byte offset
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..source.floor_char_boundary(offset)];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+struct Painter {
+    ansi: bool,
+}
+
+impl Painter {
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.ansi {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+// Whitespace that lines up with `text` when printed above it, keeping tabs
+fn padding(text: &str) -> String {
+    text.chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect()
+}
+
+pub fn render_diagnostic(source: &str, diagnostic: &Diagnostic, options: &RenderOptions) -> String {
+    let painter = Painter { ansi: options.mode == RenderMode::Ansi };
+    let color = match diagnostic.severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+    };
+
+    let start = diagnostic.span.start.min(source.len());
+    let end = diagnostic.span.end.clamp(start, source.len());
+    let (first_line, first_col) = line_col(source, start);
+    // A span ending right after a newline does not extend onto the next line
+    let last_line = if end > start && source.as_bytes()[end - 1] == b'\n' {
+        line_col(source, end - 1).0
+    } else {
+        line_col(source, end).0
+    };
+    let gutter = last_line.to_string().len();
+    let blank = " ".repeat(gutter);
+
+    let mut out = String::new();
+    let header = format!("{}[{}]", diagnostic.severity, diagnostic.code);
+    out.push_str(&painter.paint(color, &header));
+    out.push_str(&painter.paint(BOLD, &format!(": {}", diagnostic.message)));
+    out.push('\n');
+    let location = match options.file_name {
+        Some(name) => format!("{}:{}:{}", name, first_line, first_col),
+        None => format!("{}:{}", first_line, first_col),
+    };
+    out.push_str(&format!("{}{} {}\n", blank, painter.paint(BLUE, "-->"), location));
+    out.push_str(&format!("{} {}\n", blank, painter.paint(BLUE, "|")));
+
+    let lines: Vec<&str> = source.split('\n').collect();
+    let mut line_offset: usize = lines[..first_line - 1].iter().map(|l| l.len() + 1).sum();
+    let span_lines = last_line - first_line + 1;
+    for (i, line) in lines[first_line - 1..last_line].iter().enumerate() {
+        let line_number = first_line + i;
+        let text = line.strip_suffix('\r').unwrap_or(line);
+        let skipped = span_lines > MAX_SPAN_LINES && i >= MAX_SPAN_LINES / 2 && i < span_lines - MAX_SPAN_LINES / 2;
+        if skipped {
+            if i == MAX_SPAN_LINES / 2 {
+                out.push_str(&format!("{}\n", painter.paint(BLUE, "...")));
+            }
+            line_offset += line.len() + 1;
+            continue;
+        }
+
+        let from = start.saturating_sub(line_offset).min(text.len());
+        let to = end.saturating_sub(line_offset).min(text.len());
+        let from = text.floor_char_boundary(from);
+        let to = text.ceil_char_boundary(to);
+        let width = text[from..to].chars().count().max(1);
+
+        let number = format!("{:>width$} |", line_number, width = gutter);
+        out.push_str(&format!("{} {}\n", painter.paint(BLUE, &number), text));
+        let marker = "^".repeat(width);
+        out.push_str(&format!("{} {}{}\n", painter.paint(BLUE, &format!("{} |", blank)), padding(&text[..from]), painter.paint(color, &marker)));
+        line_offset += line.len() + 1;
+    }
+
+    if let Some(hint) = hint(diagnostic.code) {
+        out.push_str(&format!("{} {} {}\n", blank, painter.paint(BLUE, "="), painter.paint(CYAN, &format!("hint: {}", hint))));
+    }
+    out
+}
+
+pub fn render_diagnostics(source: &str, diagnostics: &[Diagnostic], options: &RenderOptions) -> String {
+    diagnostics.iter()
+        .map(|d| render_diagnostic(source, d, options))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Span;
+    use crate::recover::parse_tolerant;
+
+    fn render_plain(source: &str) -> String {
+        let recovered = parse_tolerant(source);
+        render_diagnostics(source, &recovered.diagnostics, &RenderOptions::default())
+    }
+
+    #[test]
+    fn test_line_col() {
+        let src = "{\n  \"a\": 1\n}";
+        assert_eq!(line_col(src, 0), (1, 1));
+        assert_eq!(line_col(src, 2), (2, 1));
+        assert_eq!(line_col(src, 9), (2, 8));
+        assert_eq!(line_col(src, 100), (3, 2));
+        assert_eq!(line_col("é\nx", 3), (2, 1));
+    }
+
+    #[test]
+    fn test_render_missing_comma() {
+        let src = "{\n  \"a\": 1\n  \"b\": 2\n}";
+        let expected = concat!(
+            "error[E003]: Expected ',' or '}' after object member\n",
+            " --> 2:9\n",
+            "  |\n",
+            "2 |   \"a\": 1\n",
+            "  |         ^\n",
+            "  = hint: did you forget a comma after the previous value?\n",
+        );
+        assert_eq!(render_plain(src), expected);
+    }
+
+    #[test]
+    fn test_render_unquoted_key_with_file_name() {
+        let src = "{name: 1}";
+        let recovered = parse_tolerant(src);
+        let options = RenderOptions { mode: RenderMode::Plain, file_name: Some("config.json") };
+        let rendered = render_diagnostic(src, &recovered.diagnostics[0], &options);
+        let expected = concat!(
+            "error[E013]: Object keys must be double-quoted\n",
+            " --> config.json:1:2\n",
+            "  |\n",
+            "1 | {name: 1}\n",
+            "  |  ^^^^\n",
+            "  = hint: object keys must be double-quoted\n",
+        );
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_render_keeps_tabs_aligned() {
+        let src = "[\t1\t2]";
+        let rendered = render_plain(src);
+        assert!(rendered.contains("1 | [\t1\t2]\n  |  \t ^\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn test_render_multiline_span() {
+        let src = "[\n  \"a\",\n  3\n";
+        let diagnostic = Diagnostic::new(ErrorCode::UnclosedContainer, Span::new(0, src.len()), "Unclosed array");
+        let rendered = render_diagnostic(src, &diagnostic, &RenderOptions::default());
+        let expected = concat!(
+            "error[E007]: Unclosed array\n",
+            " --> 1:1\n",
+            "  |\n",
+            "1 | [\n",
+            "  | ^\n",
+            "2 |   \"a\",\n",
+            "  | ^^^^^^\n",
+            "3 |   3\n",
+            "  | ^^^\n",
+            "  = hint: the bracket opened here is never closed\n",
+        );
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_render_span_inside_multibyte_char() {
+        let src = "[\"é\"]";
+        for span in [Span::new(1, 3), Span::new(2, 3), Span::new(3, 4)] {
+            let diagnostic = Diagnostic::new(ErrorCode::InvalidEscape, span, "Invalid");
+            let rendered = render_diagnostic(src, &diagnostic, &RenderOptions::default());
+            assert!(rendered.contains("1 | [\"é\"]\n"), "{}", rendered);
+        }
+    }
+
+    #[test]
+    fn test_render_long_span_is_elided() {
+        let src = "x\n".repeat(20);
+        let diagnostic = Diagnostic::new(ErrorCode::TrailingCharacters, Span::new(0, src.len()), "Trailing");
+        let rendered = render_diagnostic(&src, &diagnostic, &RenderOptions::default());
+        assert!(rendered.contains("\n...\n"));
+        assert!(rendered.contains(" 3 | x\n"));
+        assert!(!rendered.contains(" 4 | x\n"));
+        assert!(rendered.contains("18 | x\n"));
+        assert!(rendered.contains("20 | x\n"));
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let src = "[1 2]";
+        let recovered = parse_tolerant(src);
+        let options = RenderOptions { mode: RenderMode::Ansi, file_name: None };
+        let rendered = render_diagnostic(src, &recovered.diagnostics[0], &options);
+        assert!(rendered.starts_with("\x1b[1;31merror[E003]\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;36mhint:"));
+    }
+
+    #[test]
+    fn test_render_warning_and_eof() {
+        let rendered = render_plain("{\"a\": 1, \"a\": 2}");
+        assert!(rendered.starts_with("warning[W001]: Duplicate key 'a'\n --> 1:10\n"));
+        let rendered = render_plain("");
+        assert!(rendered.contains("1 | \n  | ^\n"), "{}", rendered);
+    }
+}
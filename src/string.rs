@@ -1,76 +1,135 @@
-use std::str::CharIndices;
-
 use crate::core;
+use crate::options;
+use crate::utils;
 
 use core::{Json, JsonError};
+use options::{ParseOptions, SurrogatePolicy};
+use utils::{is_string_end, peek_char, string_run};
 
-fn parse_utf8_hex(chars: &mut CharIndices) -> Option<char> {
-    let mut unicode = String::new();
-    for _ in 0..4 {
-        if let Some((_, uc)) = chars.next() {
-            unicode.push(uc);
-        } else {
-            return None;
-        }
+fn parse_hex4(bytes: &[u8]) -> Option<u16> {
+    let mut code: u16 = 0;
+    for &b in bytes.get(..4)? {
+        code = code * 16 + (b as char).to_digit(16)? as u16;
     }
-    let code = match u16::from_str_radix(&unicode, 16) {
-        Ok(c) => c,
-        Err(_) => return None,
-    };
-    std::char::from_u32(code as u32)
+    Some(code)
 }
 
-fn parse_escape_char(c: char) -> Option<char> {
-    match c {
-        '"'  => Some('"'),
-        '\\' => Some('\\'),
-        '/'  => Some('/'),
-        'b'  => Some('\u{0008}'),
-        'f'  => Some('\u{000C}'),
-        'n'  => Some('\n'),
-        'r'  => Some('\r'),
-        't'  => Some('\t'),
-        _    => None,
+// Decodes the digits of a `\u` escape starting at `*pos`, including the
+// `\uXXXX` low half that has to follow a high surrogate, and moves past them
+fn parse_utf8_hex(bytes: &[u8], pos: &mut usize, options: &ParseOptions) -> Result<char, JsonError> {
+    let code = parse_hex4(&bytes[*pos..])
+        .ok_or(JsonError::LexicalError("Expected 4 hex digits after \\u".to_string()))?;
+    *pos += 4;
+    if let Some(c) = char::from_u32(code as u32) {
+        return Ok(c);
+    }
+    if (0xD800..0xDC00).contains(&code)
+        && bytes[*pos..].starts_with(b"\\u")
+        && let Some(low) = parse_hex4(&bytes[*pos + 2..])
+        && (0xDC00..0xE000).contains(&low) {
+        *pos += 6;
+        let scalar = 0x10000 + (((code as u32) - 0xD800) << 10) + ((low as u32) - 0xDC00);
+        return Ok(char::from_u32(scalar).expect("surrogate pair is a valid scalar value"));
+    }
+    match options.lone_surrogates {
+        SurrogatePolicy::Reject => Err(JsonError::LexicalError(format!("Lone surrogate \\u{:04X} in string", code))),
+        SurrogatePolicy::Replace => Ok(char::REPLACEMENT_CHARACTER),
     }
 }
 
-pub fn parse_string(source: &str) -> Result<(Json, &str), JsonError> {
-    let mut chars: CharIndices<'_> = source.char_indices();
-
-    if let Some((_, first)) = chars.next() {
-        if first != '"' {
-            return Err(JsonError::SyntaxError(format!("Expected '\"' at start of string, found '{}'", first)));
-        }
-    } else {
-        return Err(JsonError::SyntaxError("Unexpected end of input while parsing string".to_string()));
+fn parse_escape_char(b: u8) -> Option<char> {
+    match b {
+        b'"'  => Some('"'),
+        b'\\' => Some('\\'),
+        b'/'  => Some('/'),
+        b'b'  => Some('\u{0008}'),
+        b'f'  => Some('\u{000C}'),
+        b'n'  => Some('\n'),
+        b'r'  => Some('\r'),
+        b't'  => Some('\t'),
+        _     => None,
     }
+}
 
+pub fn parse_string<'a>(source: &'a [u8], options: &ParseOptions) -> Result<(Json, &'a [u8]), JsonError> {
+    let (value, tail) = parse_quoted(source, options)?;
+    Ok((Json::JsonString(value), tail))
+}
+
+// Shared by string values and object keys
+pub fn parse_quoted<'a>(source: &'a [u8], options: &ParseOptions) -> Result<(String, &'a [u8]), JsonError> {
     let mut result = String::new();
-    let mut escape = false;
-
-    while let Some((i, c)) = chars.next() {
-        if escape {
-            result.push(if c == 'u' {
-                parse_utf8_hex(&mut chars).ok_or(JsonError::LexicalError(format!("Invalid escape character in utf-8 hex : {}", c)))?
-            } else if let Some(unescaped) = parse_escape_char(c) {
-                unescaped
-            } else {
-                return Err(JsonError::LexicalError(format!("Invalid escape character : {}", c)));
-            });
-            escape = false;
-        } else {
-            if c == '"' {
-                // End of string
-                let tail = &source[i + 1..];
-                return Ok((Json::JsonString(result), tail));
-            } else if c == '\\' {
-                escape = true;
-            } else {
-                result.push(c);
+    let tail = parse_quoted_into(source, options, &mut result)?;
+    Ok((result, tail))
+}
+
+// Appends the unescaped contents to `result` and returns what follows the
+// closing quote. Strings are the only place the input may hold more than
+// ASCII, so this is where it is checked to be UTF-8.
+pub fn parse_quoted_into<'a>(bytes: &'a [u8], options: &ParseOptions, result: &mut String) -> Result<&'a [u8], JsonError> {
+    if bytes.first() != Some(&b'"') {
+        return Err(match peek_char(bytes)? {
+            Some(first) => JsonError::SyntaxError(format!("Expected '\"' at start of string, found '{}'", first)),
+            None => JsonError::SyntaxError("Unexpected end of input while parsing string".to_string()),
+        });
+    }
+
+    let mut pos = 1;
+    loop {
+        // Copy everything up to the next quote, backslash or control byte at
+        // once
+        let (run, end) = string_run(bytes, pos)?;
+        result.push_str(run);
+        pos = end;
+
+        match bytes.get(pos) {
+            Some(b'"') => return Ok(&bytes[pos + 1..]),
+            Some(b'\\') => {
+                pos += 2;
+                match bytes.get(pos - 1) {
+                    Some(b'u') => result.push(parse_utf8_hex(bytes, &mut pos, options)?),
+                    Some(&b) => match parse_escape_char(b) {
+                        Some(unescaped) => result.push(unescaped),
+                        None => {
+                            let c = peek_char(&bytes[pos - 1..])?.unwrap_or_default();
+                            return Err(JsonError::LexicalError(format!("Invalid escape character : {}", c)));
+                        }
+                    },
+                    None => break,
+                }
+            }
+            Some(&b) => {
+                return Err(JsonError::LexicalError(format!("Unescaped control character U+{:04X} in string", b)));
             }
+            None => break,
         }
     }
-    Err(JsonError::SyntaxError(format!("Invalid string : \"{}", source)))
+    Err(JsonError::SyntaxError(format!("Invalid string : \"{}", String::from_utf8_lossy(bytes))))
+}
+
+// Writes `s` as a quoted JSON string, escaping what RFC 8259 requires
+pub fn dump_string(s: &str, out: &mut String) {
+    out.push('"');
+    let mut start = 0;
+    for (i, b) in s.bytes().enumerate() {
+        if !is_string_end(b) {
+            continue;
+        }
+        out.push_str(&s[start..i]);
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            0x08 => out.push_str("\\b"),
+            0x0C => out.push_str("\\f"),
+            _ => out.push_str(&format!("\\u{:04x}", b)),
+        }
+        start = i + 1;
+    }
+    out.push_str(&s[start..]);
+    out.push('"');
 }
 
 #[cfg(test)]
@@ -79,100 +138,142 @@ mod tests {
 
     #[test]
     fn test_parse_unicode_valid_ascii() {
-        let mut iter = "0041".char_indices(); // 'A'
-        assert_eq!(parse_utf8_hex(&mut iter), Some('A'));
-        assert_eq!(iter.next(), None); // Iterator should be at the end
+        let mut pos = 0;
+        assert_eq!(parse_utf8_hex(b"0041", &mut pos, &ParseOptions::default()).ok(), Some('A'));
+        assert_eq!(pos, 4); // Should be at the end
     }
 
     #[test]
     fn test_parse_utf8_hex_valid_bmp() {
-        let mut iter = "03A9tail".char_indices(); // 'Ω'
-        assert_eq!(parse_utf8_hex(&mut iter), Some('Ω'));
-        assert_eq!(iter.next(), Some((4, 't'))); // Iterator should have progressed
+        let mut pos = 0;
+        assert_eq!(parse_utf8_hex(b"03A9tail", &mut pos, &ParseOptions::default()).ok(), Some('Ω'));
+        assert_eq!(pos, 4); // Should have progressed
     }
 
     #[test]
     fn test_parse_utf8_hex_valid_bmp_lowercase() {
-        let mut iter = "03a9tail".char_indices(); // 'Ω'
-        assert_eq!(parse_utf8_hex(&mut iter), Some('Ω'));
-        assert_eq!(iter.next(), Some((4, 't'))); // Iterator should have progressed
+        let mut pos = 0;
+        assert_eq!(parse_utf8_hex(b"03a9tail", &mut pos, &ParseOptions::default()).ok(), Some('Ω'));
+        assert_eq!(pos, 4); // Should have progressed
     }
 
     #[test]
     fn test_parse_utf8_hex_invalid_empty() {
-        let mut iter = "".char_indices(); // Empty
-        assert_eq!(parse_utf8_hex(&mut iter), None);
+        let mut pos = 0; // Empty
+        assert_eq!(parse_utf8_hex(b"", &mut pos, &ParseOptions::default()).ok(), None);
     }
 
     #[test]
     fn test_parse_utf8_hex_invalid_short() {
-        let mut iter = "41".char_indices(); // Too short
-        assert_eq!(parse_utf8_hex(&mut iter), None);
+        let mut pos = 0; // Too short
+        assert_eq!(parse_utf8_hex(b"41", &mut pos, &ParseOptions::default()).ok(), None);
     }
 
     #[test]
     fn test_parse_utf8_hex_invalid_nonhex() {
-        let mut iter = "ZZZZ".char_indices(); // Not hex
-        assert_eq!(parse_utf8_hex(&mut iter), None);
+        let mut pos = 0; // Not hex
+        assert_eq!(parse_utf8_hex(b"ZZZZ", &mut pos, &ParseOptions::default()).ok(), None);
     }
 
     #[test]
     fn test_parse_string_valid() {
         let valid = "\"field\"   ";
-        let (field, tail) = parse_string(valid).unwrap();
+        let (field, tail) = parse_string(valid.as_bytes(), &ParseOptions::default()).unwrap();
         assert_eq!(field, Json::JsonString("field".to_string()));
-        assert_eq!(tail, "   ");
+        assert_eq!(tail, b"   ");
     }
 
     #[test]
     fn test_parse_string_valid_escape_characters() {
         let valid = "\"line\\nbreak\\tand\\tescape\\\\quote\\\"end\" rest";
-        let (parsed, tail) = parse_string(valid).unwrap();
+        let (parsed, tail) = parse_string(valid.as_bytes(), &ParseOptions::default()).unwrap();
         assert_eq!(
             parsed,
             Json::JsonString("line\nbreak\tand\tescape\\quote\"end".to_string())
         );
-        assert_eq!(tail, " rest");
+        assert_eq!(tail, b" rest");
     }
 
     #[test]
     fn test_parse_string_valid_utf8_hex() {
         let valid = "\"Omega: \\u03A9, A: \\u0041, smile: \\u263A!\" next";
-        let (parsed, tail) = parse_string(valid).unwrap();
+        let (parsed, tail) = parse_string(valid.as_bytes(), &ParseOptions::default()).unwrap();
         assert_eq!(
             parsed,
             Json::JsonString("Omega: Ω, A: A, smile: ☺!".to_string())
         );
-        assert_eq!(tail, " next");
+        assert_eq!(tail, b" next");
     }
 
     #[test]
     fn test_parse_string_valid_empty() {
         let valid = "\"\"";
-        let (field, tail) = parse_string(valid).unwrap();
+        let (field, tail) = parse_string(valid.as_bytes(), &ParseOptions::default()).unwrap();
         assert_eq!(field, Json::JsonString("".to_string()));
-        assert_eq!(tail, "");
+        assert_eq!(tail, b"");
     }
 
     #[test]
     fn test_parse_string_invalid_empty() {
         let invalid = "";
-        assert!(parse_string(invalid).is_err());
+        assert!(parse_string(invalid.as_bytes(), &ParseOptions::default()).is_err());
     }
 
     #[test]
     fn test_parse_string_invalid() {
         // Unterminated string
         let invalid = "\"abc\\u0041";
-        assert!(parse_string(invalid).is_err());
+        assert!(parse_string(invalid.as_bytes(), &ParseOptions::default()).is_err());
 
         // Invalid escape
         let invalid2 = "\"bad\\xescape\"";
-        assert!(parse_string(invalid2).is_err());
+        assert!(parse_string(invalid2.as_bytes(), &ParseOptions::default()).is_err());
 
         // Invalid unicode
         let invalid3 = "\"bad\\uZZZZ\"";
-        assert!(parse_string(invalid3).is_err());
+        assert!(parse_string(invalid3.as_bytes(), &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_string_surrogate_pair() {
+        let valid = "\"\\uD83D\\ude00 \\uDBFF\\uDFFF\"";
+        let (parsed, _) = parse_string(valid.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(parsed, Json::JsonString("\u{1F600} \u{10FFFF}".to_string()));
+    }
+
+    #[test]
+    fn test_parse_string_lone_surrogates() {
+        let replace = ParseOptions { lone_surrogates: SurrogatePolicy::Replace, ..ParseOptions::default() };
+        for input in ["\"\\uD800\"", "\"\\uDC00\"", "\"\\uD800\\u0041\"", "\"\\uDd1e\\uD834\""] {
+            assert!(parse_string(input.as_bytes(), &ParseOptions::default()).is_err(), "{}", input);
+            assert!(parse_string(input.as_bytes(), &replace).is_ok(), "{}", input);
+        }
+        let (parsed, _) = parse_string(b"\"\\uD800\\n\"", &replace).unwrap();
+        assert_eq!(parsed, Json::JsonString("\u{FFFD}\n".to_string()));
+    }
+
+    #[test]
+    fn test_parse_string_control_characters() {
+        for c in ['\u{0000}', '\n', '\t', '\u{001F}'] {
+            let invalid = format!("\"a{}b\"", c);
+            assert!(parse_string(invalid.as_bytes(), &ParseOptions::default()).is_err());
+        }
+        // DEL is not a control character as far as JSON is concerned
+        assert!(parse_string("\"\u{007F}\"".as_bytes(), &ParseOptions::default()).is_ok());
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_utf8_hex_rejects_signs() {
+        let mut pos = 0;
+        assert!(parse_utf8_hex(b"+041", &mut pos, &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_dump_string() {
+        let mut out = String::new();
+        dump_string("a\"b\\c\n\u{1}é\u{7F}", &mut out);
+        assert_eq!(out, "\"a\\\"b\\\\c\\n\\u0001é\u{7F}\"");
+        let (parsed, _) = parse_string(out.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(parsed, Json::JsonString("a\"b\\c\n\u{1}é\u{7F}".to_string()));
+    }
+}
@@ -0,0 +1,562 @@
+use crate::core;
+use crate::map;
+use crate::options;
+use crate::intern;
+use crate::utils;
+
+use core::{Json, JsonError, parse_value, strip_bom};
+use map::JsonMap;
+use options::ParseOptions;
+use intern::{Interner, Keys};
+use utils::skip_ws;
+
+// Two-stage parsing in the style of simdjson. Stage 1 classifies the input 64
+// bytes at a time and records the offset of every structural character
+// (`{}[]:,` outside strings), every opening quote and the first byte of every
+// other scalar. Stage 2 walks those offsets to build the `Json` value.
+
+const EVEN_BITS: u64 = 0x5555_5555_5555_5555;
+
+// One bit per byte of a 64-byte block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Masks {
+    quote: u64,
+    backslash: u64,
+    op: u64,
+    ws: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backend {
+    // Only the tests pick it on x86_64
+    #[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+}
+
+impl Backend {
+    pub(crate) fn detect() -> Backend {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Backend::Avx2;
+            }
+            // SSE2 is part of the x86_64 baseline
+            Backend::Sse2
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        Backend::Scalar
+    }
+
+    #[cfg(test)]
+    pub(crate) fn available() -> Vec<Backend> {
+        let mut backends = vec![Backend::Scalar];
+        #[cfg(target_arch = "x86_64")]
+        {
+            backends.push(Backend::Sse2);
+            if is_x86_feature_detected!("avx2") {
+                backends.push(Backend::Avx2);
+            }
+        }
+        backends
+    }
+}
+
+fn is_op(b: u8) -> bool {
+    matches!(b, b'{' | b'}' | b'[' | b']' | b':' | b',')
+}
+
+fn is_ws(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+fn classify_scalar(block: &[u8; 64]) -> Masks {
+    let mut masks = Masks::default();
+    for (i, &b) in block.iter().enumerate() {
+        let bit = 1u64 << i;
+        if b == b'"' {
+            masks.quote |= bit;
+        } else if b == b'\\' {
+            masks.backslash |= bit;
+        } else if is_op(b) {
+            masks.op |= bit;
+        } else if is_ws(b) {
+            masks.ws |= bit;
+        }
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::Masks;
+
+    #[target_feature(enable = "sse2")]
+    fn eq16(v: __m128i, b: u8) -> u64 {
+        _mm_movemask_epi8(_mm_cmpeq_epi8(v, _mm_set1_epi8(b as i8))) as u16 as u64
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) fn classify_sse2(block: &[u8; 64]) -> Masks {
+        let mut masks = Masks::default();
+        for (i, chunk) in block.chunks_exact(16).enumerate() {
+            // Sound: the chunk is exactly 16 readable bytes
+            let v = unsafe { _mm_loadu_si128(chunk.as_ptr() as *const __m128i) };
+            let shift = i * 16;
+            masks.quote |= eq16(v, b'"') << shift;
+            masks.backslash |= eq16(v, b'\\') << shift;
+            masks.op |= (eq16(v, b'{') | eq16(v, b'}') | eq16(v, b'[') | eq16(v, b']') | eq16(v, b':') | eq16(v, b',')) << shift;
+            masks.ws |= (eq16(v, b' ') | eq16(v, b'\t') | eq16(v, b'\n') | eq16(v, b'\r')) << shift;
+        }
+        masks
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn eq32(v: __m256i, b: u8) -> u64 {
+        _mm256_movemask_epi8(_mm256_cmpeq_epi8(v, _mm256_set1_epi8(b as i8))) as u32 as u64
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn classify_avx2(block: &[u8; 64]) -> Masks {
+        let mut masks = Masks::default();
+        for (i, chunk) in block.chunks_exact(32).enumerate() {
+            // Sound: the chunk is exactly 32 readable bytes
+            let v = unsafe { _mm256_loadu_si256(chunk.as_ptr() as *const __m256i) };
+            let shift = i * 32;
+            masks.quote |= eq32(v, b'"') << shift;
+            masks.backslash |= eq32(v, b'\\') << shift;
+            masks.op |= (eq32(v, b'{') | eq32(v, b'}') | eq32(v, b'[') | eq32(v, b']') | eq32(v, b':') | eq32(v, b',')) << shift;
+            masks.ws |= (eq32(v, b' ') | eq32(v, b'\t') | eq32(v, b'\n') | eq32(v, b'\r')) << shift;
+        }
+        masks
+    }
+}
+
+// Bit i is set when byte i is preceded by an odd number of backslashes
+fn find_escaped(backslash: u64, prev_escaped: &mut u64) -> u64 {
+    let backslash = backslash & !*prev_escaped;
+    let follows_escape = (backslash << 1) | *prev_escaped;
+    let odd_sequence_starts = backslash & !EVEN_BITS & !follows_escape;
+    let (sequences_starting_on_even_bits, overflow) = odd_sequence_starts.overflowing_add(backslash);
+    *prev_escaped = overflow as u64;
+    let invert_mask = sequences_starting_on_even_bits << 1;
+    (EVEN_BITS ^ invert_mask) & follows_escape
+}
+
+// Bit i is the parity of the bits up to and including i
+fn prefix_xor(mut x: u64) -> u64 {
+    x ^= x << 1;
+    x ^= x << 2;
+    x ^= x << 4;
+    x ^= x << 8;
+    x ^= x << 16;
+    x ^= x << 32;
+    x
+}
+
+// State carried from one block to the next
+#[derive(Default)]
+struct Scanner {
+    prev_escaped: u64,
+    prev_in_string: u64,
+    prev_scalar: u64,
+}
+
+impl Scanner {
+    fn structurals(&mut self, masks: Masks) -> u64 {
+        let escaped = find_escaped(masks.backslash, &mut self.prev_escaped);
+        let quote = masks.quote & !escaped;
+        // Covers each opening quote and the string contents, not the closing quote
+        let in_string = prefix_xor(quote) ^ self.prev_in_string;
+        self.prev_in_string = ((in_string as i64) >> 63) as u64;
+
+        let scalar = !(masks.op | masks.ws);
+        let nonquote_scalar = scalar & !quote;
+        let follows_scalar = (nonquote_scalar << 1) | self.prev_scalar;
+        self.prev_scalar = nonquote_scalar >> 63;
+        let scalar_starts = nonquote_scalar & !follows_scalar;
+
+        ((masks.op | scalar_starts) & !in_string) | (quote & in_string)
+    }
+}
+
+fn index_blocks<F: Fn(&[u8; 64]) -> Masks>(bytes: &[u8], classify: F) -> Vec<u32> {
+    let mut positions = Vec::with_capacity(bytes.len() / 8);
+    let mut scanner = Scanner::default();
+    let mut chunks = bytes.chunks_exact(64);
+    let mut base = 0u32;
+    let mut push_bits = |mut bits: u64, base: u32| {
+        while bits != 0 {
+            positions.push(base + bits.trailing_zeros());
+            bits &= bits - 1;
+        }
+    };
+    for chunk in &mut chunks {
+        let block: &[u8; 64] = chunk.try_into().expect("chunk is 64 bytes");
+        push_bits(scanner.structurals(classify(block)), base);
+        base += 64;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        // Pad with whitespace, which is never structural
+        let mut block = [b' '; 64];
+        block[..rest.len()].copy_from_slice(rest);
+        push_bits(scanner.structurals(classify(&block)), base);
+    }
+    positions
+}
+
+pub(crate) fn structural_index_with(bytes: &[u8], backend: Backend) -> Vec<u32> {
+    match backend {
+        Backend::Scalar => index_blocks(bytes, classify_scalar),
+        // Sound: only selected once the CPU is known to support the feature
+        #[cfg(target_arch = "x86_64")]
+        Backend::Sse2 => index_blocks(bytes, |block| unsafe { x86::classify_sse2(block) }),
+        #[cfg(target_arch = "x86_64")]
+        Backend::Avx2 => index_blocks(bytes, |block| unsafe { x86::classify_avx2(block) }),
+    }
+}
+
+// Offsets of the structural characters and value starts in `bytes`, using the
+// widest instruction set the CPU supports
+pub fn structural_index(bytes: &[u8]) -> Vec<u32> {
+    structural_index_with(bytes, Backend::detect())
+}
+
+struct IndexedParser<'a> {
+    source: &'a str,
+    index: &'a [u32],
+    next: usize,
+    options: &'a ParseOptions,
+    keys: Keys<'a>,
+}
+
+impl<'a> IndexedParser<'a> {
+    fn peek(&self) -> Option<usize> {
+        self.index.get(self.next).map(|&p| p as usize)
+    }
+
+    fn unexpected(&self, context: &str) -> JsonError {
+        match self.peek().and_then(|p| self.source[p..].chars().next()) {
+            Some(c) => JsonError::SyntaxError(format!("Unexpected character in {}: '{}'", context, c)),
+            None => JsonError::SyntaxError(format!("Unexpected end of input in {}", context)),
+        }
+    }
+
+    // Consumes the structural character `b`, if it comes next
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek().is_some_and(|p| self.source.as_bytes()[p] == b) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // A scalar ends where the lexer stopped; only whitespace may separate it
+    // from the next structural character
+    fn finish_scalar(&self, tail: &[u8]) -> Result<(), JsonError> {
+        let end = skip_ws(self.source.as_bytes(), self.source.len() - tail.len());
+        match self.peek() {
+            Some(p) if p == end => Ok(()),
+            None if end == self.source.len() => Ok(()),
+            _ => Err(JsonError::SyntaxError("Unexpected characters after value".to_string())),
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, JsonError> {
+        let pos = self.peek().ok_or(JsonError::SyntaxError("Unexpected end of input".to_string()))?;
+        match self.source.as_bytes()[pos] {
+            b'{' | b'[' if depth >= self.options.max_depth => {
+                Err(JsonError::DepthError { max_depth: self.options.max_depth })
+            }
+            b'{' => {
+                self.next += 1;
+                self.object(depth + 1)
+            }
+            b'[' => {
+                self.next += 1;
+                self.array(depth + 1)
+            }
+            b'}' | b']' | b':' | b',' => Err(self.unexpected("value")),
+            _ => {
+                self.next += 1;
+                let (json, tail) = parse_value(&self.source.as_bytes()[pos..], self.options, depth, &mut self.keys)?;
+                self.finish_scalar(tail)?;
+                Ok(json)
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json, JsonError> {
+        let mut values = Vec::new();
+        if self.eat(b']') {
+            return Ok(Json::JsonArray(values));
+        }
+        loop {
+            values.push(self.value(depth)?);
+            if self.eat(b']') {
+                return Ok(Json::JsonArray(values));
+            }
+            if !self.eat(b',') {
+                return Err(self.unexpected("array"));
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json, JsonError> {
+        let mut map = JsonMap::new();
+        if self.eat(b'}') {
+            return Ok(Json::JsonObject(map));
+        }
+        loop {
+            let key = match self.peek() {
+                Some(pos) if self.source.as_bytes()[pos] == b'"' => {
+                    self.next += 1;
+                    let (key, tail) = self.keys.parse(&self.source.as_bytes()[pos..], self.options)?;
+                    self.finish_scalar(tail)?;
+                    key
+                }
+                _ => return Err(self.unexpected("object key")),
+            };
+            if !self.eat(b':') {
+                return Err(self.unexpected("object"));
+            }
+            let value = self.value(depth)?;
+            map.insert(key, value);
+            if self.eat(b'}') {
+                return Ok(Json::JsonObject(map));
+            }
+            if !self.eat(b',') {
+                return Err(self.unexpected("object"));
+            }
+        }
+    }
+}
+
+fn parse_with_backend(source: &str, options: &ParseOptions, backend: Backend) -> Result<Json, JsonError> {
+    let source = strip_bom(source, options)?;
+    if u32::try_from(source.len()).is_err() {
+        return Err(JsonError::SyntaxError("Input of 4 GiB or more cannot be indexed".to_string()));
+    }
+    let index = structural_index_with(source.as_bytes(), backend);
+    let mut interner = Interner::new();
+    let keys = Keys::new(options.intern_keys.then_some(&mut interner));
+    let mut parser = IndexedParser { source, index: &index, next: 0, options, keys };
+    let json = parser.value(0)?;
+    if parser.peek().is_some() {
+        return Err(JsonError::SyntaxError("Unexpected trailing characters after JSON value".to_string()));
+    }
+    Ok(json)
+}
+
+// Same result as `parse_with_options`, built from the structural index
+pub fn parse_indexed_with_options(source: &str, options: &ParseOptions) -> Result<Json, JsonError> {
+    parse_with_backend(source, options, Backend::detect())
+}
+
+pub fn parse_indexed(source: &str) -> Result<Json, JsonError> {
+    parse_indexed_with_options(source, &ParseOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::parse_with_options;
+
+    // Straightforward byte-at-a-time definition of the index
+    fn reference_index(bytes: &[u8]) -> Vec<u32> {
+        let mut positions = Vec::new();
+        let (mut escaped, mut in_string, mut prev_scalar) = (false, false, false);
+        for (i, &b) in bytes.iter().enumerate() {
+            let is_escaped = escaped;
+            escaped = b == b'\\' && !is_escaped;
+            let quote = b == b'"' && !is_escaped;
+            if quote {
+                in_string = !in_string;
+            }
+            let nonquote_scalar = !is_op(b) && !is_ws(b) && !quote;
+            let structural = if in_string {
+                quote
+            } else {
+                is_op(b) || (nonquote_scalar && !prev_scalar)
+            };
+            prev_scalar = nonquote_scalar;
+            if structural {
+                positions.push(i as u32);
+            }
+        }
+        positions
+    }
+
+    // xorshift64*, so the property tests need no dependencies and replay
+    // the same cases on every run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+        fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+            items[self.below(items.len())]
+        }
+    }
+
+    fn random_bytes(rng: &mut Rng) -> Vec<u8> {
+        let len = rng.below(300);
+        (0..len).map(|_| {
+            if rng.below(4) == 0 {
+                rng.next() as u8
+            } else {
+                rng.pick(b"\"\\\\{}[]:, \t\nax1\"\\")
+            }
+        }).collect()
+    }
+
+    fn random_value(rng: &mut Rng, out: &mut String, depth: usize) {
+        let choice = if depth > 4 { rng.below(4) } else { rng.below(6) };
+        match choice {
+            0 => out.push_str(rng.pick(&["true", "false", "null"])),
+            1 => out.push_str(rng.pick(&["0", "-1", "12.5e3", "1e400", "-0.0", "123456789012345678901", "3.14159"])),
+            2 | 3 => {
+                out.push('"');
+                for _ in 0..rng.below(12) {
+                    out.push_str(rng.pick(&["a", "\\\"", "\\\\", "\\n", "\\u00e9", "\\uD83D\\uDE00", "é", "{", "]", ",", ":", " "]));
+                }
+                out.push('"');
+            }
+            4 => {
+                out.push('[');
+                for i in 0..rng.below(5) {
+                    if i > 0 {
+                        out.push_str(rng.pick(&[",", ", ", " ,\n"]));
+                    }
+                    random_value(rng, out, depth + 1);
+                }
+                out.push(']');
+            }
+            _ => {
+                out.push('{');
+                for i in 0..rng.below(5) {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    random_value(rng, out, depth + 4);
+                    out.push_str(rng.pick(&[":", " : "]));
+                    random_value(rng, out, depth + 1);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    // A valid document most of the time, with the odd byte-level corruption
+    fn random_document(rng: &mut Rng) -> String {
+        let mut doc = String::new();
+        random_value(rng, &mut doc, 0);
+        let mut chars: Vec<char> = doc.chars().collect();
+        for _ in 0..rng.below(3) {
+            let at = rng.below(chars.len() + 1);
+            match rng.below(3) {
+                0 if at < chars.len() => {
+                    chars.remove(at);
+                }
+                _ => chars.insert(at, rng.pick(&['"', '\\', ',', ']', '}', ':', ' ', 'x', '1', '\n'])),
+            }
+        }
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn test_find_escaped() {
+        let mut prev = 0;
+        // \\\" : the quote is escaped, the second backslash is too
+        let bs = 0b0111;
+        assert_eq!(find_escaped(bs, &mut prev), 0b1010);
+        // A run ending on the last bit escapes the first bit of the next block
+        let mut prev = 0;
+        find_escaped(1 << 63, &mut prev);
+        assert_eq!(prev, 1);
+        assert_eq!(find_escaped(0, &mut prev), 1);
+    }
+
+    #[test]
+    fn test_structural_index() {
+        let src = br#"{"a\"b": [1, true], "c":-2}"#;
+        let expected = [0, 1, 7, 9, 10, 11, 13, 17, 18, 20, 23, 24, 26];
+        for backend in Backend::available() {
+            assert_eq!(structural_index_with(src, backend), expected, "{:?}", backend);
+        }
+    }
+
+    #[test]
+    fn test_backends_match_reference() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..3000 {
+            let bytes = random_bytes(&mut rng);
+            let expected = reference_index(&bytes);
+            for backend in Backend::available() {
+                assert_eq!(structural_index_with(&bytes, backend), expected, "{:?} on {:?}", backend, bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_strings_across_blocks() {
+        let mut src = String::from("[\"");
+        src.push_str(&"x\\\\".repeat(50));
+        src.push_str("\\\"");
+        src.push_str(&"{".repeat(70));
+        src.push_str("\", 1]");
+        for backend in Backend::available() {
+            assert_eq!(structural_index_with(src.as_bytes(), backend), reference_index(src.as_bytes()));
+        }
+        assert_eq!(parse_indexed(&src), parse_with_options(&src, &ParseOptions::default()));
+    }
+
+    #[test]
+    fn test_parse_indexed_matches_scalar_parser() {
+        let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+        let options = ParseOptions::default();
+        let mut accepted = 0;
+        for _ in 0..3000 {
+            let doc = random_document(&mut rng);
+            let expected = parse_with_options(&doc, &options);
+            for backend in Backend::available() {
+                let indexed = parse_with_backend(&doc, &options, backend);
+                assert_eq!(indexed.is_ok(), expected.is_ok(), "{:?} on {}: {:?}", backend, doc, indexed);
+                if let (Ok(a), Ok(b)) = (&indexed, &expected) {
+                    assert_eq!(a, b, "{:?} on {}", backend, doc);
+                }
+            }
+            accepted += expected.is_ok() as usize;
+        }
+        // Make sure both valid and invalid documents were exercised
+        assert!(accepted > 500 && accepted < 2900, "{}", accepted);
+    }
+
+    #[test]
+    fn test_parse_indexed_options() {
+        let deep = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        assert!(parse_indexed(&deep).is_err());
+        let options = ParseOptions { max_depth: 256, allow_bom: true, ..ParseOptions::default() };
+        assert!(parse_indexed_with_options(&deep, &options).is_ok());
+        assert_eq!(parse_indexed_with_options("\u{FEFF}[1]", &options).unwrap(), json!([1]));
+        assert!(parse_indexed("\u{FEFF}[1]").is_err());
+        assert!(parse_indexed("").is_err());
+        assert!(parse_indexed("  ").is_err());
+        assert!(parse_indexed("[1] [2]").is_err());
+        assert!(parse_indexed("{\"a\" 1}").is_err());
+    }
+}
@@ -0,0 +1,651 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core;
+use crate::core::{parse_value, strip_bom};
+use crate::utils;
+use crate::options;
+use crate::string;
+use crate::number;
+use crate::map;
+use crate::intern;
+use crate::pointer;
+
+use core::{Json, JsonError};
+use utils::{ignore_ws, rest};
+use options::ParseOptions;
+use string::{dump_string, parse_quoted_into};
+use number::dump_number;
+use map::{INDEX_THRESHOLD, JsonMap};
+use intern::Keys;
+use pointer::parse_array_index;
+
+// One entry per value, in document order. A container is followed by its
+// contents and records where they end, so whole subtrees can be skipped
+// without looking at them. An object member is a `String` key node directly
+// followed by the value's nodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Node {
+    Null,
+    Bool(bool),
+    Number(f64),
+    // Byte range into the string arena
+    String { start: u32, end: u32 },
+    Array { len: u32, end: u32 },
+    Object { len: u32, end: u32 },
+}
+
+// A whole document stored in two flat buffers: the node tape and one arena
+// holding every unescaped string and key back to back. Duplicate keys are
+// resolved while parsing the way `Json` resolves them, so each object holds
+// every key once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonTape {
+    nodes: Vec<Node>,
+    strings: String,
+}
+
+impl JsonTape {
+    pub fn parse(source: &str) -> Result<JsonTape, JsonError> {
+        JsonTape::parse_with_options(source, &ParseOptions::default())
+    }
+
+    pub fn parse_with_options(source: &str, options: &ParseOptions) -> Result<JsonTape, JsonError> {
+        let source = strip_bom(source, options)?;
+        let mut builder = TapeBuilder { tape: JsonTape::empty(), options };
+        let tail = builder.value(source, 0)?;
+        if !ignore_ws(tail).is_empty() {
+            return Err(JsonError::SyntaxError("Unexpected trailing characters after JSON value".to_string()));
+        }
+        let mut tape = builder.tape;
+        tape.nodes.shrink_to_fit();
+        tape.strings.shrink_to_fit();
+        Ok(tape)
+    }
+
+    fn empty() -> JsonTape {
+        JsonTape { nodes: Vec::new(), strings: String::new() }
+    }
+
+    pub fn root(&self) -> TapeRef<'_> {
+        TapeRef { tape: self, idx: 0 }
+    }
+
+    pub fn to_json(&self) -> Json {
+        self.root().to_json()
+    }
+
+    pub fn dump(&self) -> String {
+        self.root().dump()
+    }
+
+    // Index just past the subtree starting at `idx`
+    fn skip(&self, idx: usize) -> usize {
+        match self.nodes[idx] {
+            Node::Array { end, .. } | Node::Object { end, .. } => end as usize,
+            _ => idx + 1,
+        }
+    }
+
+    fn str_at(&self, idx: usize) -> &str {
+        match self.nodes[idx] {
+            Node::String { start, end } => &self.strings[start as usize..end as usize],
+            _ => unreachable!("object keys are string nodes"),
+        }
+    }
+
+    fn push_string(&mut self, s: &str) -> Result<(), JsonError> {
+        let start = to_u32(self.strings.len())?;
+        self.strings.push_str(s);
+        let end = to_u32(self.strings.len())?;
+        self.nodes.push(Node::String { start, end });
+        Ok(())
+    }
+
+    // Leaves one member per key in the finished object at `at`, the way
+    // `JsonMap::insert` does: where the key first appears, with its last
+    // value. Returns the number of members left.
+    fn drop_duplicate_keys(&mut self, at: usize) -> usize {
+        let object = TapeRef { tape: self, idx: at };
+        let duplicated = if object.len() <= INDEX_THRESHOLD {
+            object.members().enumerate().any(|(i, (key, _))| object.members().take(i).any(|(k, _)| k == key))
+        } else {
+            let mut seen = HashSet::new();
+            !object.members().all(|(key, _)| seen.insert(key))
+        };
+        if !duplicated {
+            return object.len();
+        }
+
+        // Node indexes of each key and of the value that wins
+        let mut kept: Vec<(usize, usize)> = Vec::new();
+        let mut slots: HashMap<&str, usize> = HashMap::new();
+        for (key, value) in object.members() {
+            match slots.get(key) {
+                Some(&slot) => kept[slot].1 = value.idx,
+                None => {
+                    slots.insert(key, kept.len());
+                    kept.push((value.idx - 1, value.idx));
+                }
+            }
+        }
+        drop(slots);
+
+        let base = at + 1;
+        let old = self.nodes.split_off(base);
+        for &(key, value) in &kept {
+            self.nodes.push(old[key - base]);
+            let (from, to) = (value - base, self.nodes.len());
+            let end = match old[from] {
+                Node::Array { end, .. } | Node::Object { end, .. } => end as usize - base,
+                _ => from + 1,
+            };
+            // Containers record where they end, which moves with them
+            self.nodes.extend(old[from..end].iter().map(|&node| match node {
+                Node::Array { len, end } => Node::Array { len, end: (end as usize - value + to) as u32 },
+                Node::Object { len, end } => Node::Object { len, end: (end as usize - value + to) as u32 },
+                node => node,
+            }));
+        }
+        self.nodes[at] = Node::Object { len: kept.len() as u32, end: self.nodes.len() as u32 };
+        kept.len()
+    }
+
+    fn push_json(&mut self, json: &Json) -> Result<(), JsonError> {
+        match json {
+            Json::JsonObject(map) => {
+                let at = self.nodes.len();
+                self.nodes.push(Node::Null);
+                for (key, value) in map {
+                    self.push_string(key)?;
+                    self.push_json(value)?;
+                }
+                self.nodes[at] = Node::Object { len: to_u32(map.len())?, end: to_u32(self.nodes.len())? };
+            }
+            Json::JsonArray(values) => {
+                let at = self.nodes.len();
+                self.nodes.push(Node::Null);
+                for value in values {
+                    self.push_json(value)?;
+                }
+                self.nodes[at] = Node::Array { len: to_u32(values.len())?, end: to_u32(self.nodes.len())? };
+            }
+            Json::JsonString(s) => self.push_string(s)?,
+            Json::JsonNumber(n) => self.nodes.push(Node::Number(*n)),
+            Json::JsonBoolean(b) => self.nodes.push(Node::Bool(*b)),
+            Json::JsonNull => self.nodes.push(Node::Null),
+        }
+        Ok(())
+    }
+}
+
+fn to_u32(n: usize) -> Result<u32, JsonError> {
+    u32::try_from(n).map_err(|_| JsonError::SyntaxError("Document is too large for a JsonTape".to_string()))
+}
+
+// Fails past 4 GiB of strings or 2^32 nodes, like parsing
+impl TryFrom<&Json> for JsonTape {
+    type Error = JsonError;
+    fn try_from(json: &Json) -> Result<JsonTape, JsonError> {
+        let mut tape = JsonTape::empty();
+        tape.push_json(json)?;
+        Ok(tape)
+    }
+}
+
+impl FromStr for JsonTape {
+    type Err = JsonError;
+    fn from_str(source: &str) -> Result<JsonTape, JsonError> {
+        JsonTape::parse(source)
+    }
+}
+
+// Same grammar and messages as `parse_object` and `parse_array`, writing
+// nodes instead of building values
+struct TapeBuilder<'o> {
+    tape: JsonTape,
+    options: &'o ParseOptions,
+}
+
+impl TapeBuilder<'_> {
+    fn value<'a>(&mut self, source: &'a str, depth: usize) -> Result<&'a str, JsonError> {
+        let s = ignore_ws(source);
+        match s.as_bytes().first() {
+            Some(b'{' | b'[') if depth >= self.options.max_depth => {
+                Err(JsonError::DepthError { max_depth: self.options.max_depth })
+            }
+            Some(b'{') => self.object(s, depth + 1),
+            Some(b'[') => self.array(s, depth + 1),
+            Some(b'"') => self.string(s),
+            _ => {
+                let (json, tail) = parse_value(s.as_bytes(), self.options, depth, &mut Keys::new(None))?;
+                self.tape.nodes.push(match json {
+                    Json::JsonNumber(n) => Node::Number(n),
+                    Json::JsonBoolean(b) => Node::Bool(b),
+                    _ => Node::Null,
+                });
+                Ok(rest(s, tail))
+            }
+        }
+    }
+
+    fn string<'a>(&mut self, s: &'a str) -> Result<&'a str, JsonError> {
+        let start = to_u32(self.tape.strings.len())?;
+        let tail = parse_quoted_into(s.as_bytes(), self.options, &mut self.tape.strings)?;
+        let end = to_u32(self.tape.strings.len())?;
+        self.tape.nodes.push(Node::String { start, end });
+        Ok(rest(s, tail))
+    }
+
+    fn array<'a>(&mut self, source: &'a str, depth: usize) -> Result<&'a str, JsonError> {
+        let at = self.tape.nodes.len();
+        self.tape.nodes.push(Node::Null);
+        let mut len = 0usize;
+        let mut s = ignore_ws(&source[1..]);
+        if let Some(rest) = s.strip_prefix(']') {
+            s = rest;
+        } else {
+            loop {
+                s = ignore_ws(self.value(s, depth)?);
+                len += 1;
+                if s.starts_with(',') {
+                    s = ignore_ws(&s[1..]);
+                    if s.starts_with(']') {
+                        return Err(JsonError::SyntaxError("JSON Array should not end in a comma".to_string()));
+                    }
+                } else if s.starts_with(']') {
+                    s = &s[1..];
+                    break;
+                } else {
+                    return Err(JsonError::SyntaxError("JSON Array should separate values with commas".to_string()));
+                }
+            }
+        }
+        self.tape.nodes[at] = Node::Array { len: to_u32(len)?, end: to_u32(self.tape.nodes.len())? };
+        Ok(s)
+    }
+
+    fn object<'a>(&mut self, source: &'a str, depth: usize) -> Result<&'a str, JsonError> {
+        let at = self.tape.nodes.len();
+        self.tape.nodes.push(Node::Null);
+        let mut len = 0usize;
+        let mut s = ignore_ws(&source[1..]);
+        if let Some(rest) = s.strip_prefix('}') {
+            s = rest;
+        } else {
+            loop {
+                s = match s.chars().next() {
+                    Some('"') => ignore_ws(self.string(s)?),
+                    Some(c) => return Err(JsonError::SyntaxError(format!("Expected a '\"' as start of JSON object field but found '{}'", c))),
+                    None => return Err(JsonError::SyntaxError("Expected a '\"' start of JSON object field but found end of string".to_string())),
+                };
+                if !s.starts_with(':') {
+                    return Err(JsonError::SyntaxError("Expected ':' after field name".to_string()));
+                }
+                s = ignore_ws(self.value(&s[1..], depth)?);
+                len += 1;
+                if s.starts_with(',') {
+                    s = ignore_ws(&s[1..]);
+                    if s.starts_with('}') {
+                        return Err(JsonError::SyntaxError("JSON Object should not end in a comma".to_string()));
+                    }
+                } else if s.starts_with('}') {
+                    s = &s[1..];
+                    break;
+                } else if s.is_empty() {
+                    return Err(JsonError::SyntaxError("Unexpected end of input in object".to_string()));
+                } else {
+                    return Err(JsonError::SyntaxError(format!("Unexpected character in object: '{}'", s.chars().next().unwrap())));
+                }
+            }
+        }
+        self.tape.nodes[at] = Node::Object { len: to_u32(len)?, end: to_u32(self.tape.nodes.len())? };
+        self.tape.drop_duplicate_keys(at);
+        Ok(s)
+    }
+}
+
+// A value inside a `JsonTape`. Copying one is free and navigating never
+// allocates; lookups by key scan the members in order up to the match.
+#[derive(Clone, Copy)]
+pub struct TapeRef<'a> {
+    tape: &'a JsonTape,
+    idx: usize,
+}
+
+impl<'a> TapeRef<'a> {
+    fn node(&self) -> Node {
+        self.tape.nodes[self.idx]
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.node() {
+            Node::Object { .. } => "object",
+            Node::Array { .. } => "array",
+            Node::String { .. } => "string",
+            Node::Number(_) => "number",
+            Node::Bool(_) => "boolean",
+            Node::Null => "null",
+        }
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self.node(), Node::Object { .. })
+    }
+    pub fn is_array(&self) -> bool {
+        matches!(self.node(), Node::Array { .. })
+    }
+    pub fn is_string(&self) -> bool {
+        matches!(self.node(), Node::String { .. })
+    }
+    pub fn is_number(&self) -> bool {
+        matches!(self.node(), Node::Number(_))
+    }
+    pub fn is_bool(&self) -> bool {
+        matches!(self.node(), Node::Bool(_))
+    }
+    pub fn is_null(&self) -> bool {
+        matches!(self.node(), Node::Null)
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.node() {
+            Node::String { .. } => Some(self.tape.str_at(self.idx)),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.node() {
+            Node::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+    // Same lossless rules as `Json::as_i64` and `Json::as_u64`
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_f64().and_then(|n| Json::JsonNumber(n).as_i64())
+    }
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_f64().and_then(|n| Json::JsonNumber(n).as_u64())
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node() {
+            Node::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    // Number of elements or members; 0 for anything else
+    pub fn len(&self) -> usize {
+        match self.node() {
+            Node::Array { len, .. } | Node::Object { len, .. } => len as usize,
+            _ => 0,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &str) -> Option<TapeRef<'a>> {
+        self.members().find(|(k, _)| *k == key).map(|(_, value)| value)
+    }
+
+    pub fn at(&self, index: usize) -> Option<TapeRef<'a>> {
+        self.iter().nth(index)
+    }
+
+    // Elements of an array; empty for anything else
+    pub fn iter(&self) -> Elements<'a> {
+        match self.node() {
+            Node::Array { len, .. } => Elements { tape: self.tape, idx: self.idx + 1, remaining: len as usize },
+            _ => Elements { tape: self.tape, idx: self.idx, remaining: 0 },
+        }
+    }
+
+    // Key-value pairs of an object in document order; empty for anything else
+    pub fn members(&self) -> Members<'a> {
+        match self.node() {
+            Node::Object { len, .. } => Members { tape: self.tape, idx: self.idx + 1, remaining: len as usize },
+            _ => Members { tape: self.tape, idx: self.idx, remaining: 0 },
+        }
+    }
+
+    // RFC 6901 JSON Pointer, e.g. "/items/0/name"; "" is the value itself
+    pub fn pointer(&self, pointer: &str) -> Option<TapeRef<'a>> {
+        if pointer.is_empty() {
+            return Some(*self);
+        }
+        let mut current = *self;
+        for token in pointer.strip_prefix('/')?.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            current = match current.node() {
+                Node::Object { .. } => current.get(&token)?,
+                Node::Array { .. } => current.at(parse_array_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    pub fn to_json(&self) -> Json {
+        match self.node() {
+            Node::Object { .. } => {
+                let mut map = JsonMap::new();
+                for (key, value) in self.members() {
+                    map.insert(key.to_string(), value.to_json());
+                }
+                Json::JsonObject(map)
+            }
+            Node::Array { .. } => Json::JsonArray(self.iter().map(|value| value.to_json()).collect()),
+            Node::String { .. } => Json::JsonString(self.tape.str_at(self.idx).to_string()),
+            Node::Number(n) => Json::JsonNumber(n),
+            Node::Bool(b) => Json::JsonBoolean(b),
+            Node::Null => Json::JsonNull,
+        }
+    }
+
+    // Same output as `to_json().dump()`
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        self.dump_into(&mut out);
+        out
+    }
+
+    fn dump_into(&self, out: &mut String) {
+        match self.node() {
+            Node::Object { .. } => {
+                out.push('{');
+                for (i, (key, value)) in self.members().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    dump_string(key, out);
+                    out.push(':');
+                    value.dump_into(out);
+                }
+                out.push('}');
+            }
+            Node::Array { .. } => {
+                out.push('[');
+                for (i, value) in self.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    value.dump_into(out);
+                }
+                out.push(']');
+            }
+            Node::String { .. } => dump_string(self.tape.str_at(self.idx), out),
+            Node::Number(n) => dump_number(n, out),
+            Node::Bool(b) => out.push_str(if b { "true" } else { "false" }),
+            Node::Null => out.push_str("null"),
+        }
+    }
+}
+
+impl fmt::Debug for TapeRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.dump())
+    }
+}
+
+impl PartialEq for TapeRef<'_> {
+    // Handles into the same tape are equal only if they are the same node
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.tape, other.tape) && self.idx == other.idx
+    }
+}
+
+pub struct Elements<'a> {
+    tape: &'a JsonTape,
+    idx: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = TapeRef<'a>;
+    fn next(&mut self) -> Option<TapeRef<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let value = TapeRef { tape: self.tape, idx: self.idx };
+        self.idx = self.tape.skip(self.idx);
+        self.remaining -= 1;
+        Some(value)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Elements<'_> {}
+
+pub struct Members<'a> {
+    tape: &'a JsonTape,
+    idx: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (&'a str, TapeRef<'a>);
+    fn next(&mut self) -> Option<(&'a str, TapeRef<'a>)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let key = self.tape.str_at(self.idx);
+        let value = TapeRef { tape: self.tape, idx: self.idx + 1 };
+        self.idx = self.tape.skip(self.idx + 1);
+        self.remaining -= 1;
+        Some((key, value))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Members<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_with_options;
+    use crate::options::NumberOverflow;
+
+    const DOC: &str = r#"{"name":"Alice","tags":["a","b\n"],"nested":{"a/b":1,"m~n":[true,null,-2.5]},"n":42}"#;
+
+    #[test]
+    fn test_tape_round_trip() {
+        let tape = JsonTape::parse(DOC).unwrap();
+        let json = parse_with_options(DOC, &ParseOptions::default()).unwrap();
+        assert_eq!(tape.to_json(), json);
+        assert_eq!(tape.dump(), json.dump());
+        assert_eq!(JsonTape::try_from(&json), Ok(tape));
+        for source in ["1", "\"x\"", "[]", "{}", "[[],{}]", " null "] {
+            assert_eq!(source.parse::<JsonTape>().unwrap().to_json(), source.parse::<Json>().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_tape_navigation() {
+        let tape = JsonTape::parse(DOC).unwrap();
+        let root = tape.root();
+        assert!(root.is_object());
+        assert_eq!(root.len(), 4);
+        assert_eq!(root.get("name").and_then(|v| v.as_str()), Some("Alice"));
+        assert_eq!(root.get("n").and_then(|v| v.as_u64()), Some(42));
+        assert_eq!(root.get("missing"), None);
+        let tags = root.get("tags").unwrap();
+        assert_eq!(tags.iter().len(), 2);
+        assert_eq!(tags.at(1).and_then(|v| v.as_str()), Some("b\n"));
+        assert_eq!(tags.at(2), None);
+        let keys: Vec<&str> = root.members().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["name", "tags", "nested", "n"]);
+        assert_eq!(root.get("nested").unwrap().dump(), r#"{"a/b":1,"m~n":[true,null,-2.5]}"#);
+    }
+
+    #[test]
+    fn test_tape_pointer() {
+        let tape = JsonTape::parse(DOC).unwrap();
+        let root = tape.root();
+        assert_eq!(root.pointer(""), Some(root));
+        assert_eq!(root.pointer("/nested/a~1b").and_then(|v| v.as_i64()), Some(1));
+        assert_eq!(root.pointer("/nested/m~0n/2").and_then(|v| v.as_f64()), Some(-2.5));
+        assert!(root.pointer("/nested/m~0n/1").unwrap().is_null());
+        assert_eq!(root.pointer("/tags/01"), None);
+        assert_eq!(root.pointer("/tags/-"), None);
+        assert_eq!(root.pointer("/name/0"), None);
+        assert_eq!(root.pointer("name"), None);
+    }
+
+    #[test]
+    fn test_tape_duplicate_keys() {
+        let tape = JsonTape::parse(r#"{"a":1,"b":2,"a":3}"#).unwrap();
+        assert_eq!(tape.root().len(), 2);
+        assert_eq!(tape.root().get("a").and_then(|v| v.as_f64()), Some(3.0));
+        assert_eq!(tape.dump(), r#"{"a":3,"b":2}"#);
+        assert_eq!(tape.to_json().dump(), r#"{"a":3,"b":2}"#);
+
+        // Winning values that are containers move along with what they hold
+        let source = r#"{"x":[1,{"y":2,"y":[3]}],"z":{"k":"v"},"x":{"p":[4,5],"q":null},"w":6}"#;
+        let tape = JsonTape::parse(source).unwrap();
+        assert_eq!(tape.dump(), r#"{"x":{"p":[4,5],"q":null},"z":{"k":"v"},"w":6}"#);
+        assert_eq!(tape.to_json(), parse_with_options(source, &ParseOptions::default()).unwrap());
+        assert_eq!(tape.root().pointer("/w").and_then(|v| v.as_f64()), Some(6.0));
+        assert_eq!(tape.root().pointer("/x/p/1").and_then(|v| v.as_f64()), Some(5.0));
+        let nested = JsonTape::parse(r#"[{"y":2,"y":[3]},7]"#).unwrap();
+        assert_eq!(nested.dump(), r#"[{"y":[3]},7]"#);
+        assert_eq!(nested.root().at(1).and_then(|v| v.as_f64()), Some(7.0));
+
+        let many: Vec<String> = (0..40).map(|i| format!(r#""k{}":{}"#, i % 30, i)).collect();
+        let tape = JsonTape::parse(&format!("{{{}}}", many.join(","))).unwrap();
+        assert_eq!(tape.root().len(), 30);
+        assert_eq!(tape.root().get("k5").and_then(|v| v.as_f64()), Some(35.0));
+        assert_eq!(tape.root().get("k29").and_then(|v| v.as_f64()), Some(29.0));
+    }
+
+    #[test]
+    fn test_tape_try_from_json() {
+        let json = json!({"a": [1, {"b": null}], "c": "d"});
+        let tape = JsonTape::try_from(&json).unwrap();
+        assert_eq!(tape.to_json(), json);
+        assert_eq!(tape.root().pointer("/a/1/b").map(|v| v.is_null()), Some(true));
+    }
+
+    #[test]
+    fn test_tape_dump_non_finite_numbers() {
+        let infinity = ParseOptions { number_overflow: NumberOverflow::Infinity, ..ParseOptions::default() };
+        let tape = JsonTape::parse_with_options("[1e999, -1e999, 2]", &infinity).unwrap();
+        assert_eq!(tape.dump(), "[null,null,2]");
+        assert_eq!(tape.dump(), tape.to_json().dump());
+    }
+
+    #[test]
+    fn test_tape_rejects_invalid() {
+        for source in ["", "[1,]", "{\"a\":1,}", "{\"a\" 1}", "[1 2]", "{1:2}", "[1] x", "\u{FEFF}[]", "[\"\\x\"]"] {
+            assert!(JsonTape::parse(source).is_err(), "{:?}", source);
+        }
+        let shallow = ParseOptions { max_depth: 2, ..ParseOptions::default() };
+        assert!(JsonTape::parse_with_options("[[1]]", &shallow).is_ok());
+        assert!(JsonTape::parse_with_options("[[[1]]]", &shallow).is_err());
+    }
+}
@@ -1,15 +1,176 @@
-fn is_ws(c: char) -> bool {
-    c == '\u{0020}' || c == '\u{000A}' || c == '\u{000D}' || c == '\u{0009}'
+use crate::core;
+
+use core::JsonError;
+
+// Byte classes used by the lexer, indexed by byte value
+const WS: u8 = 1;
+// Bytes that may directly follow a literal or a number
+const DELIM: u8 = 2;
+// Bytes that end a run of plain string contents: '"', '\\' and controls
+const STRING_END: u8 = 4;
+
+static CLASSES: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 0x20 {
+        table[i] = STRING_END;
+        i += 1;
+    }
+    table[b' ' as usize] |= WS | DELIM;
+    table[b'\n' as usize] |= WS | DELIM;
+    table[b'\r' as usize] |= WS | DELIM;
+    table[b'\t' as usize] |= WS | DELIM;
+    table[b',' as usize] |= DELIM;
+    table[b':' as usize] |= DELIM;
+    table[b']' as usize] |= DELIM;
+    table[b'}' as usize] |= DELIM;
+    table[b'"' as usize] |= STRING_END;
+    table[b'\\' as usize] |= STRING_END;
+    table
+};
+
+pub fn is_ws(b: u8) -> bool {
+    CLASSES[b as usize] & WS != 0
+}
+
+// Whether a literal or number may end right before `b`; `None` is end of input
+pub fn is_delim(b: Option<u8>) -> bool {
+    b.is_none_or(|b| CLASSES[b as usize] & DELIM != 0)
+}
+
+pub fn is_string_end(b: u8) -> bool {
+    CLASSES[b as usize] & STRING_END != 0
+}
+
+pub fn skip_ws(bytes: &[u8], mut pos: usize) -> usize {
+    while pos < bytes.len() && is_ws(bytes[pos]) {
+        pos += 1;
+    }
+    pos
 }
 
 pub fn ignore_ws(source: &str) -> &str {
-    let mut s = source;
-    while let Some(c) = s.chars().next() {
-        if is_ws(c) {
-            s = &s[c.len_utf8()..];
-        } else {
-            break;
+    // Whitespace is ASCII, so the result always starts on a char boundary
+    &source[skip_ws(source.as_bytes(), 0)..]
+}
+
+pub fn ignore_ws_bytes(bytes: &[u8]) -> &[u8] {
+    &bytes[skip_ws(bytes, 0)..]
+}
+
+// The part of `source` that the lexer left as `tail`. Tails always follow a
+// whole token, so they start on a char boundary.
+pub fn rest<'a>(source: &'a str, tail: &[u8]) -> &'a str {
+    &source[source.len() - tail.len()..]
+}
+
+// The plain string contents from `start` up to the next quote, backslash or
+// control byte, and where they end. Those bytes are all ASCII, so contents
+// that are UTF-8 are whole characters. Only runs with bytes past ASCII need
+// checking, and nearly all runs have none.
+pub fn string_run(bytes: &[u8], start: usize) -> Result<(&str, usize), JsonError> {
+    let mut end = start;
+    let mut high = 0;
+    while end < bytes.len() && !is_string_end(bytes[end]) {
+        high |= bytes[end];
+        end += 1;
+    }
+    let run = &bytes[start..end];
+    if high < 0x80 {
+        // Sound because ASCII is UTF-8
+        Ok((unsafe { std::str::from_utf8_unchecked(run) }, end))
+    } else {
+        match std::str::from_utf8(run) {
+            Ok(run) => Ok((run, end)),
+            Err(err) => Err(invalid_utf8(&bytes[start + err.valid_up_to()..])),
+        }
+    }
+}
+
+// The lexer works on bytes that may not be UTF-8 and only sees the rest of
+// the input, so its encoding errors count their offset back from the end
+// until `from_start` turns it around once the whole input is known
+pub fn invalid_utf8(bytes: &[u8]) -> JsonError {
+    let message = match std::str::from_utf8(&bytes[..bytes.len().min(4)]).map_err(|err| err.error_len()) {
+        Err(Some(len)) => format!("Invalid UTF-8 sequence {:02X?}", &bytes[..len]),
+        _ => "Truncated UTF-8 sequence at end of input".to_string(),
+    };
+    JsonError::EncodingError { offset: bytes.len(), message }
+}
+
+pub fn from_start(error: JsonError, len: usize) -> JsonError {
+    match error {
+        JsonError::EncodingError { offset, message } => JsonError::EncodingError { offset: len - offset, message },
+        error => error,
+    }
+}
+
+// The character at the start of `bytes`, or `None` at end of input. Outside
+// strings the lexer only asks when the input is unexpected, which is where
+// bytes that are not UTF-8 get reported.
+pub fn peek_char(bytes: &[u8]) -> Result<Option<char>, JsonError> {
+    match std::str::from_utf8(&bytes[..bytes.len().min(4)]) {
+        Ok(s) => Ok(s.chars().next()),
+        Err(err) if err.valid_up_to() > 0 => Ok(std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default().chars().next()),
+        Err(_) => Err(invalid_utf8(bytes)),
+    }
+}
+
+// Up to `max` bytes from the start of `bytes` as text for error messages,
+// cut back to whole characters
+pub fn excerpt(bytes: &[u8], max: usize) -> Result<&str, JsonError> {
+    let prefix = &bytes[..bytes.len().min(max)];
+    match std::str::from_utf8(prefix) {
+        Ok(s) => Ok(s),
+        Err(err) if err.error_len().is_none() && prefix.len() < bytes.len() => {
+            Ok(std::str::from_utf8(&prefix[..err.valid_up_to()]).unwrap_or_default())
         }
+        Err(err) => Err(invalid_utf8(&bytes[err.valid_up_to()..])),
     }
-    s
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_ws() {
+        assert_eq!(ignore_ws(" \t\r\n x "), "x ");
+        assert_eq!(ignore_ws("\u{A0}x"), "\u{A0}x");
+        assert_eq!(ignore_ws("  "), "");
+    }
+
+    #[test]
+    fn test_string_run() {
+        assert_eq!(string_run(b"\"ab\\n\"", 1), Ok(("ab", 3)));
+        assert_eq!(string_run("\"\u{e9}t\u{e9}\"".as_bytes(), 1), Ok(("\u{e9}t\u{e9}", 6)));
+        assert_eq!(string_run(b"\"abc", 1), Ok(("abc", 4)));
+        let err = string_run(b"\"a\xC3\"", 1).unwrap_err();
+        assert_eq!(from_start(err, 4), JsonError::EncodingError { offset: 2, message: "Invalid UTF-8 sequence [C3]".to_string() });
+    }
+
+    #[test]
+    fn test_lexer_utf8_helpers() {
+        assert_eq!(peek_char(b""), Ok(None));
+        assert_eq!(peek_char("\u{e9}x".as_bytes()), Ok(Some('\u{e9}')));
+        assert_eq!(peek_char(b"\xC3\xA9\xFF"), Ok(Some('\u{e9}')));
+        assert_eq!(peek_char(b"x\xFF\xFF"), Ok(Some('x')));
+        let err = peek_char(b"\xFF, 1]").unwrap_err();
+        assert_eq!(from_start(err, 10), JsonError::EncodingError { offset: 5, message: "Invalid UTF-8 sequence [FF]".to_string() });
+        assert_eq!(excerpt("tru\u{e9}".as_bytes(), 4), Ok("tru"));
+        assert_eq!(excerpt(b"nul", 4), Ok("nul"));
+        assert!(matches!(excerpt(b"tr\xFFe", 4), Err(JsonError::EncodingError { offset: 2, .. })));
+        assert!(matches!(excerpt(b"tr\xE2\x82", 4), Err(JsonError::EncodingError { offset: 2, .. })));
+        assert_eq!(rest("[\u{e9}]", &b"[\xC3\xA9]"[3..]), "]");
+    }
+
+    #[test]
+    fn test_byte_classes() {
+        assert!(is_delim(None));
+        assert!(is_delim(Some(b']')));
+        assert!(!is_delim(Some(b'[')));
+        assert!(!is_delim(Some(0xC2)));
+        assert!(is_string_end(b'"') && is_string_end(b'\\') && is_string_end(0x1F));
+        assert!(!is_string_end(0x7F) && !is_string_end(0xE2));
+    }
+}
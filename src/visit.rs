@@ -0,0 +1,429 @@
+use std::mem;
+
+use crate::core;
+use crate::pointer;
+
+use core::Json;
+use pointer::JsonPointer;
+
+// Every traversal here keeps its own stack of open containers instead of
+// recursing, so nesting is bounded by memory rather than by the thread's
+// stack. Paths are the RFC 6901 pointers of the values being visited.
+
+// What a visitor wants done after entering a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    Continue,
+    // Don't visit the value's children; `exit` is still called for it
+    Skip,
+    // End the walk without calling any more hooks
+    Stop,
+}
+
+// Hooks called in document order: `enter` before a value's children and
+// `exit` after them
+pub trait Visitor {
+    fn enter(&mut self, _path: &JsonPointer, _value: &Json) -> Walk {
+        Walk::Continue
+    }
+    fn exit(&mut self, _path: &JsonPointer, _value: &Json) {}
+}
+
+// As `Visitor`, with the value open for changes. Whatever `enter` leaves
+// behind is what gets descended into, and `exit` sees the children as their
+// own hooks left them.
+pub trait VisitorMut {
+    fn enter(&mut self, _path: &JsonPointer, _value: &mut Json) -> Walk {
+        Walk::Continue
+    }
+    fn exit(&mut self, _path: &JsonPointer, _value: &mut Json) {}
+}
+
+fn child(node: &Json, idx: usize) -> Option<(String, &Json)> {
+    match node {
+        Json::JsonObject(map) => map.get_index(idx).map(|(key, value)| (key.to_string(), value)),
+        Json::JsonArray(values) => values.get(idx).map(|value| (idx.to_string(), value)),
+        _ => None,
+    }
+}
+
+// The mutable walk moves each child out of its parent while it is being
+// visited and back in afterwards, so only one `&mut` exists at a time
+fn take_child(node: &mut Json, idx: usize) -> Option<(String, Json)> {
+    match node {
+        Json::JsonObject(map) => map.get_index_mut(idx).map(|(key, value)| (key.to_string(), mem::take(value))),
+        Json::JsonArray(values) => values.get_mut(idx).map(|value| (idx.to_string(), mem::take(value))),
+        _ => None,
+    }
+}
+
+fn put_child(node: &mut Json, idx: usize, value: Json) {
+    let slot = match node {
+        Json::JsonObject(map) => map.get_index_mut(idx).map(|(_, slot)| slot),
+        Json::JsonArray(values) => values.get_mut(idx),
+        _ => None,
+    };
+    *slot.expect("the parent is not touched while a child is out") = value;
+}
+
+impl Json {
+    pub fn walk<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        let mut path = JsonPointer::root();
+        match visitor.enter(&path, self) {
+            Walk::Continue => {}
+            Walk::Skip => return visitor.exit(&path, self),
+            Walk::Stop => return,
+        }
+        // Entered values whose children are being visited, with the next child
+        let mut stack: Vec<(&Json, usize)> = vec![(self, 0)];
+        while let Some((node, next)) = stack.last_mut() {
+            match child(node, *next) {
+                Some((token, value)) => {
+                    *next += 1;
+                    path.push(token);
+                    match visitor.enter(&path, value) {
+                        Walk::Continue => stack.push((value, 0)),
+                        Walk::Skip => {
+                            visitor.exit(&path, value);
+                            path.pop();
+                        }
+                        Walk::Stop => return,
+                    }
+                }
+                None => {
+                    let node = *node;
+                    stack.pop();
+                    visitor.exit(&path, node);
+                    path.pop();
+                }
+            }
+        }
+    }
+
+    pub fn walk_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        let mut path = JsonPointer::root();
+        match visitor.enter(&path, self) {
+            Walk::Continue => {}
+            Walk::Skip => return visitor.exit(&path, self),
+            Walk::Stop => return,
+        }
+        let mut stack: Vec<(Json, usize)> = vec![(mem::take(self), 0)];
+        loop {
+            let (node, next) = stack.last_mut().expect("the root is popped last");
+            match take_child(node, *next) {
+                Some((token, mut value)) => {
+                    *next += 1;
+                    path.push(token);
+                    match visitor.enter(&path, &mut value) {
+                        Walk::Continue => stack.push((value, 0)),
+                        Walk::Skip => {
+                            visitor.exit(&path, &mut value);
+                            put_child(node, *next - 1, value);
+                            path.pop();
+                        }
+                        Walk::Stop => {
+                            stack.push((value, 0));
+                            break;
+                        }
+                    }
+                }
+                None => {
+                    let (mut node, _) = stack.pop().expect("the stack is not empty");
+                    visitor.exit(&path, &mut node);
+                    path.pop();
+                    match stack.last_mut() {
+                        Some((parent, next)) => put_child(parent, *next - 1, node),
+                        None => return *self = node,
+                    }
+                }
+            }
+        }
+        // Stopped early: put the open values back together without hooks
+        while let Some((node, _)) = stack.pop() {
+            match stack.last_mut() {
+                Some((parent, next)) => put_child(parent, *next - 1, node),
+                None => *self = node,
+            }
+        }
+    }
+
+    // Every value below this one with its path, parents before children.
+    // Each item owns a copy of its path.
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants { stack: vec![(self, 0)], path: JsonPointer::root() }
+    }
+
+    // The descendants for which `predicate` holds
+    pub fn filter<'a, F>(&'a self, mut predicate: F) -> impl Iterator<Item = (JsonPointer, &'a Json)>
+    where
+        F: FnMut(&JsonPointer, &Json) -> bool + 'a,
+    {
+        self.descendants().filter(move |(path, value)| predicate(path, value))
+    }
+
+    // Combines this value and all its descendants, parents before children
+    pub fn fold<B, F>(&self, init: B, f: F) -> B
+    where
+        F: FnMut(B, &JsonPointer, &Json) -> B,
+    {
+        struct Fold<B, F>(Option<B>, F);
+        impl<B, F: FnMut(B, &JsonPointer, &Json) -> B> Visitor for Fold<B, F> {
+            fn enter(&mut self, path: &JsonPointer, value: &Json) -> Walk {
+                let acc = self.0.take().expect("set between hooks");
+                self.0 = Some((self.1)(acc, path, value));
+                Walk::Continue
+            }
+        }
+        let mut fold = Fold(Some(init), f);
+        self.walk(&mut fold);
+        fold.0.expect("set between hooks")
+    }
+
+    // Rebuilds the tree bottom-up: `f` receives each value after its
+    // children have been replaced, and returns its replacement
+    pub fn map<F>(mut self, f: F) -> Json
+    where
+        F: FnMut(&JsonPointer, Json) -> Json,
+    {
+        struct Map<F>(F);
+        impl<F: FnMut(&JsonPointer, Json) -> Json> VisitorMut for Map<F> {
+            fn exit(&mut self, path: &JsonPointer, value: &mut Json) {
+                *value = (self.0)(path, mem::take(value));
+            }
+        }
+        self.walk_mut(&mut Map(f));
+        self
+    }
+
+    // Removes every array element and object member, at any depth, for
+    // which `keep` is false. Children are filtered before their parent is
+    // judged, so `keep` sees containers that have already lost theirs, and
+    // paths are those of the unfiltered tree.
+    pub fn retain_recursive<F>(&mut self, keep: F)
+    where
+        F: FnMut(&JsonPointer, &Json) -> bool,
+    {
+        // One list of verdicts per open value, filled as its children exit
+        struct Retain<F> {
+            keep: F,
+            verdicts: Vec<Vec<bool>>,
+        }
+        impl<F: FnMut(&JsonPointer, &Json) -> bool> VisitorMut for Retain<F> {
+            fn enter(&mut self, _path: &JsonPointer, _value: &mut Json) -> Walk {
+                self.verdicts.push(Vec::new());
+                Walk::Continue
+            }
+            fn exit(&mut self, path: &JsonPointer, value: &mut Json) {
+                let verdicts = self.verdicts.pop().expect("entered before exited");
+                match value {
+                    Json::JsonObject(map) => {
+                        let mut verdicts = verdicts.into_iter();
+                        map.retain(|_, _| verdicts.next().expect("one verdict per member"));
+                    }
+                    Json::JsonArray(values) => {
+                        let mut verdicts = verdicts.into_iter();
+                        values.retain(|_| verdicts.next().expect("one verdict per element"));
+                    }
+                    _ => {}
+                }
+                if let Some(parent) = self.verdicts.last_mut() {
+                    parent.push((self.keep)(path, value));
+                }
+            }
+        }
+        self.walk_mut(&mut Retain { keep, verdicts: Vec::new() });
+    }
+}
+
+pub struct Descendants<'a> {
+    stack: Vec<(&'a Json, usize)>,
+    path: JsonPointer,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = (JsonPointer, &'a Json);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, next) = self.stack.last_mut()?;
+            match child(node, *next) {
+                Some((token, value)) => {
+                    *next += 1;
+                    self.path.push(token);
+                    self.stack.push((value, 0));
+                    return Some((self.path.clone(), value));
+                }
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Json {
+        source.parse().unwrap()
+    }
+
+    // Nested far beyond what recursion on the default stack survives
+    fn deep(depth: usize) -> Json {
+        let mut json = Json::JsonNumber(1.0);
+        for i in 0..depth {
+            json = match i % 2 {
+                0 => Json::JsonArray(vec![json]),
+                _ => Json::from_iter([("k", json)]),
+            };
+        }
+        json
+    }
+
+    // Unwinds `deep` without recursive drops
+    fn flatten(mut json: Json) {
+        loop {
+            json = match json {
+                Json::JsonArray(mut values) => values.pop().unwrap_or_default(),
+                Json::JsonObject(map) => map.into_iter().next().map_or(Json::JsonNull, |(_, v)| v),
+                _ => return,
+            };
+        }
+    }
+
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn enter(&mut self, path: &JsonPointer, value: &Json) -> Walk {
+            self.0.push(format!("enter {}", path));
+            match value.as_str() {
+                Some("skip") => Walk::Skip,
+                Some("stop") => Walk::Stop,
+                _ => Walk::Continue,
+            }
+        }
+        fn exit(&mut self, path: &JsonPointer, _value: &Json) {
+            self.0.push(format!("exit {}", path));
+        }
+    }
+
+    #[test]
+    fn test_walk_order() {
+        let json = parse(r#"{"a": [1, {"b": null}], "c/d": true}"#);
+        let mut trace = Trace::default();
+        json.walk(&mut trace);
+        assert_eq!(trace.0, [
+            "enter ", "enter /a", "enter /a/0", "exit /a/0", "enter /a/1", "enter /a/1/b",
+            "exit /a/1/b", "exit /a/1", "exit /a", "enter /c~1d", "exit /c~1d", "exit ",
+        ]);
+
+        let json = parse(r#"[["skip", 1], "stop", 2]"#);
+        let mut trace = Trace::default();
+        json.walk(&mut trace);
+        assert_eq!(trace.0, ["enter ", "enter /0", "enter /0/0", "exit /0/0", "enter /0/1", "exit /0/1", "exit /0", "enter /1"]);
+    }
+
+    #[test]
+    fn test_walk_mut() {
+        struct Doubler;
+        impl VisitorMut for Doubler {
+            fn enter(&mut self, _path: &JsonPointer, value: &mut Json) -> Walk {
+                match value {
+                    Json::JsonString(s) if s == "stop" => Walk::Stop,
+                    Json::JsonString(_) => {
+                        *value = parse("[0]");
+                        Walk::Continue
+                    }
+                    _ => Walk::Continue,
+                }
+            }
+            fn exit(&mut self, _path: &JsonPointer, value: &mut Json) {
+                if let Json::JsonNumber(n) = value {
+                    *n *= 2.0;
+                }
+            }
+        }
+        let mut json = parse(r#"{"a": [1, "x"], "b": 3}"#);
+        json.walk_mut(&mut Doubler);
+        assert_eq!(json, parse(r#"{"a": [2, [0]], "b": 6}"#));
+
+        let mut json = parse(r#"[1, [2, "stop", 3], 4]"#);
+        json.walk_mut(&mut Doubler);
+        assert_eq!(json, parse(r#"[2, [4, "stop", 3], 4]"#));
+    }
+
+    #[test]
+    fn test_descendants() {
+        let json = parse(r#"{"a": [1, {"b": null}], "c": "x"}"#);
+        let paths: Vec<String> = json.descendants().map(|(path, _)| path.to_string()).collect();
+        assert_eq!(paths, ["/a", "/a/0", "/a/1", "/a/1/b", "/c"]);
+        for (path, value) in json.descendants() {
+            assert_eq!(path.resolve(&json), Some(value));
+        }
+        assert_eq!(Json::JsonNull.descendants().count(), 0);
+
+        let numbers: Vec<String> = json.filter(|_, v| v.is_number() || v.is_null()).map(|(p, _)| p.to_string()).collect();
+        assert_eq!(numbers, ["/a/0", "/a/1/b"]);
+    }
+
+    #[test]
+    fn test_fold_and_map() {
+        let json = parse(r#"{"a": [1, 2.5, {"b": 3}], "c": "4"}"#);
+        assert_eq!(json.fold(0.0, |sum, _, v| sum + v.as_f64().unwrap_or(0.0)), 6.5);
+        assert_eq!(json.fold(0, |depth, path, _| depth.max(path.depth())), 3);
+
+        let mapped = json.map(|path, value| match value {
+            Json::JsonNumber(n) => Json::JsonNumber(n * 10.0),
+            Json::JsonArray(values) => Json::JsonNumber(values.len() as f64),
+            Json::JsonString(_) => Json::JsonString(path.to_string()),
+            other => other,
+        });
+        assert_eq!(mapped, parse(r#"{"a": 3, "c": "/c"}"#));
+    }
+
+    #[test]
+    fn test_retain_recursive() {
+        let mut json = parse(r#"{"a": [null, 1, {"x": null}, null], "b": null, "c": {"d": {"e": null}}, "f": 2}"#);
+        let mut seen = Vec::new();
+        json.retain_recursive(|path, value| {
+            seen.push(path.to_string());
+            !value.is_null() && !value.as_object().is_some_and(|map| map.is_empty())
+        });
+        assert_eq!(json, parse(r#"{"a": [1], "f": 2}"#));
+        assert!(seen.contains(&"/a/3".to_string()));
+        assert!(seen.contains(&"/c/d/e".to_string()));
+    }
+
+    #[test]
+    fn test_deep_trees() {
+        struct Count(usize, usize);
+        impl Visitor for Count {
+            fn enter(&mut self, _path: &JsonPointer, _value: &Json) -> Walk {
+                self.0 += 1;
+                Walk::Continue
+            }
+            fn exit(&mut self, path: &JsonPointer, _value: &Json) {
+                self.1 = self.1.max(path.depth());
+            }
+        }
+        let depth = 200_000;
+        let mut json = deep(depth);
+        let mut count = Count(0, 0);
+        json.walk(&mut count);
+        assert_eq!((count.0, count.1), (depth + 1, depth));
+        assert_eq!(json.fold(0, |max, path, _| max.max(path.depth())), depth);
+
+        json.retain_recursive(|_, value| !value.is_number());
+        let json = json.map(|_, value| match value {
+            Json::JsonArray(values) if values.is_empty() => Json::JsonNull,
+            other => other,
+        });
+        let mut count = Count(0, 0);
+        json.walk(&mut count);
+        assert_eq!(count.0, depth);
+        flatten(json);
+    }
+}
@@ -0,0 +1,157 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use json_parser::Json;
+
+// Runs the binary with `stdin` as its input and returns the exit status,
+// standard output and standard error
+fn run(args: &[&str], stdin: &str) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_json-parser"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Fails when the command exits before reading its input, e.g. on bad arguments
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    let text = |bytes: Vec<u8>| String::from_utf8(bytes).unwrap();
+    (output.status.code().unwrap(), text(output.stdout), text(output.stderr))
+}
+
+// A file under the temporary directory, unique to this test run and removed
+// when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, contents: &[u8]) -> TempFile {
+        let path = std::env::temp_dir().join(format!("json-parser-cli-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        TempFile(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+const DOCUMENT: &str = r#"{"name": "x", "servers": [{"port": 8080}, {"port": 8081, "tls": true}], "tags": []}"#;
+
+#[test]
+fn test_validate() {
+    assert_eq!(run(&["validate"], DOCUMENT), (0, String::new(), String::new()));
+    let (status, stdout, stderr) = run(&["validate"], "{\n  \"a\": 1\n  \"b\": 2\n}");
+    assert_eq!((status, stdout.as_str()), (1, ""));
+    assert!(stderr.starts_with("error[E003]: Expected ',' or '}' after object member\n --> <stdin>:2:9\n"), "{}", stderr);
+    let (status, _, stderr) = run(&["validate"], &"[".repeat(200_000));
+    assert_eq!((status, stderr.as_str()), (1, "<stdin>: Nesting exceeds the maximum depth of 128\n"));
+    let (status, _, stderr) = run(&["validate", "--ndjson"], &format!("1\n{}\n", "[".repeat(200_000)));
+    assert_eq!((status, stderr.as_str()), (1, "<stdin>:2: Nesting exceeds the maximum depth of 128\n"));
+    let (status, _, stderr) = run(&["validate"], "trué");
+    assert_eq!(status, 1);
+    assert!(stderr.starts_with("error[E012]: Unknown literal 'trué'\n"), "{}", stderr);
+
+    let good = TempFile::new("good.json", DOCUMENT.as_bytes());
+    let bad = TempFile::new("bad.json", b"[1, 2");
+    let (status, _, stderr) = run(&["validate", good.path(), bad.path()], "");
+    assert_eq!(status, 1);
+    assert!(stderr.contains("bad.json:1:1") && !stderr.contains("good.json"), "{}", stderr);
+    let (status, _, stderr) = run(&["validate", "/nonexistent/file.json"], "");
+    assert_eq!(status, 2);
+    assert!(stderr.starts_with("json-parser: /nonexistent/file.json: "), "{}", stderr);
+    let (status, _, stderr) = run(&["validate", "--ndjson"], "{\"a\": 1}\n\n[1,]\n\"ok\"\n{\"b\" 2}\n");
+    assert_eq!((status, stderr.as_str()), (1, "<stdin>:3:3: Trailing comma in array\n<stdin>:5:5: Expected ':' after object key\n"));
+    // UTF-16 with a byte order mark goes through parse_bytes
+    let utf16: Vec<u8> = "\u{FEFF}[1]".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let file = TempFile::new("utf16.json", &utf16);
+    assert_eq!(run(&["validate", file.path()], "").0, 0);
+}
+
+#[test]
+fn test_fmt_and_minify() {
+    let (status, stdout, _) = run(&["fmt", "--sort-keys", "--indent", "1"], r#"{"b": [1, {}], "a": {"d": null, "c": "é"}}"#);
+    assert_eq!(status, 0);
+    assert_eq!(stdout, "{\n \"a\": {\n  \"c\": \"é\",\n  \"d\": null\n },\n \"b\": [\n  1,\n  {}\n ]\n}\n");
+    assert_eq!(run(&["minify"], "[ 1 ,\n {\"a\" : \"\\u0041\"} ]").1, "[1,{\"a\":\"A\"}]\n");
+    assert_eq!(run(&["minify", "--sort-keys", "--ndjson"], "{\"b\": 1, \"a\": 2}\n\n[ true ]\n").1, "{\"a\":2,\"b\":1}\n[true]\n");
+
+    let file = TempFile::new("fmt.json", DOCUMENT.as_bytes());
+    assert_eq!(run(&["fmt", "-i", file.path()], ""), (0, String::new(), String::new()));
+    let formatted = fs::read_to_string(&file.0).unwrap();
+    assert_eq!(formatted, format!("{}\n", DOCUMENT.parse::<Json>().unwrap().dump_pretty(2)));
+    assert!(formatted.contains("\n  \"servers\": [\n    {\n      \"port\": 8080\n"), "{}", formatted);
+
+    let broken = TempFile::new("broken.json", b"{\"a\": }");
+    assert_eq!(run(&["fmt", "-i", broken.path()], "").0, 1);
+    assert_eq!(fs::read_to_string(&broken.0).unwrap(), "{\"a\": }");
+    assert_eq!(run(&["fmt", "-i"], DOCUMENT).0, 2);
+}
+
+#[test]
+fn test_get_and_keys() {
+    assert_eq!(run(&["get", "/servers/1/port"], DOCUMENT), (0, "8081\n".to_string(), String::new()));
+    assert_eq!(run(&["get", "/servers/1"], DOCUMENT).1, "{\n  \"port\": 8081,\n  \"tls\": true\n}\n");
+    assert_eq!(run(&["get", ""], "[1]").1, "[\n  1\n]\n");
+    let (status, _, stderr) = run(&["get", "/servers/2"], DOCUMENT);
+    assert_eq!((status, stderr.as_str()), (1, "json-parser: <stdin>: no value at \"/servers/2\"\n"));
+    assert_eq!(run(&["get", "servers"], DOCUMENT).0, 2);
+    assert_eq!(run(&["get", "--ndjson", "/a"], "{\"a\": 1}\n{\"b\": 2}\n{\"a\": [3]}\n").1, "1\n[3]\n");
+
+    assert_eq!(run(&["keys"], DOCUMENT).1, "name\nservers\ntags\n");
+    assert_eq!(run(&["keys", "--pointer", "/servers"], DOCUMENT).1, "0\n1\n");
+    assert_eq!(run(&["keys", "--pointer=/name"], DOCUMENT).0, 1);
+    assert_eq!(run(&["keys", "--ndjson"], "{\"a\": 1, \"b\": 2}\n3\n{\"c\": 1, \"a\": 2}\n").1, "a\nb\nc\n");
+}
+
+#[test]
+fn test_stats() {
+    let (status, stdout, _) = run(&["stats"], DOCUMENT);
+    assert_eq!(status, 0);
+    let stats: Json = stdout.parse().unwrap();
+    assert_eq!(stats["values"], Json::JsonNumber(9.0));
+    assert_eq!(stats["objects"], Json::JsonNumber(3.0));
+    assert_eq!(stats["members"], Json::JsonNumber(6.0));
+    assert_eq!(stats["max_depth"], Json::JsonNumber(3.0));
+    let stats: Json = run(&["stats", "--ndjson"], "1\n[true, null]\n\"ab\"\n").1.parse().unwrap();
+    assert_eq!(stats["documents"], Json::JsonNumber(3.0));
+    assert_eq!(stats["values"], Json::JsonNumber(5.0));
+    assert_eq!(stats["string_bytes"], Json::JsonNumber(2.0));
+}
+
+#[test]
+fn test_diff() {
+    let a = TempFile::new("a.json", DOCUMENT.as_bytes());
+    let b = TempFile::new("b.json", br#"{"servers": [{"port": 9090}], "name": "x", "tags": [], "extra": {"k": 1}}"#);
+    let (a, b) = (a.path(), b.path());
+    let (status, stdout, _) = run(&["diff", a, b], "");
+    assert_eq!(status, 1);
+    assert_eq!(stdout, "~ /servers/0/port: 8080 -> 9090\n- /servers/1: {\"port\":8081,\"tls\":true}\n+ /extra: {\"k\":1}\n");
+    assert_eq!(run(&["diff", a, "-"], DOCUMENT), (0, String::new(), String::new()));
+    assert_eq!(run(&["diff", "-", a], "[]").1, format!("~ (root): [] -> {}\n", DOCUMENT.parse::<Json>().unwrap().dump()));
+    assert_eq!(run(&["diff", a], "").0, 2);
+}
+
+#[test]
+fn test_usage() {
+    let (status, stdout, _) = run(&["--help"], "");
+    assert_eq!(status, 0);
+    assert!(stdout.starts_with("Usage: json-parser <command>"));
+    assert_eq!(run(&[], "").0, 2);
+    assert_eq!(run(&["frobnicate"], "").0, 2);
+    let (status, _, stderr) = run(&["fmt", "--ndjson"], "");
+    assert_eq!(status, 2);
+    assert!(stderr.starts_with("json-parser: `fmt` does not take --ndjson\n"));
+    assert_eq!(run(&["fmt", "--indent"], "").0, 2);
+    assert_eq!(run(&["fmt", "--indent", "two"], "").0, 2);
+    assert_eq!(run(&["stats", "a", "b"], "").0, 2);
+}
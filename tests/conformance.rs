@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::PathBuf;
+
+use json_parser::{NumberOverflow, ParseOptions, SurrogatePolicy, parse_bytes_with_options, parse_with_options};
+
+// (case, accepted with ParseOptions::default(), accepted with permissive())
+const IMPLEMENTATION_DEFINED: &[(&str, bool, bool)] = &[
+    ("i_number_double_huge_neg_exp", true, true),
+    ("i_number_huge_exp", false, true),
+    ("i_number_neg_int_huge_exp", false, true),
+    ("i_number_pos_double_huge_exp", false, true),
+    ("i_number_real_neg_overflow", false, true),
+    ("i_number_real_pos_overflow", false, true),
+    ("i_number_real_underflow", true, true),
+    ("i_number_too_big_neg_int", true, true),
+    ("i_number_too_big_pos_int", true, true),
+    ("i_number_very_big_negative_int", true, true),
+    ("i_object_key_lone_2nd_surrogate", false, true),
+    ("i_string_1st_surrogate_but_2nd_missing", false, true),
+    ("i_string_1st_valid_surrogate_2nd_invalid", false, true),
+    ("i_string_incomplete_surrogate_and_escape_valid", false, true),
+    ("i_string_incomplete_surrogate_pair", false, true),
+    ("i_string_incomplete_surrogates_escape_valid", false, true),
+    ("i_string_invalid_lonely_surrogate", false, true),
+    ("i_string_invalid_surrogate", false, true),
+    ("i_string_inverted_surrogates_U+1D11E", false, true),
+    ("i_string_lone_second_surrogate", false, true),
+    // Detected as UTF-16 by parse_bytes; the BOM needs allow_bom like any other
+    ("i_string_UTF-16LE_with_BOM", false, true),
+    ("i_string_utf16BE_no_BOM", true, true),
+    ("i_string_utf16LE_no_BOM", true, true),
+    // Invalid UTF-8 is never accepted
+    ("i_string_UTF-8_invalid_sequence", false, false),
+    ("i_string_UTF8_surrogate_U+D800", false, false),
+    ("i_string_invalid_utf-8", false, false),
+    ("i_string_iso_latin_1", false, false),
+    ("i_string_lone_utf8_continuation_byte", false, false),
+    ("i_string_not_in_unicode_range", false, false),
+    ("i_string_overlong_sequence_2_bytes", false, false),
+    ("i_string_overlong_sequence_6_bytes", false, false),
+    ("i_string_overlong_sequence_6_bytes_null", false, false),
+    ("i_string_truncated-utf-8", false, false),
+    ("i_structure_500_nested_arrays", false, true),
+    ("i_structure_UTF-8_BOM_empty_object", false, true),
+];
+
+fn permissive() -> ParseOptions {
+    ParseOptions {
+        max_depth: 512,
+        allow_bom: true,
+        lone_surrogates: SurrogatePolicy::Replace,
+        number_overflow: NumberOverflow::Infinity,
+        ..ParseOptions::default()
+    }
+}
+
+fn cases(prefix: &str) -> Vec<(String, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/JSONTestSuite/test_parsing");
+    let mut cases: Vec<(String, Vec<u8>)> = fs::read_dir(&dir)
+        .expect("test corpus is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| (path.file_stem().unwrap().to_string_lossy().into_owned(), fs::read(&path).unwrap()))
+        .filter(|(name, _)| name.starts_with(prefix))
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty());
+    cases
+}
+
+#[test]
+fn test_accepts_every_y_case() {
+    let failures: Vec<String> = cases("y_").into_iter()
+        .filter_map(|(name, bytes)| parse_bytes_with_options(&bytes, &ParseOptions::default()).err().map(|err| format!("{}: {:?}", name, err)))
+        .collect();
+    assert!(failures.is_empty(), "rejected valid documents:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_rejects_every_n_case() {
+    for options in [ParseOptions::default(), permissive()] {
+        let failures: Vec<String> = cases("n_").into_iter()
+            .filter(|(_, bytes)| parse_bytes_with_options(bytes, &options).is_ok())
+            .map(|(name, _)| name)
+            .collect();
+        assert!(failures.is_empty(), "accepted invalid documents with {:?}:\n{}", options, failures.join("\n"));
+    }
+}
+
+#[test]
+fn test_i_cases_follow_options() {
+    let cases = cases("i_");
+    assert_eq!(cases.len(), IMPLEMENTATION_DEFINED.len());
+    for (name, bytes) in cases {
+        let &(_, by_default, when_permissive) = IMPLEMENTATION_DEFINED.iter()
+            .find(|(case, _, _)| *case == name)
+            .unwrap_or_else(|| panic!("{} has no documented behavior", name));
+        assert_eq!(parse_bytes_with_options(&bytes, &ParseOptions::default()).is_ok(), by_default, "{}", name);
+        assert_eq!(parse_bytes_with_options(&bytes, &permissive()).is_ok(), when_permissive, "{}", name);
+    }
+}
+
+#[test]
+fn test_y_values() {
+    let parse = |s: &str| parse_with_options(s, &ParseOptions::default()).unwrap();
+    assert_eq!(parse(r#"["𝄞"]"#)[0].as_str(), Some("\u{1D11E}"));
+    assert_eq!(parse(r#"{"foo\u0000bar": 42}"#)["foo\0bar"].as_i64(), Some(42));
+    assert_eq!(parse("[-0]")[0].as_f64().map(f64::is_sign_negative), Some(true));
+    assert_eq!(parse(r#"["\"\\\/\b\f\n\r\t"]"#)[0].as_str(), Some("\"\\/\u{8}\u{c}\n\r\t"));
+}
+
+#[test]
+fn test_max_depth_boundary() {
+    let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    let options = ParseOptions::default();
+    assert!(parse_with_options(&nested(options.max_depth), &options).is_ok());
+    assert!(parse_with_options(&nested(options.max_depth + 1), &options).is_err());
+    let objects = format!("{}1{}", "{\"a\":".repeat(options.max_depth + 1), "}".repeat(options.max_depth + 1));
+    assert!(parse_with_options(&objects, &options).is_err());
+}